@@ -0,0 +1,46 @@
+//! Compares walking `HKEY_CLASSES_ROOT` on one thread with `par_walk`. Run with
+//! `cargo bench --features rayon`.
+
+use std::time::{Duration, Instant};
+
+use rayon::iter::ParallelIterator;
+use registry::{Hive, OnError, Security, WalkOptions};
+
+const RUNS: u32 = 3;
+
+fn options() -> WalkOptions {
+    WalkOptions {
+        include_values: true,
+        on_error: OnError::Skip,
+        ..Default::default()
+    }
+}
+
+fn time<F: FnMut() -> usize>(name: &str, mut f: F) -> Duration {
+    let mut best = Duration::MAX;
+    let mut count = 0;
+
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        count = f();
+        best = best.min(start.elapsed());
+    }
+
+    println!("{:<10} {:>8} keys in {:?}", name, count, best);
+    best
+}
+
+fn main() {
+    let key = Hive::ClassesRoot.open("", Security::Read).unwrap();
+
+    let serial = time("walk", || key.walk(options()).filter(Result::is_ok).count());
+    let parallel = time("par_walk", || {
+        key.par_walk(options()).filter(Result::is_ok).count()
+    });
+
+    println!(
+        "speedup    {:.2}x on {} threads",
+        serial.as_secs_f64() / parallel.as_secs_f64(),
+        rayon::current_num_threads()
+    );
+}
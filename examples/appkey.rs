@@ -3,7 +3,7 @@ use std::path::Path;
 use registry::{Hive, RegKey, Security};
 
 fn main() -> Result<(), std::io::Error> {
-    let hive_key = Hive::load_file(
+    let hive_key = Hive::load_app_key(
         Path::new(r"C:\Users\Default\NTUSER.DAT"),
         Security::Read | Security::Write,
     )
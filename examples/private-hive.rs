@@ -22,7 +22,7 @@ fn main() -> Result<(), std::io::Error> {
 
     set_privilege(token, SE_RESTORE_NAME)?;
     set_privilege(token, SE_BACKUP_NAME)?;
-    let hive_key = Hive::load_file(
+    let hive_key = Hive::load_app_key(
         r"C:\Users\Default\NTUSER.DAT",
         Security::Read | Security::Write,
     )
@@ -0,0 +1,165 @@
+//! Derive macros for the [`registry`](https://docs.rs/registry) crate.
+//!
+//! These are re-exported by `registry` when its `derive` feature is enabled, and should be
+//! used from there.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Fields, LitStr};
+
+/// Generates `load(hive, path)` and `save(&self, hive, path)` methods that read and write
+/// each field of a struct as a value of a registry key.
+///
+/// Each field's type must implement both `registry::value::FromRegValue` and
+/// `registry::value::ToRegValue`. Values are named after their fields unless overridden:
+///
+/// ```ignore
+/// #[derive(RegistrySettings)]
+/// struct Settings {
+///     #[registry(name = "Port", default = 8080)]
+///     port: u32,
+///     #[registry(default)]
+///     user_name: String,
+/// }
+///
+/// let settings = Settings::load(Hive::CurrentUser, r"SOFTWARE\MyApp")?;
+/// settings.save(Hive::CurrentUser, r"SOFTWARE\MyApp")?;
+/// ```
+///
+/// A field with `default` takes its `Default` value, or the given expression, when its value
+/// or the whole key does not exist. Loading fails if a field without a default is missing.
+#[proc_macro_derive(RegistrySettings, attributes(registry))]
+pub fn derive_registry_settings(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+enum Default {
+    Trait,
+    Expr(Expr),
+}
+
+struct Field {
+    ident: syn::Ident,
+    name: String,
+    default: Option<Default>,
+}
+
+impl Field {
+    fn parse(field: &syn::Field) -> syn::Result<Field> {
+        let ident = field
+            .ident
+            .clone()
+            .ok_or_else(|| syn::Error::new(field.span(), "expected a named field"))?;
+        let mut name = ident.to_string();
+        let mut default = None;
+
+        for attr in field.attrs.iter().filter(|x| x.path().is_ident("registry")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                    Ok(())
+                } else if meta.path.is_ident("default") {
+                    default = Some(match meta.value() {
+                        Ok(value) => Default::Expr(value.parse()?),
+                        Err(_) => Default::Trait,
+                    });
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `name` or `default`"))
+                }
+            })?;
+        }
+
+        Ok(Field {
+            ident,
+            name,
+            default,
+        })
+    }
+
+    fn load(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        let name = &self.name;
+        let missing = match &self.default {
+            Some(Default::Trait) => quote!(::std::default::Default::default()),
+            Some(Default::Expr(expr)) => quote!(#expr),
+            None => quote!(return Err(::registry::__private::not_found(#name))),
+        };
+
+        quote! {
+            #ident: match ::registry::__private::load(key, #name)? {
+                Some(value) => value,
+                None => #missing,
+            }
+        }
+    }
+
+    fn save(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        let name = &self.name;
+        quote! {
+            key.set_value(#name, &self.#ident)?;
+        }
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(Field::parse)
+                .collect::<syn::Result<Vec<_>>>()?,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "RegistrySettings requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "RegistrySettings can only be derived for structs",
+            ))
+        }
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let loads = fields.iter().map(Field::load);
+    let saves = fields.iter().map(Field::save);
+
+    Ok(quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Loads these settings from the values of the key at `path` in `hive`.
+            pub fn load(
+                hive: ::registry::Hive,
+                path: &str,
+            ) -> ::std::result::Result<Self, ::registry::Error> {
+                let key = ::registry::__private::open(hive, path)?;
+                let key = key.as_ref();
+                Ok(Self {
+                    #(#loads,)*
+                })
+            }
+
+            /// Saves these settings as values of the key at `path` in `hive`, creating
+            /// the key if it does not exist.
+            pub fn save(
+                &self,
+                hive: ::registry::Hive,
+                path: &str,
+            ) -> ::std::result::Result<(), ::registry::Error> {
+                let key = ::registry::__private::create(hive, path)?;
+                #(#saves)*
+                Ok(())
+            }
+        }
+    })
+}
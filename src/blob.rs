@@ -0,0 +1,137 @@
+//! Storing arbitrary serializable types in `REG_BINARY` values, encoded with
+//! [`bincode`](https://docs.rs/bincode).
+//!
+//! Each value starts with a four byte header, the magic bytes `RSB` followed by the
+//! format version, so data written by other software or a later format is rejected
+//! rather than decoded as garbage:
+//!
+//! ```ignore
+//! regkey.set_serialized("State", &state)?;
+//! let state: State = regkey.get_deserialized("State")?;
+//! ```
+//!
+//! The encoding does not describe the type it holds, so changing the type, such as
+//! adding a field, makes existing values fail to decode. Treat such values as a cache
+//! that may need to be rebuilt.
+
+use std::convert::TryInto;
+
+use serde::{de::DeserializeOwned, Serialize};
+use utfx::U16CString;
+use winapi::um::winnt::REG_BINARY;
+
+use crate::{value, RegKey};
+
+const MAGIC: &[u8; 3] = b"RSB";
+const VERSION: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("A value error occurred.")]
+    Value(#[from] value::Error),
+
+    #[error("Value '{0}' does not hold serialized data")]
+    InvalidHeader(String),
+
+    #[error("Value '{0}' holds serialized data of unsupported version {1}")]
+    UnsupportedVersion(String, u8),
+
+    #[error("Failed to encode or decode serialized data")]
+    Bincode(#[from] bincode::Error),
+}
+
+impl RegKey {
+    /// Serializes `data` into a `REG_BINARY` value, after a version header.
+    pub fn set_serialized<S, T>(&self, value_name: S, data: &T) -> Result<(), Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: Serialize + ?Sized,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let mut buf = MAGIC.to_vec();
+        buf.push(VERSION);
+        bincode::serialize_into(&mut buf, data)?;
+        Ok(value::set_value_raw(
+            self.handle,
+            &value_name,
+            REG_BINARY,
+            &buf,
+        )?)
+    }
+
+    /// Deserializes a value written by `set_serialized`.
+    ///
+    /// Fails with `Error::InvalidHeader` if the value is not a `REG_BINARY` value
+    /// starting with the header, and with `Error::UnsupportedVersion` if it was written
+    /// in another format version.
+    pub fn get_deserialized<T, S>(&self, value_name: S) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let mut buf = Vec::new();
+        let ty = value::query_value_into(self.handle, &value_name, &mut buf)?;
+
+        if ty != REG_BINARY || buf.len() < 4 || &buf[..3] != MAGIC {
+            return Err(Error::InvalidHeader(value_name.to_string_lossy()));
+        }
+
+        if buf[3] != VERSION {
+            return Err(Error::UnsupportedVersion(
+                value_name.to_string_lossy(),
+                buf[3],
+            ));
+        }
+
+        Ok(bincode::deserialize(&buf[4..])?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::Error;
+    use crate::{Hive, Security};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct State {
+        name: String,
+        counts: Vec<u32>,
+        last: Option<u64>,
+    }
+
+    #[test]
+    fn round_trip() {
+        const KEY: &str = r"Test\registry-rust-crate-serialized";
+
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        let state = State {
+            name: "cache".into(),
+            counts: vec![1, 2, 3],
+            last: Some(4),
+        };
+
+        key.set_serialized("state", &state).unwrap();
+        assert_eq!(key.get_deserialized::<State, _>("state").unwrap(), state);
+        assert_eq!(&key.value_raw("state").unwrap().1[..4], b"RSB\x01");
+
+        key.set_value("plain", &vec![1u8, 2, 3, 4]).unwrap();
+        assert!(matches!(
+            key.get_deserialized::<State, _>("plain"),
+            Err(Error::InvalidHeader(_))
+        ));
+
+        key.set_value_raw("future", winapi::um::winnt::REG_BINARY, b"RSB\x02")
+            .unwrap();
+        assert!(matches!(
+            key.get_deserialized::<State, _>("future"),
+            Err(Error::UnsupportedVersion(_, 2))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+}
@@ -0,0 +1,819 @@
+//! Deserializing data structures from registry keys with [`serde`](https://serde.rs).
+//!
+//! This is the inverse of [`ser`](../ser/index.html): values of a key become struct fields
+//! or map entries, and subkeys become nested structs and maps. Names are matched to struct
+//! fields case-insensitively, as the registry does.
+//!
+//! Numbers are read from `REG_DWORD` and `REG_QWORD` values, with signed integers read
+//! back from their two's complement bits, and floats are parsed from strings. Sequences can
+//! be read from `REG_MULTI_SZ` or `REG_BINARY` values, and unit enum variants from strings.
+
+use std::{marker::PhantomData, vec};
+
+use serde::de::{
+    self,
+    value::{SeqDeserializer, StringDeserializer},
+    DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor,
+};
+
+use utfx::U16CString;
+
+use crate::{iter, key, value, Data, RegKey, Security};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("A key error occurred.")]
+    Key(#[from] key::Error),
+
+    #[error("A keys error occurred.")]
+    Keys(#[from] iter::keys::Error),
+
+    #[error("A value error occurred.")]
+    Value(#[from] value::Error),
+
+    #[error("A values error occurred.")]
+    Values(#[from] iter::values::Error),
+
+    #[error("Unknown value or subkey: '{0}'")]
+    Unknown(String),
+
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// How to handle struct fields with no matching value or subkey.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingFields {
+    /// Fail as serde usually does, unless the field is an `Option` or has a `#[serde(default)]`.
+    Error,
+    /// Use the zero value of the field's type: `false`, `0`, an empty string or sequence,
+    /// `None`, or a struct of zero values.
+    Zero,
+}
+
+/// How to handle values and subkeys that don't match any struct field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownValues {
+    Ignore,
+    /// Fail with `Error::Unknown`. The unnamed default value is always ignored.
+    Error,
+}
+
+/// Options for [`from_key_with_config`](fn.from_key_with_config.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub missing_fields: MissingFields,
+    pub unknown_values: UnknownValues,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            missing_fields: MissingFields::Error,
+            unknown_values: UnknownValues::Ignore,
+        }
+    }
+}
+
+/// Reads a struct or map from the values and subkeys of `key`.
+#[inline]
+pub fn from_key<T: DeserializeOwned>(key: &RegKey) -> Result<T, Error> {
+    from_key_with_config(key, Config::default())
+}
+
+/// Reads a struct or map from the values and subkeys of `key`, handling missing fields
+/// and unknown values as configured.
+pub fn from_key_with_config<T: DeserializeOwned>(key: &RegKey, config: Config) -> Result<T, Error> {
+    T::deserialize(Deserializer { key, config })
+}
+
+/// Iterates over the immediate subkeys of a key, deserializing each with
+/// [`from_key_with_config`](fn.from_key_with_config.html), created with
+/// [`RegKey::iter_as`](../struct.RegKey.html#method.iter_as).
+///
+/// The subkey names are read when the iterator is created, and each subkey is opened and
+/// read as it is reached. A subkey that cannot be read yields its name with the error, and
+/// iteration continues with the next.
+pub struct IterAs<'a, T> {
+    key: &'a RegKey,
+    names: vec::IntoIter<U16CString>,
+    config: Config,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> std::fmt::Debug for IterAs<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IterAs")
+            .field("key", &self.key)
+            .field("remaining", &self.names.len())
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for IterAs<'_, T> {
+    type Item = (U16CString, Result<T, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let name = self.names.next()?;
+        let result = self
+            .key
+            .open(&name, Security::Read)
+            .map_err(Error::from)
+            .and_then(|key| from_key_with_config(&key, self.config));
+        Some((name, result))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.names.size_hint()
+    }
+}
+
+impl<T: DeserializeOwned> ExactSizeIterator for IterAs<'_, T> {}
+
+impl RegKey {
+    /// Deserializes each immediate subkey of this key into `T`, yielding the subkey's name
+    /// with the result, such as to read every entry of an `Uninstall` key:
+    ///
+    /// ```ignore
+    /// #[derive(Deserialize)]
+    /// #[serde(rename_all = "PascalCase")]
+    /// struct App {
+    ///     display_name: Option<String>,
+    ///     display_version: Option<String>,
+    /// }
+    ///
+    /// for (name, app) in uninstall.iter_as::<App>()? {
+    ///     println!("{}: {:?}", name.to_string_lossy(), app?.display_name);
+    /// }
+    /// ```
+    #[inline]
+    pub fn iter_as<T: DeserializeOwned>(&self) -> Result<IterAs<'_, T>, Error> {
+        self.iter_as_with_config(Config::default())
+    }
+
+    /// Deserializes each immediate subkey like `iter_as`, handling missing fields and
+    /// unknown values as configured.
+    pub fn iter_as_with_config<T: DeserializeOwned>(
+        &self,
+        config: Config,
+    ) -> Result<IterAs<'_, T>, Error> {
+        let names = self
+            .keys()
+            .map(|x| Ok(x?.into_name()))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(IterAs {
+            key: self,
+            names: names.into_iter(),
+            config,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Deserializes the contents of a key.
+struct Deserializer<'a> {
+    key: &'a RegKey,
+    config: Config,
+}
+
+impl<'a> Deserializer<'a> {
+    fn entries(
+        &self,
+        fields: Option<&'static [&'static str]>,
+    ) -> Result<Vec<(String, Entry)>, Error> {
+        let mut entries = vec![];
+
+        for value in self.key.values() {
+            let (name, data) = value?.into_inner();
+            entries.push((name.to_string_lossy(), Entry::Value(data)));
+        }
+
+        for subkey in self.key.keys() {
+            let name = subkey?.name().to_string_lossy();
+            entries.push((name.clone(), Entry::Key(name)));
+        }
+
+        let fields = match fields {
+            Some(fields) => fields,
+            None => return Ok(entries),
+        };
+
+        let mut matched = Vec::with_capacity(entries.len());
+        for (name, entry) in entries {
+            match fields.iter().find(|x| x.eq_ignore_ascii_case(&name)) {
+                Some(field) => matched.push((field.to_string(), entry)),
+                None if name.is_empty() || self.config.unknown_values == UnknownValues::Ignore => {}
+                None => return Err(Error::Unknown(name)),
+            }
+        }
+
+        if self.config.missing_fields == MissingFields::Zero {
+            for field in fields {
+                if !matched.iter().any(|(name, _)| name == field) {
+                    matched.push((field.to_string(), Entry::Missing));
+                }
+            }
+        }
+
+        Ok(matched)
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for Deserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let entries = self.entries(None)?;
+        visitor.visit_map(MapAccess::new(Some(self.key), entries, self.config))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let entries = self.entries(Some(fields))?;
+        visitor.visit_map(MapAccess::new(Some(self.key), entries, self.config))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct enum identifier ignored_any
+    }
+}
+
+enum Entry {
+    Value(Data),
+    Key(String),
+    Missing,
+}
+
+/// Yields the entries of a key, opening subkeys as they are reached.
+struct MapAccess<'a> {
+    key: Option<&'a RegKey>,
+    entries: vec::IntoIter<(String, Entry)>,
+    pending: Option<Entry>,
+    config: Config,
+}
+
+impl<'a> MapAccess<'a> {
+    fn new(key: Option<&'a RegKey>, entries: Vec<(String, Entry)>, config: Config) -> Self {
+        MapAccess {
+            key,
+            entries: entries.into_iter(),
+            pending: None,
+            config,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((name, entry)) => {
+                self.pending = Some(entry);
+                let name: StringDeserializer<Error> = name.into_deserializer();
+                seed.deserialize(name).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.pending.take() {
+            Some(Entry::Value(data)) => seed.deserialize(ValueDeserializer(data)),
+            Some(Entry::Key(name)) => {
+                let key = self
+                    .key
+                    .ok_or_else(|| Error::Custom("subkey without a parent key".into()))?;
+                let subkey = key.open(name, Security::Read)?;
+                seed.deserialize(Deserializer {
+                    key: &subkey,
+                    config: self.config,
+                })
+            }
+            Some(Entry::Missing) => seed.deserialize(ZeroDeserializer),
+            None => Err(Error::Custom(
+                "next_value_seed called before next_key_seed".into(),
+            )),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Deserializes the data of a single value.
+struct ValueDeserializer(Data);
+
+impl ValueDeserializer {
+    fn invalid(&self, exp: &dyn de::Expected) -> Error {
+        let unexp = match &self.0 {
+            Data::None(_) => de::Unexpected::Unit,
+            Data::U32(x) | Data::U32BE(x) => de::Unexpected::Unsigned(*x as u64),
+            Data::U64(x) => de::Unexpected::Unsigned(*x),
+            Data::MultiString(_) => de::Unexpected::Seq,
+            Data::String(_) | Data::ExpandString(_) => de::Unexpected::Other("a string value"),
+            _ => de::Unexpected::Other("a binary value"),
+        };
+        de::Error::invalid_type(unexp, exp)
+    }
+
+    fn number(&self) -> Option<u64> {
+        match self.0 {
+            Data::U32(x) | Data::U32BE(x) => Some(x as u64),
+            Data::U64(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Reads a signed integer, treating a `REG_DWORD` as an `i32`.
+    fn signed(&self) -> Option<i64> {
+        match self.0 {
+            Data::U32(x) | Data::U32BE(x) => Some(x as i32 as i64),
+            Data::U64(x) => Some(x as i64),
+            _ => None,
+        }
+    }
+
+    fn into_string(self) -> Result<String, Error> {
+        match self.0 {
+            Data::String(s) | Data::ExpandString(s) => {
+                Ok(s.to_string().map_err(value::Error::from)?)
+            }
+            _ => Err(self.invalid(&"a string value")),
+        }
+    }
+}
+
+macro_rules! deserialize_signed {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.signed() {
+                    Some(x) => visitor.visit_i64(x),
+                    None => Err(self.invalid(&visitor)),
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! deserialize_unsigned {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.number() {
+                    Some(x) => visitor.visit_u64(x),
+                    None => Err(self.invalid(&visitor)),
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! deserialize_float {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                if let Some(x) = self.signed() {
+                    return visitor.visit_f64(x as f64);
+                }
+                let s = self.into_string()?;
+                match s.trim().parse::<f64>() {
+                    Ok(x) => visitor.visit_f64(x),
+                    Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(&s), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Data::None(_) => visitor.visit_unit(),
+            Data::String(s) | Data::ExpandString(s) => {
+                visitor.visit_string(s.to_string().map_err(value::Error::from)?)
+            }
+            Data::U32(x) | Data::U32BE(x) => visitor.visit_u32(x),
+            Data::U64(x) => visitor.visit_u64(x),
+            Data::MultiString(x) => {
+                ValueDeserializer(Data::MultiString(x)).deserialize_seq(visitor)
+            }
+            Data::Binary(x)
+            | Data::Link(x)
+            | Data::ResourceList(x)
+            | Data::FullResourceDescriptor(x)
+            | Data::ResourceRequirementsList(x) => visitor.visit_byte_buf(x),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.number() {
+            Some(x) => visitor.visit_bool(x != 0),
+            None => Err(self.invalid(&visitor)),
+        }
+    }
+
+    deserialize_signed! { deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 }
+    deserialize_unsigned! { deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 }
+    deserialize_float! { deserialize_f32 deserialize_f64 }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.into_string()?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.into_string()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_string(self.into_string()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Data::String(_) | Data::ExpandString(_) | Data::MultiString(_) => {
+                Err(self.invalid(&visitor))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Data::MultiString(strings) => {
+                let strings = strings
+                    .into_iter()
+                    .map(|s| s.to_string())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(value::Error::from)?;
+                visitor.visit_seq(SeqDeserializer::new(strings.into_iter()))
+            }
+            Data::Binary(bytes) => visitor.visit_seq(SeqDeserializer::new(bytes.into_iter())),
+            _ => Err(self.invalid(&visitor)),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let variant: StringDeserializer<Error> = self.into_string()?.into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 unit unit_struct map struct identifier ignored_any
+    }
+}
+
+/// Deserializes the zero value of a type, for missing fields.
+struct ZeroDeserializer;
+
+impl<'de> de::Deserializer<'de> for ZeroDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bool(false)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i64(0)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i64(0)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i64(0)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_i64(0)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u64(0)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u64(0)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u64(0)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_u64(0)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f64(0.0)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_f64(0.0)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str("")
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str("")
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bytes(&[])
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bytes(&[])
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_none()
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(SeqDeserializer::new(std::iter::empty::<u8>()))
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(MapAccess::new(None, vec![], Config::default()))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let entries = fields
+            .iter()
+            .map(|x| (x.to_string(), Entry::Missing))
+            .collect();
+        visitor.visit_map(MapAccess::new(None, entries, Config::default()))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char unit unit_struct tuple tuple_struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hive;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Window {
+        width: u32,
+        height: u32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Theme {
+        Dark,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        enabled: bool,
+        offset: i32,
+        size: u64,
+        ratio: f64,
+        theme: Theme,
+        recent: Vec<String>,
+        blob: Vec<u8>,
+        missing: Option<u32>,
+        window: Window,
+        extra: BTreeMap<String, u32>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Sparse {
+        name: String,
+        count: u32,
+        window: Window,
+    }
+
+    fn create(key_path: &str) -> RegKey {
+        let key = Hive::CurrentUser
+            .create(key_path, Security::AllAccess)
+            .unwrap();
+        key.set_value("Name", "registry").unwrap();
+        key.set_value("enabled", &1u32).unwrap();
+        key.set_value("offset", &u32::MAX).unwrap();
+        key.set_value("size", &(1u64 << 40)).unwrap();
+        key.set_value("ratio", "0.5").unwrap();
+        key.set_value("theme", "Dark").unwrap();
+        key.set_value("recent", &["a", "b"][..]).unwrap();
+        key.set_value("blob", &vec![1u8, 2, 3]).unwrap();
+        let window = key.create("window", Security::AllAccess).unwrap();
+        window.set_value("width", &640u32).unwrap();
+        window.set_value("height", &480u32).unwrap();
+        let extra = key.create("extra", Security::AllAccess).unwrap();
+        extra.set_value("Answer", &42u32).unwrap();
+        key
+    }
+
+    #[test]
+    fn deserialize_struct() {
+        const KEY: &str = r"Test\registry-rust-crate-deserialize";
+
+        let key = create(KEY);
+        let config: Config = from_key(&key).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "registry".into(),
+                enabled: true,
+                offset: -1,
+                size: 1 << 40,
+                ratio: 0.5,
+                theme: Theme::Dark,
+                recent: vec!["a".into(), "b".into()],
+                blob: vec![1, 2, 3],
+                missing: None,
+                window: Window {
+                    width: 640,
+                    height: 480,
+                },
+                extra: vec![("Answer".to_string(), 42)].into_iter().collect(),
+            }
+        );
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn deserialize_config() {
+        const KEY: &str = r"Test\registry-rust-crate-deserialize-config";
+
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        key.set_value("name", "registry").unwrap();
+        key.set_value("unknown", &1u32).unwrap();
+
+        assert!(from_key::<Sparse>(&key).is_err());
+
+        let zero = super::Config {
+            missing_fields: MissingFields::Zero,
+            ..Default::default()
+        };
+        let sparse: Sparse = from_key_with_config(&key, zero).unwrap();
+        assert_eq!(
+            sparse,
+            Sparse {
+                name: "registry".into(),
+                count: 0,
+                window: Window {
+                    width: 0,
+                    height: 0,
+                },
+            }
+        );
+
+        let strict = super::Config {
+            unknown_values: UnknownValues::Error,
+            ..zero
+        };
+        match from_key_with_config::<Sparse>(&key, strict) {
+            Err(Error::Unknown(name)) => assert_eq!(name, "unknown"),
+            x => panic!("{:?}", x),
+        }
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn iter_as() {
+        const KEY: &str = r"Test\registry-rust-crate-iter-as";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        for (name, width) in &[("a", 1u32), ("b", 2)] {
+            let subkey = key.create(*name, Security::AllAccess).unwrap();
+            subkey.set_value("width", width).unwrap();
+            subkey.set_value("height", &10u32).unwrap();
+        }
+        key.create("broken", Security::AllAccess).unwrap();
+
+        let mut windows = key
+            .iter_as::<Window>()
+            .unwrap()
+            .map(|(name, x)| (name.to_string_lossy(), x.ok()))
+            .collect::<Vec<_>>();
+        windows.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            windows,
+            [
+                (
+                    "a".to_string(),
+                    Some(Window {
+                        width: 1,
+                        height: 10
+                    })
+                ),
+                (
+                    "b".to_string(),
+                    Some(Window {
+                        width: 2,
+                        height: 10
+                    })
+                ),
+                ("broken".to_string(), None),
+            ]
+        );
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+//! Support code for `#[derive(RegistrySettings)]`. Not public API.
+
+use crate::{
+    value::{self, FromRegValue},
+    Hive, RegKey, Security,
+};
+
+pub fn open(hive: Hive, path: &str) -> Result<Option<RegKey>, crate::Error> {
+    match hive.open(path, Security::Read) {
+        Ok(key) => Ok(Some(key)),
+        Err(e) if e.is_not_found() => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn create(hive: Hive, path: &str) -> Result<RegKey, crate::Error> {
+    Ok(hive.create(path, Security::Write)?)
+}
+
+pub fn load<T: FromRegValue>(key: Option<&RegKey>, name: &str) -> Result<Option<T>, crate::Error> {
+    let key = match key {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+
+    match key.get_value(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.is_not_found() => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn not_found(name: &str) -> crate::Error {
+    value::Error::not_found(name).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Hive, RegistrySettings, Security};
+
+    #[derive(Debug, PartialEq, RegistrySettings)]
+    struct Settings {
+        #[registry(name = "Port", default = 8080)]
+        port: u32,
+        #[registry(default)]
+        user_name: String,
+        enabled: bool,
+    }
+
+    #[test]
+    fn load_and_save() {
+        const KEY: &str = r"Test\registry-rust-crate-settings";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        assert!(Settings::load(Hive::CurrentUser, KEY).is_err());
+
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        key.set_value("enabled", &true).unwrap();
+        assert_eq!(
+            Settings::load(Hive::CurrentUser, KEY).unwrap(),
+            Settings {
+                port: 8080,
+                user_name: String::new(),
+                enabled: true,
+            }
+        );
+
+        let settings = Settings {
+            port: 1234,
+            user_name: "user".into(),
+            enabled: false,
+        };
+        settings.save(Hive::CurrentUser, KEY).unwrap();
+        assert_eq!(key.value("Port").unwrap(), crate::Data::U32(1234));
+        assert_eq!(Settings::load(Hive::CurrentUser, KEY).unwrap(), settings);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+}
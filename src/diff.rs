@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+
+use utfx::U16CString;
+
+use crate::{Data, Error, RegKey, RegPath, Security};
+
+/// A single difference between two key trees, found by [`diff`](fn.diff.html).
+///
+/// Paths are relative to the keys being compared, and are empty for the keys
+/// themselves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A subkey only exists in the new tree. Its contents are not listed separately.
+    AddedKey(RegPath),
+    /// A subkey only exists in the old tree. Its contents are not listed separately.
+    RemovedKey(RegPath),
+    AddedValue {
+        path: RegPath,
+        name: U16CString,
+        data: Data,
+    },
+    RemovedValue {
+        path: RegPath,
+        name: U16CString,
+        data: Data,
+    },
+    ChangedValue {
+        path: RegPath,
+        name: U16CString,
+        old: Data,
+        new: Data,
+    },
+}
+
+/// The differences between two key trees, returned by [`diff`](fn.diff.html).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+}
+
+impl Diff {
+    /// Whether the two trees had identical values and subkeys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// How [`RegKey::merge_from`](struct.RegKey.html#method.merge_from) handles values
+/// that exist in both trees with different data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Replace the destination's data with the source's.
+    Overwrite,
+    /// Keep the destination's data.
+    Skip,
+    /// Fail with `Error::Conflict` before anything is merged.
+    Error,
+}
+
+/// Compares the values and subkeys of two keys recursively, listing the changes needed
+/// to turn the tree of `old` into that of `new`.
+///
+/// Key and value names are compared case-insensitively, as the registry does.
+pub fn diff(old: &RegKey, new: &RegKey) -> Result<Diff, Error> {
+    let mut diff = Diff::default();
+    diff_keys(old, new, &RegPath::default(), &mut diff.changes)?;
+    Ok(diff)
+}
+
+/// Copies the values and subkeys of `src` that are missing from `dest` into it, and
+/// resolves values that differ with the given policy. Nothing is deleted from `dest`.
+pub(crate) fn merge(dest: &RegKey, src: &RegKey, policy: MergePolicy) -> Result<(), Error> {
+    let changes = diff(dest, src)?.changes;
+
+    if policy == MergePolicy::Error {
+        let conflict = changes
+            .iter()
+            .find(|x| matches!(x, Change::ChangedValue { .. }));
+
+        if let Some(conflict) = conflict {
+            return Err(Error::Conflict(Box::new(conflict.clone())));
+        }
+    }
+
+    for change in changes {
+        match change {
+            Change::AddedKey(path) => {
+                let target = dest.create(&path, Security::AllAccess)?;
+                src.open(&path, Security::Read)?.copy_tree_to(&target)?;
+            }
+            Change::AddedValue { path, name, data } => dest.set_value_at(&path, name, &data)?,
+            Change::ChangedValue {
+                path, name, new, ..
+            } if policy == MergePolicy::Overwrite => dest.set_value_at(&path, name, &new)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn diff_keys(
+    old: &RegKey,
+    new: &RegKey,
+    path: &RegPath,
+    changes: &mut Vec<Change>,
+) -> Result<(), Error> {
+    let mut new_values = values(new)?;
+
+    for (key, (name, data)) in values(old)? {
+        match new_values.remove(&key) {
+            Some((_, new)) if new == data => {}
+            Some((_, new)) => changes.push(Change::ChangedValue {
+                path: path.clone(),
+                name,
+                old: data,
+                new,
+            }),
+            None => changes.push(Change::RemovedValue {
+                path: path.clone(),
+                name,
+                data,
+            }),
+        }
+    }
+
+    for (name, data) in new_values.into_values() {
+        changes.push(Change::AddedValue {
+            path: path.clone(),
+            name,
+            data,
+        });
+    }
+
+    let mut new_keys = subkeys(new)?;
+
+    for (key, name) in subkeys(old)? {
+        let child = path.join(name.to_string_lossy());
+
+        match new_keys.remove(&key) {
+            Some(new_name) => {
+                let old = old.open(&name, Security::Read)?;
+                let new = new.open(&new_name, Security::Read)?;
+                diff_keys(&old, &new, &child, changes)?;
+            }
+            None => changes.push(Change::RemovedKey(child)),
+        }
+    }
+
+    for name in new_keys.into_values() {
+        changes.push(Change::AddedKey(path.join(name.to_string_lossy())));
+    }
+
+    Ok(())
+}
+
+/// The values of a key, by lowercased name.
+fn values(key: &RegKey) -> Result<BTreeMap<String, (U16CString, Data)>, Error> {
+    key.values()
+        .map(|value| {
+            let (name, data) = value?.into_inner();
+            Ok((name.to_string_lossy().to_lowercase(), (name, data)))
+        })
+        .collect()
+}
+
+/// The names of a key's subkeys, by lowercased name.
+fn subkeys(key: &RegKey) -> Result<BTreeMap<String, U16CString>, Error> {
+    key.keys()
+        .map(|key| {
+            let name = key?.name().to_ucstring();
+            Ok((name.to_string_lossy().to_lowercase(), name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hive;
+
+    #[test]
+    fn diff_trees() {
+        const KEY: &str = r"Test\registry-rust-crate-diff";
+
+        let root = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        let old = root.create("old", Security::AllAccess).unwrap();
+        let new = root.create("new", Security::AllAccess).unwrap();
+
+        for key in &[&old, &new] {
+            key.set_value("same", &Data::U32(1)).unwrap();
+            key.create(r"shared\nested", Security::AllAccess).unwrap();
+        }
+        old.set_value("changed", &Data::U32(1)).unwrap();
+        new.set_value("CHANGED", &Data::U32(2)).unwrap();
+        old.set_value("removed", &Data::U32(1)).unwrap();
+        new.open(r"shared\nested", Security::AllAccess)
+            .unwrap()
+            .set_value("added", &Data::U64(1))
+            .unwrap();
+        old.create("gone", Security::AllAccess).unwrap();
+        new.create("fresh", Security::AllAccess).unwrap();
+
+        let changes = diff(&old, &new).unwrap().changes;
+        assert_eq!(changes.len(), 5, "{:?}", changes);
+        assert!(changes.contains(&Change::ChangedValue {
+            path: RegPath::default(),
+            name: U16CString::from_str("changed").unwrap(),
+            old: Data::U32(1),
+            new: Data::U32(2),
+        }));
+        assert!(changes.contains(&Change::RemovedValue {
+            path: RegPath::default(),
+            name: U16CString::from_str("removed").unwrap(),
+            data: Data::U32(1),
+        }));
+        assert!(changes.contains(&Change::AddedValue {
+            path: RegPath::new(r"shared\nested"),
+            name: U16CString::from_str("added").unwrap(),
+            data: Data::U64(1),
+        }));
+        assert!(changes.contains(&Change::RemovedKey(RegPath::new("gone"))));
+        assert!(changes.contains(&Change::AddedKey(RegPath::new("fresh"))));
+
+        assert!(diff(&old, &old).unwrap().is_empty());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn merge_trees() {
+        const KEY: &str = r"Test\registry-rust-crate-merge";
+
+        let root = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        let dest = root.create("dest", Security::AllAccess).unwrap();
+        let src = root.create("src", Security::AllAccess).unwrap();
+        dest.set_value("kept", &Data::U32(1)).unwrap();
+        dest.set_value("conflict", &Data::U32(1)).unwrap();
+        src.set_value("conflict", &Data::U32(2)).unwrap();
+        src.create(r"added\nested", Security::AllAccess)
+            .unwrap()
+            .set_value("value", &Data::U64(3))
+            .unwrap();
+
+        let result = dest.merge_from(&src, MergePolicy::Error);
+        assert!(matches!(result, Err(Error::Conflict(_))));
+        assert!(!dest.subkey_exists("added").unwrap());
+
+        dest.merge_from(&src, MergePolicy::Skip).unwrap();
+        assert_eq!(dest.value("conflict").unwrap(), Data::U32(1));
+        let nested = dest.open(r"added\nested", Security::Read).unwrap();
+        assert_eq!(nested.value("value").unwrap(), Data::U64(3));
+
+        dest.merge_from(&src, MergePolicy::Overwrite).unwrap();
+        assert_eq!(dest.value("conflict").unwrap(), Data::U32(2));
+        assert_eq!(dest.value("kept").unwrap(), Data::U32(1));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+}
@@ -1,14 +1,25 @@
-use std::{convert::TryInto, fmt::Display};
+use std::{convert::TryInto, fmt::Display, str::FromStr};
 
 use utfx::{U16CStr, U16CString};
 use winapi::um::winreg::{
     HKEY_CLASSES_ROOT, HKEY_CURRENT_CONFIG, HKEY_CURRENT_USER, HKEY_CURRENT_USER_LOCAL_SETTINGS,
-    HKEY_LOCAL_MACHINE, HKEY_PERFORMANCE_DATA, HKEY_USERS,
+    HKEY_DYN_DATA, HKEY_LOCAL_MACHINE, HKEY_PERFORMANCE_DATA, HKEY_PERFORMANCE_NLSTEXT,
+    HKEY_PERFORMANCE_TEXT, HKEY_USERS,
+};
+use winapi::{
+    shared::{minwindef::HKEY, winerror::ERROR_INVALID_HANDLE},
+    um::{
+        winnt::HANDLE,
+        winreg::{
+            RegLoadAppKeyW, RegLoadKeyW, RegOverridePredefKey, RegReplaceKeyW, RegUnLoadKeyW,
+        },
+    },
 };
-use winapi::{shared::minwindef::HKEY, um::winreg::RegLoadAppKeyW};
 
-use crate::key::{self, Error};
-use crate::{sec::Security, RegKey};
+use crate::iter::UserProfiles;
+use crate::key::{self, Disposition, Error, SaveFormat};
+use crate::sec::{CreateOptions, Options, ReadOptions, Security, TypeFilter, View};
+use crate::{value, RegKey, Transaction};
 
 /// All hives of the Windows Registry. Start here to get to a registry key.
 #[derive(Debug, Copy, Clone)]
@@ -18,64 +29,341 @@ pub enum Hive {
     CurrentConfig,
     CurrentUser,
     CurrentUserLocalSettings,
+    /// Legacy dynamic data, only present on Windows 9x.
+    DynData,
     LocalMachine,
     PerformanceData,
+    /// Performance counter names and help text in US English.
+    PerformanceText,
+    /// Performance counter names and help text in the system language.
+    PerformanceNlsText,
     Users,
 
     #[doc(hidden)]
     Application,
+
+    #[doc(hidden)]
+    Unknown,
 }
 
 impl Hive {
+    /// The predefined handle of this hive, or `None` for `Hive::Application` and
+    /// `Hive::Unknown`, which have none.
     #[inline]
-    fn as_hkey(&self) -> HKEY {
-        match self {
+    pub(crate) fn as_hkey(&self) -> Option<HKEY> {
+        Some(match self {
             Hive::ClassesRoot => HKEY_CLASSES_ROOT,
             Hive::CurrentConfig => HKEY_CURRENT_CONFIG,
             Hive::CurrentUser => HKEY_CURRENT_USER,
             Hive::CurrentUserLocalSettings => HKEY_CURRENT_USER_LOCAL_SETTINGS,
+            Hive::DynData => HKEY_DYN_DATA,
             Hive::LocalMachine => HKEY_LOCAL_MACHINE,
             Hive::PerformanceData => HKEY_PERFORMANCE_DATA,
+            Hive::PerformanceText => HKEY_PERFORMANCE_TEXT,
+            Hive::PerformanceNlsText => HKEY_PERFORMANCE_NLSTEXT,
             Hive::Users => HKEY_USERS,
-            Hive::Application => panic!("as_hkey must not be called for Application hives"),
-        }
+            Hive::Application | Hive::Unknown => return None,
+        })
+    }
+
+    /// The predefined handle of this hive, failing with `ERROR_INVALID_HANDLE` for hives
+    /// that have none.
+    #[inline]
+    fn hkey(&self) -> Result<HKEY, Error> {
+        self.as_hkey()
+            .ok_or_else(|| Error::from_code(ERROR_INVALID_HANDLE as i32, self.to_string()))
     }
 
     #[inline]
     pub fn open<P>(&self, path: P, sec: Security) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        self.open_with_options(path, sec, Options::empty())
+    }
+
+    /// Returns an iterator over the user profiles currently loaded under `HKEY_USERS`.
+    #[inline]
+    pub fn user_profiles() -> Result<UserProfiles, crate::Error> {
+        UserProfiles::new()
+    }
+
+    /// Opens a key, only creating it if it does not exist.
+    ///
+    /// Unlike `create`, this does not need `Security::CreateSubKey` access to the
+    /// parent key when the key already exists.
+    #[inline]
+    pub fn open_or_create<P>(&self, path: P, sec: Security) -> Result<RegKey, Error>
     where
         P: TryInto<U16CString>,
         P::Error: Into<Error>,
     {
         let path = path.try_into().map_err(Into::into)?;
-        key::open_hkey(self.as_hkey(), &path, sec).map(|handle| RegKey {
-            hive: *self,
-            handle,
-            path,
-        })
+        match self.open(&path, sec) {
+            Err(e) if e.is_not_found() => self.create(path, sec),
+            result => result,
+        }
+    }
+
+    /// Returns whether a key exists at the given path.
+    ///
+    /// Errors other than the key not being found, such as being denied access, are
+    /// returned as errors.
+    #[inline]
+    pub fn exists<P>(&self, path: P) -> Result<bool, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        match self.open(path, Security::QueryValue) {
+            Ok(_) => Ok(true),
+            Err(e) if e.is_not_found() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Opens a key with the given options, such as `Options::BackupRestore`.
+    #[inline]
+    pub fn open_with_options<P>(
+        &self,
+        path: P,
+        sec: Security,
+        options: Options,
+    ) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        key::open_hkey(self.hkey()?, &path, sec, options)
+            .map(|handle| RegKey::new(*self, handle, path, View::from_security(sec), false))
+    }
+
+    /// Opens a key in the given WOW64 view of the registry. Subkeys opened from the
+    /// returned key inherit the view.
+    #[inline]
+    pub fn open_with_view<P>(&self, path: P, sec: Security, view: View) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        self.open(path, view.apply(sec))
+    }
+
+    /// Creates or opens a key in the given WOW64 view of the registry. Subkeys opened
+    /// from the returned key inherit the view.
+    #[inline]
+    pub fn create_with_view<P>(&self, path: P, sec: Security, view: View) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        self.create(path, view.apply(sec))
+    }
+
+    /// Connects to this hive on a remote machine, such as `\\server`.
+    ///
+    /// Only `LocalMachine`, `Users` and `PerformanceData` may be opened remotely.
+    /// The returned key is the root of the remote hive, and can be used with the
+    /// normal `RegKey` API.
+    #[inline]
+    pub fn connect<M>(&self, machine_name: M) -> Result<RegKey, Error>
+    where
+        M: TryInto<U16CString>,
+        M::Error: Into<Error>,
+    {
+        let machine_name = machine_name.try_into().map_err(Into::into)?;
+        key::connect_hkey(self.hkey()?, &machine_name)
+            .map(|handle| RegKey::new(*self, handle, U16CString::default(), View::Default, true))
+    }
+
+    /// Opens the `HKEY_CURRENT_USER` hive of the user the current thread is
+    /// impersonating, using `RegOpenCurrentUser`.
+    ///
+    /// Services impersonating a client should use this rather than `Hive::CurrentUser`,
+    /// which is cached per process and refers to the profile of the process's own user.
+    #[inline]
+    pub fn open_current_user(sec: Security) -> Result<RegKey, Error> {
+        RegKey::open_current_user(sec)
+    }
+
+    /// Opens the merged `HKEY_CLASSES_ROOT` view of the user identified by the given
+    /// access token, using `RegOpenUserClassesRoot`.
+    ///
+    /// This allows a process running as another user, such as an installer running as
+    /// `SYSTEM`, to register per-user file associations and classes. The user's profile
+    /// must already be loaded.
+    ///
+    /// # Safety
+    ///
+    /// `token` must be a valid access token handle opened with `TOKEN_QUERY`,
+    /// `TOKEN_DUPLICATE` and `TOKEN_IMPERSONATE` access.
+    #[inline]
+    pub unsafe fn open_user_classes_root(token: HANDLE, sec: Security) -> Result<RegKey, Error> {
+        RegKey::open_user_classes_root(token, sec)
+    }
+
+    /// Redirects this hive to the given key for the current process, using
+    /// `RegOverridePredefKey`.
+    ///
+    /// This is intended for tests, so that code under test writing to a hive such as
+    /// `Hive::LocalMachine` only modifies a scratch key. The override applies to the
+    /// whole process until [`Hive::clear_override`](#method.clear_override) is called.
+    #[inline]
+    pub fn override_with(&self, regkey: &RegKey) -> Result<(), Error> {
+        let result = unsafe { RegOverridePredefKey(self.hkey()?, regkey.handle) };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        Err(Error::from_code(result, self.to_string()))
+    }
+
+    /// Restores the default mapping of this hive after a call to
+    /// [`Hive::override_with`](#method.override_with).
+    #[inline]
+    pub fn clear_override(&self) -> Result<(), Error> {
+        let result = unsafe { RegOverridePredefKey(self.hkey()?, std::ptr::null_mut()) };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        Err(Error::from_code(result, self.to_string()))
     }
 
     #[inline]
     pub fn write<P>(&self, file_path: P) -> Result<(), Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        self.write_with_format(file_path, SaveFormat::NoCompression)
+    }
+
+    /// Saves this hive to a hive file in the given format, using `RegSaveKeyExW`.
+    /// The file must not already exist.
+    ///
+    /// The calling process must hold `SeBackupPrivilege`.
+    #[inline]
+    pub fn write_with_format<P>(&self, file_path: P, format: SaveFormat) -> Result<(), Error>
     where
         P: TryInto<U16CString>,
         P::Error: Into<Error>,
     {
         let path = file_path.try_into().map_err(Into::into)?;
-        key::save_hkey(self.as_hkey(), &path)
+        key::save_hkey(self.hkey()?, &path, format)
     }
 
     #[inline]
     pub fn create<P>(&self, path: P, sec: Security) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        self.create_with_options(path, sec, Options::empty())
+    }
+
+    /// Creates or opens a key with the given options, such as `Options::Volatile` or
+    /// `Options::BackupRestore`.
+    #[inline]
+    pub fn create_with_options<P>(
+        &self,
+        path: P,
+        sec: Security,
+        options: Options,
+    ) -> Result<RegKey, Error>
     where
         P: TryInto<U16CString>,
         P::Error: Into<Error>,
     {
         let path = path.try_into().map_err(Into::into)?;
-        key::create_hkey(self.as_hkey(), &path, sec).map(|handle| RegKey {
-            hive: *self,
-            handle,
-            path,
+        key::create_hkey(self.hkey()?, &path, sec, options)
+            .map(|(handle, _)| RegKey::new(*self, handle, path, View::from_security(sec), false))
+    }
+
+    /// Reads a value of the key at the given path, using `RegGetValueW`, without
+    /// opening the key first.
+    #[inline]
+    pub fn get_value<P, S>(&self, path: P, value_name: S) -> Result<value::Data, value::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<value::Error>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_value_with_filter(path, value_name, TypeFilter::Any)
+    }
+
+    /// Reads a value of the key at the given path, failing with
+    /// `value::Error::TypeMismatch` if it is not one of the types in the filter.
+    pub fn get_value_with_filter<P, S>(
+        &self,
+        path: P,
+        value_name: S,
+        filter: TypeFilter,
+    ) -> Result<value::Data, value::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<value::Error>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_value_with_options(path, value_name, &filter.into())
+    }
+
+    /// Reads a value of the key at the given path, restricting its type and expanding
+    /// environment variables as given in the options.
+    pub fn get_value_with_options<P, S>(
+        &self,
+        path: P,
+        value_name: S,
+        opts: &ReadOptions,
+    ) -> Result<value::Data, value::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<value::Error>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let hkey = self.as_hkey().ok_or_else(|| {
+            value::Error::from_code(ERROR_INVALID_HANDLE as i32, self.to_string())
+        })?;
+        value::get_value(hkey, &path, &value_name, opts.rrf_flags())
+    }
+
+    /// Creates or opens a key, creating any missing intermediate keys with the
+    /// separate access rights and security descriptor given in the options.
+    pub fn create_with<P>(&self, path: P, opts: &CreateOptions) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let view = View::from_security(opts.security);
+        key::create_hkey_with(self.hkey()?, &path, opts, view)
+            .map(|(handle, _)| RegKey::new(*self, handle, path, view, false))
+    }
+
+    /// Creates or opens a key, also returning whether it was newly created.
+    #[inline]
+    pub fn create_with_disposition<P>(
+        &self,
+        path: P,
+        sec: Security,
+    ) -> Result<(RegKey, Disposition), Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        key::create_hkey(self.hkey()?, &path, sec, Options::empty()).map(|(handle, disposition)| {
+            let regkey = RegKey::new(*self, handle, path, View::from_security(sec), false);
+            (regkey, disposition)
         })
     }
 
@@ -86,9 +374,169 @@ impl Hive {
         P::Error: Into<Error>,
     {
         let path = path.try_into().map_err(Into::into)?;
-        key::delete_hkey(self.as_hkey(), path, is_recursive)
+        key::delete_hkey(self.hkey()?, path, View::Default, is_recursive)
+    }
+
+    /// Deletes a key and all of its subkeys one at a time, calling `progress` with the
+    /// path of each key as it is deleted. See [`RegKey::delete_with_progress`](struct.RegKey.html#method.delete_with_progress).
+    pub fn delete_with_progress<P, F>(&self, path: P, mut progress: F) -> Result<bool, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+        F: FnMut(&U16CStr) -> bool,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        key::delete_hkey_with_progress(self.hkey()?, &path, View::Default, &mut progress)
+    }
+
+    /// Opens a key as part of the given transaction.
+    #[inline]
+    pub fn open_transacted<P>(
+        &self,
+        path: P,
+        sec: Security,
+        transaction: &Transaction,
+    ) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        key::open_hkey_transacted(self.hkey()?, &path, sec, transaction)
+            .map(|handle| RegKey::new(*self, handle, path, View::from_security(sec), false))
+    }
+
+    /// Creates or opens a key as part of the given transaction.
+    #[inline]
+    pub fn create_transacted<P>(
+        &self,
+        path: P,
+        sec: Security,
+        transaction: &Transaction,
+    ) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        key::create_hkey_transacted(self.hkey()?, &path, sec, transaction)
+            .map(|handle| RegKey::new(*self, handle, path, View::from_security(sec), false))
+    }
+
+    /// Deletes a key as part of the given transaction.
+    #[inline]
+    pub fn delete_transacted<P>(
+        &self,
+        path: P,
+        is_recursive: bool,
+        transaction: &Transaction,
+    ) -> Result<(), Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        key::delete_hkey_transacted(self.hkey()?, path, View::Default, is_recursive, transaction)
+    }
+
+    /// Loads an application hive with `RegLoadAppKeyW`, returning its root key.
+    ///
+    /// Application hives are private to the calling process and do not require
+    /// `SeBackupPrivilege` or `SeRestorePrivilege`. If no file exists at the given
+    /// path, a new empty hive is created there. The hive remains mounted until the
+    /// returned `RegKey` and all keys opened beneath it are dropped.
+    #[inline]
+    pub fn load_app_key<P: AsRef<std::path::Path>>(
+        file_path: P,
+        sec: Security,
+    ) -> Result<RegKey, Error> {
+        let path = U16CString::from_os_str(file_path.as_ref().as_os_str())?;
+        load_appkey(&path, sec)
+            .map(|handle| {
+                RegKey::new(
+                    Hive::Application,
+                    handle,
+                    U16CString::default(),
+                    View::from_security(sec),
+                    false,
+                )
+            })
+            .map_err(|e| Error::from_io(e, path.to_string_lossy()))
+    }
+
+    /// Loads a hive file as the given subkey of this hive, using `RegLoadKeyW`.
+    ///
+    /// Only `Hive::LocalMachine` and `Hive::Users` can have hives loaded into them, and
+    /// the calling process must hold `SeBackupPrivilege` and `SeRestorePrivilege`. The
+    /// hive is unloaded when the returned guard is dropped, provided no other keys
+    /// within it are still open.
+    pub fn load<P, F>(&self, path: P, file_path: F, sec: Security) -> Result<LoadedHive, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+        F: AsRef<std::path::Path>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let file_path = U16CString::from_os_str(file_path.as_ref().as_os_str())?;
+        let hkey = self.hkey()?;
+        let result = unsafe { RegLoadKeyW(hkey, path.as_ptr(), file_path.as_ptr()) };
+
+        if result != 0 {
+            return Err(Error::from_code(result, file_path.to_string_lossy()));
+        }
+
+        let regkey = match self.open(path.clone(), sec) {
+            Ok(v) => v,
+            Err(e) => {
+                unsafe { RegUnLoadKeyW(hkey, path.as_ptr()) };
+                return Err(e);
+            }
+        };
+
+        Ok(LoadedHive {
+            hive: *self,
+            path,
+            regkey: Some(regkey),
+        })
+    }
+
+    /// Stages a replacement for the file backing the hive loaded at the given path,
+    /// using `RegReplaceKeyW`. The replacement takes effect when the system next starts.
+    ///
+    /// The current file is moved to `old_file_path` as a backup. The calling process
+    /// must hold `SeRestorePrivilege`.
+    pub fn replace_file<P, N, O>(
+        &self,
+        path: P,
+        new_file_path: N,
+        old_file_path: O,
+    ) -> Result<(), Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+        N: AsRef<std::path::Path>,
+        O: AsRef<std::path::Path>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let new_file_path = U16CString::from_os_str(new_file_path.as_ref().as_os_str())?;
+        let old_file_path = U16CString::from_os_str(old_file_path.as_ref().as_os_str())?;
+        let result = unsafe {
+            RegReplaceKeyW(
+                self.hkey()?,
+                path.as_ptr(),
+                new_file_path.as_ptr(),
+                old_file_path.as_ptr(),
+            )
+        };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        Err(Error::from_code(result, path.to_string_lossy()))
     }
 
+    #[deprecated(note = "use `Hive::load_app_key` instead")]
     #[inline]
     pub fn load_file<P: AsRef<std::path::Path>>(
         file_path: P,
@@ -102,10 +550,120 @@ impl Hive {
         }
         let path = U16CString::from_os_str(file_path.as_ref().as_os_str())
             .expect("Path must always be UTF-16 on Windows");
-        load_appkey(&path, sec).map(|handle| RegKey {
-            hive: Hive::Application,
-            handle,
-            path: "".try_into().unwrap(),
+        load_appkey(&path, sec).map(|handle| {
+            RegKey::new(
+                Hive::Application,
+                handle,
+                U16CString::default(),
+                View::from_security(sec),
+                false,
+            )
+        })
+    }
+
+    /// Splits a full registry path such as `HKLM\Software\Foo` into its hive and
+    /// the remaining path, which is empty if the path names only a hive.
+    pub fn split_path(path: &str) -> Result<(Hive, &str), ParseHiveError> {
+        let mut parts = path.splitn(2, '\\');
+        let hive = parts.next().unwrap_or_default().parse()?;
+        let rest = parts.next().unwrap_or_default().trim_start_matches('\\');
+        Ok((hive, rest))
+    }
+
+    /// Splits an absolute NT registry path, such as a link target returned by
+    /// [`RegKey::read_link`](struct.RegKey.html#method.read_link), into the hive and path
+    /// it can be opened with. Only `\REGISTRY\MACHINE` and `\REGISTRY\USER` map to a
+    /// hive, and matching is case-insensitive.
+    pub fn split_nt_path(path: &str) -> Option<(Hive, &str)> {
+        const PREFIXES: [(&str, Hive); 2] = [
+            (r"\REGISTRY\MACHINE", Hive::LocalMachine),
+            (r"\REGISTRY\USER", Hive::Users),
+        ];
+
+        PREFIXES.iter().find_map(|(prefix, hive)| {
+            let head = path.get(..prefix.len())?;
+            let rest = &path[prefix.len()..];
+            if !head.eq_ignore_ascii_case(prefix) || !(rest.is_empty() || rest.starts_with('\\')) {
+                return None;
+            }
+            Some((*hive, rest.trim_start_matches('\\')))
+        })
+    }
+}
+
+/// A hive file loaded with [`Hive::load`](enum.Hive.html#method.load), which is unloaded
+/// with `RegUnLoadKeyW` when dropped.
+///
+/// Dereferences to the `RegKey` at the root of the loaded hive.
+#[derive(Debug)]
+pub struct LoadedHive {
+    hive: Hive,
+    path: U16CString,
+    regkey: Option<RegKey>,
+}
+
+impl LoadedHive {
+    /// Unloads the hive, returning any error that occurred.
+    ///
+    /// Unloading fails if any keys within the hive are still open.
+    pub fn unload(mut self) -> Result<(), Error> {
+        self.unload_inner()
+    }
+
+    fn unload_inner(&mut self) -> Result<(), Error> {
+        // The root key must be closed before the hive can be unloaded.
+        match self.regkey.take() {
+            Some(regkey) => drop(regkey),
+            None => return Ok(()),
+        }
+
+        let result = unsafe { RegUnLoadKeyW(self.hive.hkey()?, self.path.as_ptr()) };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        Err(Error::from_code(result, self.path.to_string_lossy()))
+    }
+}
+
+impl std::ops::Deref for LoadedHive {
+    type Target = RegKey;
+
+    fn deref(&self) -> &RegKey {
+        self.regkey.as_ref().unwrap()
+    }
+}
+
+impl Drop for LoadedHive {
+    fn drop(&mut self) {
+        // No point checking the return value here.
+        let _ = self.unload_inner();
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid or unknown hive name: {0:?}")]
+pub struct ParseHiveError(String);
+
+impl FromStr for Hive {
+    type Err = ParseHiveError;
+
+    /// Parses a hive from its full name, such as `HKEY_LOCAL_MACHINE`, or its
+    /// common abbreviation, such as `HKLM`. Matching is case-insensitive.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match &*s.to_ascii_uppercase() {
+            "HKEY_CLASSES_ROOT" | "HKCR" => Hive::ClassesRoot,
+            "HKEY_CURRENT_CONFIG" | "HKCC" => Hive::CurrentConfig,
+            "HKEY_CURRENT_USER" | "HKCU" => Hive::CurrentUser,
+            "HKEY_CURRENT_USER_LOCAL_SETTINGS" => Hive::CurrentUserLocalSettings,
+            "HKEY_DYN_DATA" | "HKDD" => Hive::DynData,
+            "HKEY_LOCAL_MACHINE" | "HKLM" => Hive::LocalMachine,
+            "HKEY_PERFORMANCE_DATA" | "HKPD" => Hive::PerformanceData,
+            "HKEY_PERFORMANCE_TEXT" => Hive::PerformanceText,
+            "HKEY_PERFORMANCE_NLSTEXT" => Hive::PerformanceNlsText,
+            "HKEY_USERS" | "HKU" => Hive::Users,
+            _ => return Err(ParseHiveError(s.to_string())),
         })
     }
 }
@@ -117,10 +675,14 @@ impl Display for Hive {
             Hive::CurrentConfig => "HKEY_CURRENT_CONFIG",
             Hive::CurrentUser => "HKEY_CURRENT_USER",
             Hive::CurrentUserLocalSettings => "HKEY_CURRENT_USER_LOCAL_SETTINGS",
+            Hive::DynData => "HKEY_DYN_DATA",
             Hive::LocalMachine => "HKEY_LOCAL_MACHINE",
             Hive::PerformanceData => "HKEY_PERFORMANCE_DATA",
+            Hive::PerformanceText => "HKEY_PERFORMANCE_TEXT",
+            Hive::PerformanceNlsText => "HKEY_PERFORMANCE_NLSTEXT",
             Hive::Users => "HKEY_USERS",
             Hive::Application => "<App>",
+            Hive::Unknown => "<Unknown>",
         })
     }
 }
@@ -140,3 +702,56 @@ where
 
     Err(std::io::Error::from_raw_os_error(result))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Hive;
+    use crate::Security;
+
+    #[test]
+    fn parse_hive_names() {
+        assert!(matches!(
+            "HKEY_LOCAL_MACHINE".parse(),
+            Ok(Hive::LocalMachine)
+        ));
+        assert!(matches!("hklm".parse(), Ok(Hive::LocalMachine)));
+        assert!(matches!("HKCU".parse(), Ok(Hive::CurrentUser)));
+        assert!(matches!("HKU".parse(), Ok(Hive::Users)));
+        assert!("HKXX".parse::<Hive>().is_err());
+    }
+
+    #[test]
+    fn split_full_path() {
+        let (hive, path) = Hive::split_path(r"HKLM\Software\Foo").unwrap();
+        assert!(matches!(hive, Hive::LocalMachine));
+        assert_eq!(path, r"Software\Foo");
+
+        let (hive, path) = Hive::split_path("HKEY_CLASSES_ROOT").unwrap();
+        assert!(matches!(hive, Hive::ClassesRoot));
+        assert_eq!(path, "");
+    }
+
+    #[test]
+    fn split_nt_path() {
+        let (hive, path) = Hive::split_nt_path(r"\REGISTRY\MACHINE\SOFTWARE\Foo").unwrap();
+        assert!(matches!(hive, Hive::LocalMachine));
+        assert_eq!(path, r"SOFTWARE\Foo");
+
+        let (hive, path) = Hive::split_nt_path(r"\Registry\User").unwrap();
+        assert!(matches!(hive, Hive::Users));
+        assert_eq!(path, "");
+
+        assert!(Hive::split_nt_path(r"\REGISTRY\USERS").is_none());
+        assert!(Hive::split_nt_path(r"\REGISTRY\A").is_none());
+        assert!(Hive::split_nt_path(r"SOFTWARE\Foo").is_none());
+    }
+
+    #[test]
+    fn hive_without_handle() {
+        assert!(Hive::Unknown.as_hkey().is_none());
+        assert!(Hive::Unknown.open("Software", Security::Read).is_err());
+        assert!(Hive::Application
+            .create("Software", Security::Read)
+            .is_err());
+    }
+}
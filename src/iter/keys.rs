@@ -1,15 +1,21 @@
 use std::{
+    cmp::Ordering,
     fmt::{Debug, Display},
     ptr::null_mut,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use utfx::{U16CString, U16String};
-use winapi::shared::winerror::ERROR_NO_MORE_ITEMS;
+use utfx::{U16CStr, U16CString};
+use winapi::shared::minwindef::{FILETIME, HKEY};
+use winapi::shared::winerror::{ERROR_MORE_DATA, ERROR_NO_MORE_ITEMS};
 use winapi::um::winreg::{RegEnumKeyExW, RegQueryInfoKeyW};
 
-use crate::key::RegKey;
+use crate::key::{filetime_to_system_time, RegKey};
 use crate::sec::Security;
 
+/// The maximum length of a key name in UTF-16 code units, excluding the NUL terminator.
+const MAX_KEY_NAME_LEN: usize = 255;
+
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 pub enum Error {
@@ -21,18 +27,51 @@ pub enum Error {
 
     #[error("Invalid null found in string")]
     InvalidNul(#[from] utfx::NulError<u16>),
+
+    #[error("An unknown IO error occurred for index: {0:?}")]
+    Unknown(u32, #[source] std::io::Error),
+}
+
+/// The order to return subkeys in from
+/// [`Keys::sorted`](struct.Keys.html#method.sorted).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// The order the registry enumerates them in.
+    None,
+    /// By name, ignoring case as the registry and regedit do.
+    Name,
+    /// By last write time, oldest first, then by name.
+    LastWrite,
 }
 
+/// Iterates over a key's immediate subkeys, created with
+/// [`RegKey::keys`](../../struct.RegKey.html#method.keys).
+///
+/// The number of subkeys is read when the iterator is created, and bounds iteration from
+/// either end, so subkeys added while iterating are not included. If it cannot be read,
+/// such as for a key opened with only `Security::EnumerateSubKeys`, iteration continues
+/// until the registry reports no more subkeys, and any error is reported by enumerating.
+/// `remaining` and `size_hint` give the number of subkeys left from that count, which is
+/// only exact if no subkeys are deleted while iterating.
+///
+/// Subkeys are enumerated by index, so the iterator can also be reversed with `rev`.
+/// Adding or deleting subkeys shifts the indices of those after them, which iterating
+/// forwards detects so that no subkey is skipped or repeated, unless the iterator is made
+/// `unchecked`. To modify subkeys while iterating, `snapshot` reads them all first.
 #[derive(Debug)]
 pub struct Keys<'a> {
     regkey: &'a RegKey,
     buf: Vec<u16>,
     index: u32,
+    end: Option<u32>,
+    is_checked: bool,
+    prev: Option<Vec<u16>>,
 }
 
 pub struct KeyRef<'a> {
     regkey: &'a RegKey,
     name: U16CString,
+    last_write_time: SystemTime,
 }
 
 impl Display for KeyRef<'_> {
@@ -50,25 +89,26 @@ impl<'a> Debug for KeyRef<'a> {
 }
 
 impl<'a> KeyRef<'a> {
+    #[inline]
+    pub fn name(&self) -> &U16CStr {
+        &self.name
+    }
+
+    /// The time the subkey, or any of its values, was last modified, as reported while
+    /// enumerating, so without opening the subkey.
+    #[inline]
+    pub fn last_write_time(&self) -> SystemTime {
+        self.last_write_time
+    }
+
+    #[inline]
+    pub fn into_name(self) -> U16CString {
+        self.name
+    }
+
     #[inline]
     pub fn open(&self, sec: Security) -> Result<RegKey, crate::key::Error> {
-        let path = self.regkey.path.to_ustring();
-        let suffix = self.name.to_ustring();
-        let bs = U16String::from_str("\\");
-        let chars = path
-            .as_slice()
-            .iter()
-            .chain(bs.as_slice())
-            .chain(suffix.as_slice())
-            .copied()
-            .collect::<Vec<u16>>();
-
-        let path = U16CString::new(chars)?;
-        crate::key::open_hkey(self.regkey.handle, &self.name, sec).map(|handle| RegKey {
-            hive: self.regkey.hive,
-            handle,
-            path,
-        })
+        self.regkey.open(&self.name, sec)
     }
 }
 
@@ -76,76 +116,348 @@ impl<'a> Iterator for Keys<'a> {
     type Item = Result<KeyRef<'a>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // Reset first byte, just in case.
-        self.buf[0] = 0;
-        let mut len = self.buf.len() as u32;
+        let item = self.read_next()?;
+        Some(item.and_then(|(len, last_write_time)| self.key_ref(len, last_write_time)))
+    }
 
-        let result = unsafe {
-            RegEnumKeyExW(
-                self.regkey.handle,
-                self.index,
-                self.buf.as_mut_ptr(),
-                &mut len,
-                null_mut(),
-                null_mut(),
-                null_mut(),
-                null_mut(),
-            )
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.end {
+            Some(end) => {
+                let len = end.saturating_sub(self.index) as usize;
+                (len, Some(len))
+            }
+            None => (0, None),
+        }
+    }
+}
+
+impl DoubleEndedIterator for Keys<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let mut end = match self.end {
+            Some(end) => end,
+            None => match query_lens(self.regkey.handle) {
+                Ok((count, _)) => count,
+                Err(e) => {
+                    self.end = Some(self.index);
+                    return Some(Err(Error::Unknown(self.index, e)));
+                }
+            },
         };
 
-        if result == ERROR_NO_MORE_ITEMS as i32 {
+        // Skip past the end of the subkeys if any have been deleted.
+        while end > self.index {
+            end -= 1;
+            self.end = Some(end);
+
+            if let Some(item) = enum_key(self.regkey.handle, end, &mut self.buf) {
+                return Some(item.and_then(|(len, time)| self.key_ref(len, time)));
+            }
+        }
+
+        self.end = Some(end);
+        None
+    }
+}
+
+impl<'a> Keys<'a> {
+    pub fn new(regkey: &'a RegKey) -> Result<Keys<'a>, std::io::Error> {
+        query_lens(regkey.handle).map(|lens| Keys::with_lens(regkey, Some(lens)))
+    }
+
+    /// Creates the iterator without the number of subkeys, for when it cannot be read.
+    pub(crate) fn unbounded(regkey: &'a RegKey) -> Keys<'a> {
+        Keys::with_lens(regkey, None)
+    }
+
+    fn with_lens(regkey: &'a RegKey, lens: Option<(u32, u32)>) -> Keys<'a> {
+        let max_name_len = lens.map_or(MAX_KEY_NAME_LEN as u32, |x| x.1);
+
+        Keys {
+            regkey,
+            buf: vec![0u16; max_name_len as usize + 1],
+            index: 0,
+            end: lens.map(|x| x.0),
+            is_checked: true,
+            prev: None,
+        }
+    }
+
+    /// Advances the iterator like `next`, but borrows the subkey's name from the
+    /// iterator's buffer instead of allocating it, so enumerating allocates nothing per
+    /// subkey. As this cannot be done by an `Iterator`, it is used in a `while let` loop:
+    ///
+    /// ```ignore
+    /// let mut keys = key.keys();
+    /// while let Some(name) = keys.next_name() {
+    ///     println!("{}", name?.to_string_lossy());
+    /// }
+    /// ```
+    pub fn next_name(&mut self) -> Option<Result<&U16CStr, Error>> {
+        let len = match self.read_next()? {
+            Ok((len, _)) => len,
+            Err(e) => return Some(Err(e)),
+        };
+
+        Some(super::borrow_name(&mut self.buf, len).map_err(Error::InvalidNul))
+    }
+
+    /// The number of subkeys left from the count read when the iterator was created, or 0
+    /// if it could not be read. This is more than are left if subkeys have been deleted.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.size_hint().0
+    }
+
+    /// Stops detecting subkeys being added or deleted before the current position while
+    /// iterating forwards, which may then skip or repeat subkeys.
+    ///
+    /// Detecting this checks that the subkey last read has not moved before reading each
+    /// subkey, doubling the calls made, so this is faster when the key is not modified
+    /// while iterating.
+    pub fn unchecked(mut self) -> Keys<'a> {
+        self.is_checked = false;
+        self.prev = None;
+        self
+    }
+
+    /// Reads all of the remaining subkeys before returning any of them, so the key can be
+    /// modified while iterating over them, such as to delete each subkey.
+    pub fn snapshot(self) -> Result<std::vec::IntoIter<KeyRef<'a>>, Error> {
+        Ok(self.collect::<Result<Vec<_>, _>>()?.into_iter())
+    }
+
+    /// Reads all of the remaining subkeys like `snapshot`, then sorts them by `order`.
+    ///
+    /// The result can be reversed, such as to list the most recently written subkeys
+    /// first with `Order::LastWrite`.
+    pub fn sorted(self, order: Order) -> Result<std::vec::IntoIter<KeyRef<'a>>, Error> {
+        let mut keys = self.collect::<Result<Vec<_>, _>>()?;
+
+        match order {
+            Order::None => {}
+            Order::Name => keys.sort_by(|a, b| cmp_names(&a.name, &b.name)),
+            Order::LastWrite => keys.sort_by(|a, b| {
+                a.last_write_time
+                    .cmp(&b.last_write_time)
+                    .then_with(|| cmp_names(&a.name, &b.name))
+            }),
+        }
+
+        Ok(keys.into_iter())
+    }
+
+    /// Reads the name of the next subkey from the front into the buffer, first moving
+    /// past the subkey last read if it has changed index and the iterator is checked.
+    fn read_next(&mut self) -> Option<Result<(usize, SystemTime), Error>> {
+        if self.is_done() {
             return None;
         }
 
+        if let Some(prev) = &self.prev {
+            let handle = self.regkey.handle;
+            let read = |index, buf: &mut Vec<u16>| Some(enum_key(handle, index, buf)?.map(|x| x.0));
+            match super::resync(prev, self.index, &mut self.buf, read) {
+                Ok(index) => {
+                    if let Some(end) = self.end.as_mut() {
+                        *end = (*end + index).saturating_sub(self.index).max(index);
+                    }
+                    self.index = index;
+                }
+                Err(e) => {
+                    self.prev = None;
+                    return Some(Err(e));
+                }
+            }
+
+            if self.is_done() {
+                return None;
+            }
+        }
+
+        let index = self.index;
         self.index += 1;
 
-        if result != 0 {
-            // TODO: don't panic
-            panic!();
+        match enum_key(self.regkey.handle, index, &mut self.buf) {
+            Some(Ok((len, last_write_time))) => {
+                if self.is_checked {
+                    let mut prev = self.prev.take().unwrap_or_default();
+                    prev.clear();
+                    prev.extend_from_slice(&self.buf[..len]);
+                    self.prev = Some(prev);
+                }
+                Some(Ok((len, last_write_time)))
+            }
+            Some(Err(e)) => {
+                self.prev = None;
+                // Without a count, there is no knowing whether the subkeys after it can be
+                // read either, so stop rather than possibly failing forever.
+                if self.end.is_none() {
+                    self.end = Some(self.index);
+                }
+                Some(Err(e))
+            }
+            None => {
+                // Subkeys have been deleted, so there are none left.
+                self.index = index;
+                self.end = Some(index);
+                None
+            }
+        }
+    }
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        matches!(self.end, Some(end) if self.index >= end)
+    }
+
+    fn key_ref(&self, len: usize, last_write_time: SystemTime) -> Result<KeyRef<'a>, Error> {
+        Ok(KeyRef {
+            regkey: self.regkey,
+            name: U16CString::new(&self.buf[0..len])?,
+            last_write_time,
+        })
+    }
+}
+
+/// Iterates over a key's immediate subkeys, opening each one, created with
+/// [`RegKey::open_keys`](../../struct.RegKey.html#method.open_keys).
+///
+/// Names are borrowed from the underlying `Keys` iterator's buffer, so the only
+/// allocations per subkey are those of opening it.
+#[derive(Debug)]
+pub struct OpenKeys<'a> {
+    regkey: &'a RegKey,
+    keys: Keys<'a>,
+    sec: Security,
+}
+
+impl<'a> OpenKeys<'a> {
+    pub(crate) fn new(regkey: &'a RegKey, sec: Security) -> OpenKeys<'a> {
+        OpenKeys {
+            regkey,
+            keys: regkey.keys(),
+            sec,
         }
+    }
+}
 
-        let name = match U16CString::new(&self.buf[0..len as usize]) {
+impl OpenKeys<'_> {
+    /// The number of subkeys left to open. See
+    /// [`Keys::remaining`](struct.Keys.html#method.remaining).
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.keys.remaining()
+    }
+}
+
+impl Iterator for OpenKeys<'_> {
+    type Item = Result<RegKey, crate::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let name = match self.keys.next_name()? {
             Ok(v) => v,
-            Err(e) => return Some(Err(Error::InvalidNul(e))),
+            Err(e) => return Some(Err(e.into())),
         };
 
-        Some(Ok(KeyRef {
-            regkey: self.regkey,
-            name,
-        }))
+        Some(self.regkey.open(name, self.sec).map_err(Into::into))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
     }
 }
 
-impl<'a> Keys<'a> {
-    pub fn new(regkey: &'a RegKey) -> Result<Keys<'a>, std::io::Error> {
-        let mut subkeys_max_str_len = 0u32;
-        // let mut subkeys_len = 0u32;
+/// Compares names by UTF-16 code unit after converting each to upper case, close to how
+/// the registry orders subkeys and regedit displays them, so `_` sorts after letters.
+#[inline]
+fn cmp_names(a: &U16CStr, b: &U16CStr) -> Ordering {
+    crate::key::cmp_ignore_case(a.as_slice(), b.as_slice())
+}
+
+/// Reads the name of the subkey at `index` into `buf`, returning its length and the
+/// subkey's last write time, or `None` if there is no subkey at `index`.
+/// Queries the number of subkeys, and the length of the longest subkey name in UTF-16
+/// code units.
+fn query_lens(handle: HKEY) -> Result<(u32, u32), std::io::Error> {
+    let mut subkeys_len = 0u32;
+    let mut subkeys_max_str_len = 0u32;
+
+    let result = unsafe {
+        RegQueryInfoKeyW(
+            handle,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            &mut subkeys_len,
+            &mut subkeys_max_str_len,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+        )
+    };
+
+    if result == 0 {
+        return Ok((subkeys_len, subkeys_max_str_len));
+    }
+
+    Err(std::io::Error::from_raw_os_error(result))
+}
+
+fn enum_key(
+    handle: HKEY,
+    index: u32,
+    buf: &mut Vec<u16>,
+) -> Option<Result<(usize, SystemTime), Error>> {
+    let mut filetime = FILETIME {
+        dwLowDateTime: 0,
+        dwHighDateTime: 0,
+    };
+
+    let (result, len) = loop {
+        // Reset first byte, just in case.
+        buf[0] = 0;
+        let mut len = buf.len() as u32;
 
         let result = unsafe {
-            RegQueryInfoKeyW(
-                regkey.handle,
-                null_mut(),
-                null_mut(),
-                null_mut(),
-                null_mut(), // &mut subkeys_len,
-                &mut subkeys_max_str_len,
-                null_mut(),
-                null_mut(),
-                null_mut(),
+            RegEnumKeyExW(
+                handle,
+                index,
+                buf.as_mut_ptr(),
+                &mut len,
                 null_mut(),
                 null_mut(),
                 null_mut(),
+                &mut filetime,
             )
         };
 
-        if result == 0 {
-            return Ok(Keys {
-                regkey,
-                buf: vec![0u16; subkeys_max_str_len as usize + 1],
-                index: 0,
-            });
+        // A subkey with a longer name may have been added since the buffer was sized.
+        if result == ERROR_MORE_DATA as i32 && buf.len() <= MAX_KEY_NAME_LEN {
+            buf.resize(MAX_KEY_NAME_LEN + 1, 0);
+            continue;
         }
 
-        Err(std::io::Error::from_raw_os_error(result))
+        break (result, len);
+    };
+
+    if result == ERROR_NO_MORE_ITEMS as i32 {
+        return None;
     }
+
+    if result != 0 {
+        return Some(Err(Error::Unknown(
+            index,
+            std::io::Error::from_raw_os_error(result),
+        )));
+    }
+
+    let filetime = (filetime.dwHighDateTime as u64) << 32 | filetime.dwLowDateTime as u64;
+    let last_write_time = filetime_to_system_time(filetime).unwrap_or(UNIX_EPOCH);
+
+    Some(Ok((len as usize, last_write_time)))
 }
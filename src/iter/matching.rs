@@ -0,0 +1,239 @@
+use utfx::{U16CStr, U16CString};
+
+use super::values::{Error, ValueNames, ValueRef};
+use crate::{key::RegKey, value, RegPath, Security};
+
+/// A case-insensitive glob pattern for a single key or value name, where `*` matches any
+/// run of characters and `?` matches any one character.
+#[derive(Debug, Clone)]
+pub(crate) struct Glob(Vec<char>);
+
+impl Glob {
+    pub(crate) fn new(pattern: &str) -> Glob {
+        Glob(pattern.chars().flat_map(char::to_lowercase).collect())
+    }
+
+    pub(crate) fn is_match(&self, name: &str) -> bool {
+        let name = name
+            .chars()
+            .flat_map(char::to_lowercase)
+            .collect::<Vec<_>>();
+        let (mut p, mut n) = (0, 0);
+        // The position after the last `*` and the name position it was tried at.
+        let mut retry = None;
+
+        while n < name.len() {
+            match self.0.get(p) {
+                Some('*') => {
+                    p += 1;
+                    retry = Some((p, n));
+                }
+                Some('?') => {
+                    p += 1;
+                    n += 1;
+                }
+                Some(c) if *c == name[n] => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match retry {
+                    // Let the last `*` match one more character and try again.
+                    Some((retry_p, retry_n)) => {
+                        p = retry_p;
+                        n = retry_n + 1;
+                        retry = Some((retry_p, retry_n + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+
+        self.0[p..].iter().all(|x| *x == '*')
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Matcher {
+    Glob(Glob),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl Matcher {
+    fn is_match(&self, name: &U16CStr) -> bool {
+        match self {
+            Matcher::Glob(glob) => glob.is_match(&name.to_string_lossy()),
+            #[cfg(feature = "regex")]
+            Matcher::Regex(regex) => regex.is_match(&name.to_string_lossy()),
+        }
+    }
+}
+
+struct Frame {
+    path: RegPath,
+    key: Option<RegKey>,
+    names: std::vec::IntoIter<U16CString>,
+}
+
+/// Iterates over the paths of the keys matching a pattern, created with
+/// [`RegKey::keys_matching`](../../struct.RegKey.html#method.keys_matching).
+///
+/// Each component of the pattern is matched against the subkeys at that depth, so only
+/// keys whose paths can still match are opened.
+pub struct KeysMatching<'a> {
+    root: &'a RegKey,
+    components: Vec<Matcher>,
+    stack: Vec<Frame>,
+    is_started: bool,
+}
+
+impl std::fmt::Debug for KeysMatching<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeysMatching")
+            .field("root", &self.root)
+            .field("components", &self.components)
+            .finish()
+    }
+}
+
+impl<'a> KeysMatching<'a> {
+    pub(crate) fn new(root: &'a RegKey, components: Vec<Matcher>) -> KeysMatching<'a> {
+        KeysMatching {
+            root,
+            components,
+            stack: vec![],
+            is_started: false,
+        }
+    }
+
+    /// Lists the subkeys of `key` whose names match the component at the next depth.
+    fn push(&mut self, path: RegPath, key: Option<RegKey>) -> Result<(), crate::Error> {
+        let matcher = &self.components[self.stack.len()];
+        let names = key
+            .as_ref()
+            .unwrap_or(self.root)
+            .keys()
+            .filter_map(|x| match x {
+                Ok(x) if matcher.is_match(x.name()) => Some(Ok(x.into_name())),
+                Ok(_) => None,
+                Err(e) => Some(Err(e.into())),
+            })
+            .collect::<Result<Vec<_>, crate::Error>>()?;
+
+        self.stack.push(Frame {
+            path,
+            key,
+            names: names.into_iter(),
+        });
+        Ok(())
+    }
+}
+
+impl Iterator for KeysMatching<'_> {
+    type Item = Result<RegPath, crate::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.is_started {
+            self.is_started = true;
+            if self.components.is_empty() {
+                return None;
+            }
+            if let Err(e) = self.push(RegPath::default(), None) {
+                return Some(Err(e));
+            }
+        }
+
+        loop {
+            let depth = self.stack.len();
+            let frame = self.stack.last_mut()?;
+            let name = match frame.names.next() {
+                Some(name) => name,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            let path = frame.path.join(name.to_string_lossy());
+
+            if depth == self.components.len() {
+                return Some(Ok(path));
+            }
+
+            let parent = frame.key.as_ref().unwrap_or(self.root);
+            let key = match parent.open(&name, Security::Read) {
+                Ok(key) => key,
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            if let Err(e) = self.push(path, Some(key)) {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Iterates over the values whose names match a pattern, created with
+/// [`RegKey::values_matching`](../../struct.RegKey.html#method.values_matching).
+///
+/// Only the names of values are enumerated, and the data of matching values is then read,
+/// so the data of other values is never read.
+#[derive(Debug)]
+pub struct ValuesMatching<'a> {
+    regkey: &'a RegKey,
+    names: ValueNames<'a>,
+    matcher: Matcher,
+}
+
+impl<'a> ValuesMatching<'a> {
+    pub(crate) fn new(regkey: &'a RegKey, matcher: Matcher) -> ValuesMatching<'a> {
+        ValuesMatching {
+            regkey,
+            names: regkey.value_names(),
+            matcher,
+        }
+    }
+}
+
+impl<'a> Iterator for ValuesMatching<'a> {
+    type Item = Result<ValueRef<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let name = match self.names.next()? {
+                Ok(name) if self.matcher.is_match(&name) => name,
+                Ok(_) => continue,
+                Err(e) => return Some(Err(e)),
+            };
+
+            // A value deleted since its name was enumerated is skipped.
+            return match value::query_value(self.regkey.handle, &name) {
+                Ok(data) => Some(Ok(ValueRef::new(self.regkey, name, data))),
+                Err(e) if e.is_not_found() => continue,
+                Err(e) => Some(Err(Error::Data(e))),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Glob;
+
+    #[test]
+    fn glob() {
+        let matches = |pattern: &str, name: &str| Glob::new(pattern).is_match(name);
+
+        assert!(matches("Display*", "DisplayName"));
+        assert!(matches("display*", "DISPLAYVERSION"));
+        assert!(matches("Display*", "Display"));
+        assert!(!matches("Display*", "NoDisplay"));
+        assert!(matches("*Name", "DisplayName"));
+        assert!(matches("*a*b*", "xxaxxbxx"));
+        assert!(!matches("*a*b", "xxaxxbxx"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("*", ""));
+        assert!(!matches("", "a"));
+        assert!(matches("{*}", "{0000-1111}"));
+    }
+}
@@ -1,5 +1,84 @@
 pub mod keys;
+mod matching;
+pub mod search;
+pub mod users;
 pub mod values;
+pub mod walk;
 
-pub(crate) use keys::Keys;
-pub(crate) use values::Values;
+pub(crate) use keys::{Keys, OpenKeys};
+pub(crate) use matching::{Glob, Matcher};
+pub use matching::{KeysMatching, ValuesMatching};
+pub(crate) use search::Search;
+pub(crate) use users::UserProfiles;
+pub(crate) use values::{LazyValues, RawValues, ValueNames, Values};
+pub(crate) use walk::Walk;
+
+use utfx::{U16CStr, U16CString};
+
+/// Borrows a name read into the first `len` code units of `buf`, failing as
+/// `U16CString::new` does if it contains a nul.
+pub(crate) fn borrow_name(buf: &mut [u16], len: usize) -> Result<&U16CStr, utfx::NulError<u16>> {
+    if buf[..len].contains(&0) {
+        return Err(U16CString::new(&buf[..len]).unwrap_err());
+    }
+
+    // The registry terminates the name, but make sure of it as this is relied on below.
+    buf[len] = 0;
+
+    // SAFETY: the slice's only nul is its last element.
+    Ok(unsafe { U16CStr::from_slice_with_nul_unchecked(&buf[..=len]) })
+}
+
+/// Finds the index to continue enumerating from after `prev`, the name last read from the
+/// front, at `index - 1`. If entries before it have been added or deleted since, it has
+/// moved and is searched for outwards from where it was, as it has usually only moved by
+/// a few places. `read` reads the name at an index into a buffer, returning its length,
+/// or `None` past the last entry.
+pub(crate) fn resync<E>(
+    prev: &[u16],
+    index: u32,
+    buf: &mut Vec<u16>,
+    mut read: impl FnMut(u32, &mut Vec<u16>) -> Option<Result<usize, E>>,
+) -> Result<u32, E> {
+    let last = match index.checked_sub(1) {
+        Some(last) => last,
+        None => return Ok(0),
+    };
+
+    if let Some(len) = read(last, buf).transpose()? {
+        if buf[..len] == *prev {
+            return Ok(index);
+        }
+    }
+
+    let mut distance = 1;
+    let mut has_above = true;
+
+    loop {
+        let below = last.checked_sub(distance);
+        if below.is_none() && !has_above {
+            break;
+        }
+
+        if let Some(i) = below {
+            if let Some(len) = read(i, buf).transpose()? {
+                if buf[..len] == *prev {
+                    return Ok(i + 1);
+                }
+            }
+        }
+
+        if has_above {
+            match read(last + distance, buf).transpose()? {
+                Some(len) if buf[..len] == *prev => return Ok(last + distance + 1),
+                Some(_) => {}
+                None => has_above = false,
+            }
+        }
+
+        distance += 1;
+    }
+
+    // The entry itself has been deleted, so continue from where it was.
+    Ok(last)
+}
@@ -0,0 +1,156 @@
+use std::collections::VecDeque;
+
+use utfx::U16CString;
+
+use super::Walk;
+use crate::{key::RegKey, Data, OnError, RegPath, Security, WalkEntry, WalkOptions};
+
+/// What to search for with [`RegKey::search`](../../struct.RegKey.html#method.search),
+/// like the options of regedit's Find.
+#[derive(Debug, Clone)]
+pub struct Query {
+    /// The text to look for.
+    pub text: String,
+    /// Whether key names are searched.
+    pub key_names: bool,
+    /// Whether value names are searched.
+    pub value_names: bool,
+    /// Whether the data of string and multi string values is searched.
+    pub value_data: bool,
+    /// Whether case must match, rather than being ignored.
+    pub match_case: bool,
+    /// Whether the whole name or string must match, rather than containing the text.
+    pub whole_string: bool,
+    /// The deepest level of subkeys searched, starting at 1 for immediate subkeys, or
+    /// `None` to search the whole subtree.
+    pub max_depth: Option<usize>,
+    /// The access rights each subkey is opened with, which must allow enumerating it.
+    pub security: Security,
+    /// What to do when a subkey cannot be opened or read.
+    pub on_error: OnError,
+}
+
+impl Query {
+    /// Searches key names, value names and value data for `text`, ignoring case.
+    pub fn new<S: Into<String>>(text: S) -> Query {
+        Query {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    fn is_match(&self, haystack: &str) -> bool {
+        let (haystack, text) = if self.match_case {
+            (haystack.to_string(), self.text.clone())
+        } else {
+            (haystack.to_lowercase(), self.text.to_lowercase())
+        };
+
+        if self.whole_string {
+            haystack == text
+        } else {
+            haystack.contains(&text)
+        }
+    }
+
+    fn is_data_match(&self, data: &Data) -> bool {
+        match data {
+            Data::String(x) | Data::ExpandString(x) => self.is_match(&x.to_string_lossy()),
+            Data::MultiString(x) => x.iter().any(|x| self.is_match(&x.to_string_lossy())),
+            _ => false,
+        }
+    }
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Query {
+            text: String::new(),
+            key_names: true,
+            value_names: true,
+            value_data: true,
+            match_case: false,
+            whole_string: false,
+            max_depth: None,
+            security: Security::Read,
+            on_error: OnError::Report,
+        }
+    }
+}
+
+/// Something found by a search. Paths are relative to the key being searched.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SearchMatch {
+    /// A key whose name matches.
+    Key(RegPath),
+    /// A value whose name matches, with the path of its key.
+    ValueName(RegPath, U16CString),
+    /// A value whose data matches, with the path of its key and its name.
+    ValueData(RegPath, U16CString, Data),
+}
+
+/// Iterates over the matches of a search, created with
+/// [`RegKey::search`](../../struct.RegKey.html#method.search).
+///
+/// The subtree is walked depth-first as by `RegKey::walk`, and matches are yielded as
+/// keys are visited, so a search can be stopped once something is found.
+#[derive(Debug)]
+pub struct Search<'a> {
+    walk: Walk<'a>,
+    query: Query,
+    found: VecDeque<SearchMatch>,
+}
+
+impl<'a> Search<'a> {
+    pub(crate) fn new(root: &'a RegKey, query: Query) -> Search<'a> {
+        let opts = WalkOptions {
+            security: query.security,
+            include_values: query.value_names || query.value_data,
+            on_error: query.on_error,
+            max_depth: query.max_depth,
+            prune: None,
+        };
+
+        Search {
+            walk: Walk::new(root, opts),
+            query,
+            found: VecDeque::new(),
+        }
+    }
+
+    fn search(&mut self, entry: WalkEntry) {
+        let query = &self.query;
+
+        if query.key_names && matches!(entry.path.name(), Some(x) if query.is_match(x)) {
+            self.found.push_back(SearchMatch::Key(entry.path.clone()));
+        }
+
+        // A value is only reported once, even if both its name and data match.
+        for (name, data) in entry.values {
+            if query.value_names && query.is_match(&name.to_string_lossy()) {
+                self.found
+                    .push_back(SearchMatch::ValueName(entry.path.clone(), name));
+            } else if query.value_data && query.is_data_match(&data) {
+                self.found
+                    .push_back(SearchMatch::ValueData(entry.path.clone(), name, data));
+            }
+        }
+    }
+}
+
+impl Iterator for Search<'_> {
+    type Item = Result<SearchMatch, crate::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(found) = self.found.pop_front() {
+                return Some(Ok(found));
+            }
+
+            match self.walk.next()? {
+                Ok(entry) => self.search(entry),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{key, Hive, RegKey, Security};
+
+/// A user profile loaded under `HKEY_USERS`, identified by its security identifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserProfile {
+    sid: String,
+    is_classes: bool,
+}
+
+impl UserProfile {
+    /// Parses the name of a subkey of `HKEY_USERS`, such as `S-1-5-21-...` or
+    /// `S-1-5-21-..._Classes`. Returns `None` for subkeys that are not named by a
+    /// SID, such as `.DEFAULT`.
+    pub fn from_key_name(name: &str) -> Option<UserProfile> {
+        let (sid, is_classes) = match name.strip_suffix("_Classes") {
+            Some(sid) => (sid, true),
+            None => (name, false),
+        };
+
+        let mut parts = sid.split('-');
+        if parts.next() != Some("S") {
+            return None;
+        }
+
+        let mut count = 0;
+        for part in parts {
+            if part.is_empty() || !part.bytes().all(|x| x.is_ascii_digit()) {
+                return None;
+            }
+            count += 1;
+        }
+
+        // A SID has at least a revision and an identifier authority.
+        if count < 2 {
+            return None;
+        }
+
+        Some(UserProfile {
+            sid: sid.to_string(),
+            is_classes,
+        })
+    }
+
+    /// The SID of the user, such as `S-1-5-18`.
+    pub fn sid(&self) -> &str {
+        &self.sid
+    }
+
+    /// Whether this is the user's classes key (`<SID>_Classes`), which is merged into
+    /// `HKEY_CLASSES_ROOT` for that user, rather than the root of their profile.
+    pub fn is_classes(&self) -> bool {
+        self.is_classes
+    }
+
+    /// The name of this profile's subkey under `HKEY_USERS`.
+    pub fn key_name(&self) -> String {
+        if self.is_classes {
+            format!("{}_Classes", self.sid)
+        } else {
+            self.sid.clone()
+        }
+    }
+
+    /// Opens this profile's subkey under `HKEY_USERS`.
+    pub fn open(&self, sec: Security) -> Result<RegKey, key::Error> {
+        Hive::Users.open(self.key_name(), sec)
+    }
+}
+
+/// An iterator over the user profiles loaded under `HKEY_USERS`, created by
+/// [`Hive::user_profiles`](../../enum.Hive.html#method.user_profiles).
+#[derive(Debug)]
+pub struct UserProfiles {
+    profiles: std::vec::IntoIter<UserProfile>,
+}
+
+impl UserProfiles {
+    pub(crate) fn new() -> Result<UserProfiles, crate::Error> {
+        let regkey = Hive::Users.open("", Security::Read)?;
+        let mut profiles = vec![];
+
+        for keyref in regkey.keys() {
+            if let Some(profile) = UserProfile::from_key_name(&keyref?.to_string()) {
+                profiles.push(profile);
+            }
+        }
+
+        Ok(UserProfiles {
+            profiles: profiles.into_iter(),
+        })
+    }
+}
+
+impl Iterator for UserProfiles {
+    type Item = UserProfile;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.profiles.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.profiles.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserProfile;
+
+    #[test]
+    fn parse_profile_names() {
+        let profile = UserProfile::from_key_name("S-1-5-18").unwrap();
+        assert_eq!(profile.sid(), "S-1-5-18");
+        assert!(!profile.is_classes());
+
+        let profile = UserProfile::from_key_name("S-1-5-21-1-2-3-1001_Classes").unwrap();
+        assert_eq!(profile.sid(), "S-1-5-21-1-2-3-1001");
+        assert!(profile.is_classes());
+        assert_eq!(profile.key_name(), "S-1-5-21-1-2-3-1001_Classes");
+
+        assert!(UserProfile::from_key_name(".DEFAULT").is_none());
+        assert!(UserProfile::from_key_name("S-1").is_none());
+        assert!(UserProfile::from_key_name("S-1-5-x").is_none());
+    }
+
+    #[test]
+    fn enumerate_profiles() {
+        let profiles = crate::Hive::user_profiles().unwrap().collect::<Vec<_>>();
+        assert!(profiles.iter().any(|x| x.sid() == "S-1-5-18"));
+    }
+}
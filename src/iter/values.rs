@@ -1,11 +1,15 @@
 use std::{convert::TryInto, fmt::Debug, ptr::null_mut};
 
 use utfx::{U16CStr, U16CString};
-use winapi::shared::winerror::ERROR_NO_MORE_ITEMS;
+use winapi::shared::minwindef::HKEY;
+use winapi::shared::winerror::{ERROR_MORE_DATA, ERROR_NO_MORE_ITEMS};
 use winapi::um::winreg::{RegEnumValueW, RegQueryInfoKeyW};
 
 use crate::{key::RegKey, Data};
 
+/// The maximum length of a value name in UTF-16 code units, excluding the NUL terminator.
+const MAX_VALUE_NAME_LEN: usize = 16_383;
+
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 pub enum Error {
@@ -25,12 +29,25 @@ pub enum Error {
     Unknown(u32, #[source] std::io::Error),
 }
 
+/// Iterates over a key's values, created with
+/// [`RegKey::values`](../../struct.RegKey.html#method.values).
+///
+/// The number of values is read when the iterator is created, and bounds iteration from
+/// either end, so values added while iterating are not included. If it cannot be read,
+/// enumerating reports the error instead. `remaining` and `size_hint` give the number
+/// of values left from that count, which is only exact if no values are deleted while
+/// iterating.
+///
+/// Values are enumerated by index, so the iterator can also be reversed with `rev`.
+/// Adding or deleting values shifts the indices of those after them, which iterating
+/// forwards detects so that no value is skipped or repeated, unless the iterator is made
+/// `unchecked`. To modify values while iterating, `snapshot` reads them all first.
 #[derive(Debug)]
 pub struct Values<'a> {
     regkey: &'a RegKey,
     name_buf: Vec<u16>,
     data_buf: Vec<u16>,
-    index: u32,
+    indices: Indices,
 }
 
 pub struct ValueRef<'a> {
@@ -49,6 +66,11 @@ impl<'a> Debug for ValueRef<'a> {
 }
 
 impl<'a> ValueRef<'a> {
+    #[inline]
+    pub(crate) fn new(regkey: &'a RegKey, name: U16CString, data: Data) -> ValueRef<'a> {
+        ValueRef { regkey, name, data }
+    }
+
     pub fn set_name<S>(&mut self, name: S) -> Result<(), Error>
     where
         S: TryInto<U16CString>,
@@ -95,91 +117,632 @@ impl<'a> Iterator for Values<'a> {
     type Item = Result<ValueRef<'a>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.name_buf[0] = 0;
-        let mut name_len = self.name_buf.len() as u32;
+        let lens = self.indices.read_next(
+            self.regkey.handle,
+            &mut self.name_buf,
+            Some(&mut self.data_buf),
+        )?;
+        Some(lens.and_then(|lens| self.value(lens)))
+    }
 
-        for v in &mut self.data_buf {
-            *v = 0;
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Values<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let lens = self.indices.read_back(
+            self.regkey.handle,
+            &mut self.name_buf,
+            Some(&mut self.data_buf),
+        )?;
+        Some(lens.and_then(|lens| self.value(lens)))
+    }
+}
+
+impl<'a> Values<'a> {
+    pub fn new(regkey: &'a RegKey) -> Result<Values<'a>, std::io::Error> {
+        query_lens(regkey.handle).map(|lens| Values::with_lens(regkey, Some(lens)))
+    }
+
+    /// Creates the iterator without the number of values, for when it cannot be read, in
+    /// which case enumerating reports the error instead.
+    pub(crate) fn unbounded(regkey: &'a RegKey) -> Values<'a> {
+        Values::with_lens(regkey, None)
+    }
+
+    fn with_lens(regkey: &'a RegKey, lens: Option<(u32, u32, u32)>) -> Values<'a> {
+        // The data buffer grows as needed if the lengths are unknown.
+        let (max_value_name_len, max_value_data_len) =
+            lens.map_or((MAX_VALUE_NAME_LEN as u32, 0), |x| (x.1, x.2));
+
+        Values {
+            regkey,
+            name_buf: vec![0u16; max_value_name_len as usize + 1],
+            data_buf: vec![0u16; (max_value_data_len / 2 + max_value_data_len % 2) as usize],
+            indices: Indices::new(lens.map(|x| x.0)),
         }
-        let mut data_type: u32 = 0u32;
-        let mut data_len = (self.data_buf.len() * 2) as u32;
+    }
 
-        let result = unsafe {
-            RegEnumValueW(
-                self.regkey.handle,
-                self.index,
-                self.name_buf.as_mut_ptr(),
-                &mut name_len,
-                null_mut(),
-                &mut data_type,
-                self.data_buf.as_mut_ptr() as *mut u8,
-                &mut data_len,
-            )
+    /// The number of values left from the count read when the iterator was created, or 0
+    /// if it could not be read. This is more than are left if values have been deleted.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.size_hint().0
+    }
+
+    /// Stops detecting values being added or deleted before the current position while
+    /// iterating forwards, which may then skip or repeat values.
+    ///
+    /// Detecting this checks that the value last read has not moved before reading each
+    /// value, doubling the calls made, so this is faster when the key is not modified
+    /// while iterating.
+    pub fn unchecked(mut self) -> Values<'a> {
+        self.indices.uncheck();
+        self
+    }
+
+    /// Reads all of the remaining values before returning any of them, so the key can be
+    /// modified while iterating over them, such as to delete each value.
+    pub fn snapshot(self) -> Result<std::vec::IntoIter<ValueRef<'a>>, Error> {
+        Ok(self.collect::<Result<Vec<_>, _>>()?.into_iter())
+    }
+
+    fn value(
+        &self,
+        (name_len, data_type, data_len): (usize, u32, usize),
+    ) -> <Self as Iterator>::Item {
+        let name = U16CString::new(&self.name_buf[0..name_len])?;
+        let data = crate::value::parse_value_type_data(
+            data_type,
+            self.data_buf[..data_len / 2 + data_len % 2].to_vec(),
+            data_len,
+        )?;
+
+        Ok(ValueRef {
+            regkey: self.regkey,
+            name,
+            data,
+        })
+    }
+}
+
+/// Iterates over the names of a key's values, without reading their data, created with
+/// [`RegKey::value_names`](../../struct.RegKey.html#method.value_names).
+///
+/// Like `Values`, iteration is bounded by the number of values when it was created,
+/// unless that could not be read, in which case enumerating reports the error instead.
+#[derive(Debug)]
+pub struct ValueNames<'a> {
+    regkey: &'a RegKey,
+    name_buf: Vec<u16>,
+    indices: Indices,
+}
+
+impl<'a> ValueNames<'a> {
+    pub(crate) fn new(regkey: &'a RegKey) -> ValueNames<'a> {
+        let lens = query_lens(regkey.handle).ok();
+        let max_value_name_len = lens.map_or(MAX_VALUE_NAME_LEN as u32, |x| x.1);
+
+        ValueNames {
+            regkey,
+            name_buf: vec![0u16; max_value_name_len as usize + 1],
+            indices: Indices::new(lens.map(|x| x.0)),
+        }
+    }
+
+    /// Advances the iterator like `next`, but borrows the name from the iterator's buffer
+    /// instead of allocating it, so enumerating allocates nothing per value. As this
+    /// cannot be done by an `Iterator`, it is used in a `while let` loop:
+    ///
+    /// ```ignore
+    /// let mut names = key.value_names();
+    /// while let Some(name) = names.next_name() {
+    ///     println!("{}", name?.to_string_lossy());
+    /// }
+    /// ```
+    pub fn next_name(&mut self) -> Option<Result<&U16CStr, Error>> {
+        let name_len = match self
+            .indices
+            .read_next(self.regkey.handle, &mut self.name_buf, None)?
+        {
+            Ok((name_len, _, _)) => name_len,
+            Err(e) => return Some(Err(e)),
         };
 
-        if result == ERROR_NO_MORE_ITEMS as i32 {
+        Some(super::borrow_name(&mut self.name_buf, name_len).map_err(Error::InvalidNul))
+    }
+
+    /// Stops detecting values being added or deleted while iterating forwards. See
+    /// [`Values::unchecked`](struct.Values.html#method.unchecked).
+    pub fn unchecked(mut self) -> ValueNames<'a> {
+        self.indices.uncheck();
+        self
+    }
+
+    /// Reads all of the remaining names before returning any of them, so the key can be
+    /// modified while iterating over them.
+    pub fn snapshot(self) -> Result<std::vec::IntoIter<U16CString>, Error> {
+        Ok(self.collect::<Result<Vec<_>, _>>()?.into_iter())
+    }
+}
+
+impl Iterator for ValueNames<'_> {
+    type Item = Result<U16CString, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lens = self
+            .indices
+            .read_next(self.regkey.handle, &mut self.name_buf, None)?;
+        Some(lens.and_then(|(name_len, _, _)| Ok(U16CString::new(&self.name_buf[0..name_len])?)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for ValueNames<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let lens = self
+            .indices
+            .read_back(self.regkey.handle, &mut self.name_buf, None)?;
+        Some(lens.and_then(|(name_len, _, _)| Ok(U16CString::new(&self.name_buf[0..name_len])?)))
+    }
+}
+
+/// Iterates over a key's values as their names, raw type codes and raw data, created
+/// with [`RegKey::raw_values`](../../struct.RegKey.html#method.raw_values).
+///
+/// Unlike `Values`, this never fails to parse data, so values of types unknown to this
+/// crate are included. `size_hint` behaves as for `ValueNames`.
+#[derive(Debug)]
+pub struct RawValues<'a> {
+    regkey: &'a RegKey,
+    name_buf: Vec<u16>,
+    data_buf: Vec<u16>,
+    indices: Indices,
+}
+
+impl<'a> RawValues<'a> {
+    pub(crate) fn new(regkey: &'a RegKey) -> RawValues<'a> {
+        // If the key cannot be queried, enumerating it reports the error instead, and the
+        // data buffer grows as needed.
+        let lens = query_lens(regkey.handle).ok();
+        let (max_value_name_len, max_value_data_len) =
+            lens.map_or((MAX_VALUE_NAME_LEN as u32, 0), |x| (x.1, x.2));
+
+        RawValues {
+            regkey,
+            name_buf: vec![0u16; max_value_name_len as usize + 1],
+            data_buf: vec![0u16; (max_value_data_len / 2 + max_value_data_len % 2) as usize],
+            indices: Indices::new(lens.map(|x| x.0)),
+        }
+    }
+
+    /// Stops detecting values being added or deleted while iterating forwards. See
+    /// [`Values::unchecked`](struct.Values.html#method.unchecked).
+    pub fn unchecked(mut self) -> RawValues<'a> {
+        self.indices.uncheck();
+        self
+    }
+
+    fn raw_value(
+        &self,
+        (name_len, data_type, data_len): (usize, u32, usize),
+    ) -> <Self as Iterator>::Item {
+        let name = U16CString::new(&self.name_buf[0..name_len])?;
+        let mut data =
+            crate::value::u16_slice_to_u8_vec(&self.data_buf[..data_len / 2 + data_len % 2]);
+        data.truncate(data_len);
+        Ok((name, data_type, data))
+    }
+}
+
+impl Iterator for RawValues<'_> {
+    type Item = Result<(U16CString, u32, Vec<u8>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lens = self.indices.read_next(
+            self.regkey.handle,
+            &mut self.name_buf,
+            Some(&mut self.data_buf),
+        )?;
+        Some(lens.and_then(|lens| self.raw_value(lens)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for RawValues<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let lens = self.indices.read_back(
+            self.regkey.handle,
+            &mut self.name_buf,
+            Some(&mut self.data_buf),
+        )?;
+        Some(lens.and_then(|lens| self.raw_value(lens)))
+    }
+}
+
+/// A value's name, type and size, read without its data, from
+/// [`RegKey::lazy_values`](../../struct.RegKey.html#method.lazy_values).
+pub struct LazyValue<'a> {
+    regkey: &'a RegKey,
+    name: U16CString,
+    ty: u32,
+    size: u32,
+}
+
+impl Debug for LazyValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LazyValue")
+            .field("name", &self.name.to_string_lossy())
+            .field("ty", &self.ty)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl<'a> LazyValue<'a> {
+    #[inline]
+    pub fn name(&self) -> &U16CStr {
+        &self.name
+    }
+
+    #[inline]
+    pub fn into_name(self) -> U16CString {
+        self.name
+    }
+
+    /// The raw type code of the value, such as `REG_BINARY`.
+    #[inline]
+    pub fn value_type(&self) -> u32 {
+        self.ty
+    }
+
+    /// The length of the value's data in bytes, when it was enumerated.
+    #[inline]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Reads the value's data, as it is now rather than when it was enumerated.
+    #[inline]
+    pub fn read(&self) -> Result<Data, crate::value::Error> {
+        self.regkey.value(&*self.name)
+    }
+
+    /// Reads the value's raw type code and data, as for
+    /// [`RegKey::value_raw`](../../struct.RegKey.html#method.value_raw).
+    #[inline]
+    pub fn read_raw(&self) -> Result<(u32, Vec<u8>), crate::value::Error> {
+        self.regkey.value_raw(&*self.name)
+    }
+}
+
+/// Iterates over a key's values without reading their data, created with
+/// [`RegKey::lazy_values`](../../struct.RegKey.html#method.lazy_values).
+///
+/// Each value's data can be read on demand with `LazyValue::read`, so large values that
+/// are not needed are never copied. `size_hint` behaves as for `ValueNames`.
+#[derive(Debug)]
+pub struct LazyValues<'a> {
+    regkey: &'a RegKey,
+    name_buf: Vec<u16>,
+    indices: Indices,
+}
+
+impl<'a> LazyValues<'a> {
+    pub(crate) fn new(regkey: &'a RegKey) -> LazyValues<'a> {
+        let lens = query_lens(regkey.handle).ok();
+        let max_value_name_len = lens.map_or(MAX_VALUE_NAME_LEN as u32, |x| x.1);
+
+        LazyValues {
+            regkey,
+            name_buf: vec![0u16; max_value_name_len as usize + 1],
+            indices: Indices::new(lens.map(|x| x.0)),
+        }
+    }
+
+    /// Stops detecting values being added or deleted while iterating forwards. See
+    /// [`Values::unchecked`](struct.Values.html#method.unchecked).
+    pub fn unchecked(mut self) -> LazyValues<'a> {
+        self.indices.uncheck();
+        self
+    }
+
+    fn lazy_value(&self, (name_len, ty, size): (usize, u32, usize)) -> <Self as Iterator>::Item {
+        Ok(LazyValue {
+            regkey: self.regkey,
+            name: U16CString::new(&self.name_buf[0..name_len])?,
+            ty,
+            size: size as u32,
+        })
+    }
+}
+
+impl<'a> Iterator for LazyValues<'a> {
+    type Item = Result<LazyValue<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lens = self
+            .indices
+            .read_next(self.regkey.handle, &mut self.name_buf, None)?;
+        Some(lens.and_then(|lens| self.lazy_value(lens)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for LazyValues<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let lens = self
+            .indices
+            .read_back(self.regkey.handle, &mut self.name_buf, None)?;
+        Some(lens.and_then(|lens| self.lazy_value(lens)))
+    }
+}
+
+/// The indices of the values left to enumerate from either end.
+///
+/// The end is the number of values when the iterator was created, or `None` until it is
+/// first needed if that could not be read. If `is_checked`, the name last read from the
+/// front is kept to detect values before it being added or deleted, which shifts the
+/// indices.
+#[derive(Debug)]
+struct Indices {
+    front: u32,
+    back: Option<u32>,
+    is_checked: bool,
+    prev: Option<Vec<u16>>,
+}
+
+impl Indices {
+    #[inline]
+    fn new(count: Option<u32>) -> Indices {
+        Indices {
+            front: 0,
+            back: count,
+            is_checked: true,
+            prev: None,
+        }
+    }
+
+    #[inline]
+    fn uncheck(&mut self) {
+        self.is_checked = false;
+        self.prev = None;
+    }
+
+    /// Reads the next value from the front, as `enum_value` does, first moving past the
+    /// value last read if it has changed index and `is_checked` is set.
+    fn read_next(
+        &mut self,
+        handle: HKEY,
+        name_buf: &mut Vec<u16>,
+        data_buf: Option<&mut Vec<u16>>,
+    ) -> Option<Result<(usize, u32, usize), Error>> {
+        if self.is_done() {
             return None;
         }
 
-        if result != 0 {
-            return Some(Err(Error::Unknown(
-                self.index,
-                std::io::Error::from_raw_os_error(result),
-            )));
+        if let Some(prev) = &self.prev {
+            let read = |index, buf: &mut Vec<u16>| {
+                Some(enum_value(handle, index, buf, None)?.map(|x| x.0))
+            };
+            match super::resync(prev, self.front, name_buf, read) {
+                Ok(front) => self.move_front(front),
+                Err(e) => {
+                    self.prev = None;
+                    return Some(Err(e));
+                }
+            }
+
+            if self.is_done() {
+                return None;
+            }
         }
 
-        self.index += 1;
+        let index = self.front;
+        self.front += 1;
+
+        match enum_value(handle, index, name_buf, data_buf) {
+            Some(Ok(lens)) => {
+                if self.is_checked {
+                    let mut prev = self.prev.take().unwrap_or_default();
+                    prev.clear();
+                    prev.extend_from_slice(&name_buf[..lens.0]);
+                    self.prev = Some(prev);
+                }
+                Some(Ok(lens))
+            }
+            Some(Err(e)) => {
+                self.prev = None;
+                // Without a count, there is no knowing whether the values after it can be
+                // read either, so stop rather than possibly failing forever.
+                if self.back.is_none() {
+                    self.back = Some(self.front);
+                }
+                Some(Err(e))
+            }
+            None => {
+                // Values have been deleted, so there are none left.
+                self.front = index;
+                self.back = Some(index);
+                None
+            }
+        }
+    }
 
-        let name = match U16CString::new(&self.name_buf[0..name_len as usize]) {
-            Ok(v) => v,
-            Err(e) => return Some(Err(Error::InvalidNul(e))),
+    /// Reads the next value from the back, skipping past the end of the values if any
+    /// have been deleted.
+    fn read_back(
+        &mut self,
+        handle: HKEY,
+        name_buf: &mut Vec<u16>,
+        mut data_buf: Option<&mut Vec<u16>>,
+    ) -> Option<Result<(usize, u32, usize), Error>> {
+        let mut back = match self.back {
+            Some(back) => back,
+            None => match query_lens(handle) {
+                Ok((count, _, _)) => count,
+                Err(e) => {
+                    self.back = Some(self.front);
+                    return Some(Err(Error::Unknown(self.front, e)));
+                }
+            },
         };
 
-        let data = match crate::value::parse_value_type_data(data_type, self.data_buf.clone()) {
-            Ok(v) => v,
-            Err(e) => return Some(Err(Error::Data(e))),
-        };
+        while back > self.front {
+            back -= 1;
+            self.back = Some(back);
 
-        Some(Ok(ValueRef {
-            regkey: self.regkey,
-            name,
-            data,
-        }))
+            if let Some(item) = enum_value(handle, back, name_buf, data_buf.as_deref_mut()) {
+                return Some(item);
+            }
+        }
+
+        self.back = Some(back);
+        None
+    }
+
+    #[inline]
+    fn is_done(&self) -> bool {
+        matches!(self.back, Some(back) if self.front >= back)
+    }
+
+    /// Moves the front to `front`, moving the back along with it.
+    fn move_front(&mut self, front: u32) {
+        if let Some(back) = self.back.as_mut() {
+            *back = (*back + front).saturating_sub(self.front).max(front);
+        }
+        self.front = front;
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.back {
+            Some(back) => {
+                let len = back.saturating_sub(self.front) as usize;
+                (len, Some(len))
+            }
+            None => (0, None),
+        }
     }
 }
 
-impl<'a> Values<'a> {
-    pub fn new(regkey: &'a RegKey) -> Result<Values<'a>, std::io::Error> {
-        let mut value_count = 0u32;
-        let mut max_value_name_len = 0u32;
-        let mut max_value_data_len = 0u32;
+/// Queries the number of values, the length of the longest value name in UTF-16 code
+/// units, and the size of the largest value's data in bytes.
+fn query_lens(handle: HKEY) -> Result<(u32, u32, u32), std::io::Error> {
+    let mut value_count = 0u32;
+    let mut max_value_name_len = 0u32;
+    let mut max_value_data_len = 0u32;
+
+    let result = unsafe {
+        RegQueryInfoKeyW(
+            handle,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            &mut value_count,
+            &mut max_value_name_len,
+            &mut max_value_data_len,
+            null_mut(),
+            null_mut(),
+        )
+    };
+
+    if result == 0 {
+        return Ok((value_count, max_value_name_len, max_value_data_len));
+    }
+
+    Err(std::io::Error::from_raw_os_error(result))
+}
+
+/// Reads the name, type, data size and, if given a data buffer, the data of the value at
+/// `index`.
+/// Returns the lengths of the name in code units and the data in bytes, or `None` if
+/// there is no value at `index`.
+///
+/// A value may grow after the buffers were sized, so they are grown and the read retried.
+fn enum_value(
+    handle: HKEY,
+    index: u32,
+    name_buf: &mut Vec<u16>,
+    mut data_buf: Option<&mut Vec<u16>>,
+) -> Option<Result<(usize, u32, usize), Error>> {
+    loop {
+        name_buf[0] = 0;
+        let mut name_len = name_buf.len() as u32;
+        let mut data_type = 0u32;
+        let mut data_len = data_buf.as_ref().map(|x| x.len() * 2).unwrap_or(0) as u32;
+        // Without a data buffer, only the size of the data is read.
+        let data_ptr = match data_buf.as_mut() {
+            Some(buf) => buf.as_mut_ptr() as *mut u8,
+            None => null_mut(),
+        };
 
         let result = unsafe {
-            RegQueryInfoKeyW(
-                regkey.handle,
-                null_mut(),
-                null_mut(),
-                null_mut(),
-                null_mut(),
-                null_mut(),
-                null_mut(),
-                &mut value_count,
-                &mut max_value_name_len,
-                &mut max_value_data_len,
-                null_mut(),
+            RegEnumValueW(
+                handle,
+                index,
+                name_buf.as_mut_ptr(),
+                &mut name_len,
                 null_mut(),
+                &mut data_type,
+                data_ptr,
+                &mut data_len,
             )
         };
 
-        if result == 0 {
-            return Ok(Values {
-                regkey,
-                name_buf: vec![0u16; max_value_name_len as usize + 1],
-                data_buf: vec![0u16; (max_value_data_len / 2 + max_value_data_len % 2) as usize],
-                index: 0,
-            });
+        if result == ERROR_NO_MORE_ITEMS as i32 {
+            return None;
+        }
+
+        if result == ERROR_MORE_DATA as i32 {
+            // Only the required data size is reported, so a name that does not fit is
+            // retried with the longest possible name.
+            match data_buf.as_mut() {
+                Some(buf) if data_len as usize > buf.len() * 2 => {
+                    buf.resize((data_len / 2 + data_len % 2) as usize, 0);
+                    continue;
+                }
+                _ if name_buf.len() <= MAX_VALUE_NAME_LEN => {
+                    name_buf.resize(MAX_VALUE_NAME_LEN + 1, 0);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        if result != 0 {
+            return Some(Err(Error::Unknown(
+                index,
+                std::io::Error::from_raw_os_error(result),
+            )));
+        }
+
+        // Clear the unused half of a `u16` holding an odd final byte, which may be left
+        // over from a previous value.
+        if let Some(buf) = data_buf {
+            if data_len % 2 == 1 {
+                buf[data_len as usize / 2] &= 0x00ff;
+            }
         }
 
-        Err(std::io::Error::from_raw_os_error(result))
+        return Some(Ok((name_len as usize, data_type, data_len as usize)));
     }
 }
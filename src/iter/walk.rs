@@ -0,0 +1,310 @@
+use std::sync::Arc;
+
+use utfx::U16CString;
+
+use crate::{key::RegKey, Data, RegPath, Security};
+
+/// What a walk does when a subkey cannot be opened or read, such as when access to it
+/// is denied.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OnError {
+    /// Skips the subkey and everything beneath it without reporting it.
+    Skip,
+    /// Yields the error and continues with the next subkey.
+    Report,
+    /// Yields the error and ends the walk.
+    Stop,
+}
+
+/// Decides whether a walk skips the key at a path, relative to the key being walked.
+pub type Prune = Arc<dyn Fn(&RegPath) -> bool + Send + Sync>;
+
+/// Options for [`RegKey::walk`](../../struct.RegKey.html#method.walk).
+#[derive(Clone)]
+pub struct WalkOptions {
+    /// The access rights each subkey is opened with, which must allow enumerating it.
+    pub security: Security,
+    /// Whether each key's values are read into `WalkEntry::values`.
+    pub include_values: bool,
+    /// What to do when a subkey cannot be opened, enumerated or have its values read.
+    pub on_error: OnError,
+    /// The deepest keys visited, where immediate subkeys have a depth of 1, or `None` to
+    /// visit the whole subtree. Keys at this depth are not enumerated.
+    pub max_depth: Option<usize>,
+    /// Called with the path of each subkey before it is opened. If it returns `true`, the
+    /// subkey and everything beneath it are skipped without being opened or enumerated:
+    ///
+    /// ```ignore
+    /// let opts = WalkOptions {
+    ///     prune: Some(Arc::new(|path| path.name() == Some("Classes"))),
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub prune: Option<Prune>,
+}
+
+impl WalkOptions {
+    fn is_pruned(&self, path: &RegPath) -> bool {
+        matches!(&self.prune, Some(prune) if prune(path))
+    }
+
+    fn is_too_deep(&self, depth: usize) -> bool {
+        matches!(self.max_depth, Some(max_depth) if depth > max_depth)
+    }
+}
+
+impl std::fmt::Debug for WalkOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WalkOptions")
+            .field("security", &self.security)
+            .field("include_values", &self.include_values)
+            .field("on_error", &self.on_error)
+            .field("max_depth", &self.max_depth)
+            .field("prune", &self.prune.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            security: Security::Read,
+            include_values: false,
+            on_error: OnError::Report,
+            max_depth: None,
+            prune: None,
+        }
+    }
+}
+
+/// A key found by a walk.
+#[derive(Debug)]
+pub struct WalkEntry {
+    /// The path of the key, relative to the key being walked.
+    pub path: RegPath,
+    /// The number of keys between the key being walked and this one, starting at 1 for
+    /// its immediate subkeys.
+    pub depth: usize,
+    /// The key's values, if `WalkOptions::include_values` is set.
+    pub values: Vec<(U16CString, Data)>,
+}
+
+struct Frame {
+    path: RegPath,
+    key: Option<RegKey>,
+    names: Option<std::vec::IntoIter<U16CString>>,
+}
+
+/// Iterates depth-first over every key beneath a key, created with
+/// [`RegKey::walk`](../../struct.RegKey.html#method.walk).
+///
+/// Each key is yielded before its subkeys. The subkey names of a key are read when the
+/// walk first descends into it, so subkeys added after that are not visited.
+pub struct Walk<'a> {
+    root: &'a RegKey,
+    opts: WalkOptions,
+    stack: Vec<Frame>,
+    is_done: bool,
+}
+
+impl std::fmt::Debug for Walk<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Walk")
+            .field("root", &self.root)
+            .field("opts", &self.opts)
+            .field("depth", &self.stack.len())
+            .finish()
+    }
+}
+
+impl<'a> Walk<'a> {
+    pub(crate) fn new(root: &'a RegKey, opts: WalkOptions) -> Walk<'a> {
+        // With a maximum depth of 0, there is nothing to visit.
+        let names = if opts.is_too_deep(1) {
+            Some(vec![].into_iter())
+        } else {
+            None
+        };
+
+        Walk {
+            root,
+            opts,
+            stack: vec![Frame {
+                path: RegPath::default(),
+                key: None,
+                names,
+            }],
+            is_done: false,
+        }
+    }
+
+    /// Applies the error policy, returning the item to yield, if any.
+    fn fail(&mut self, err: crate::Error) -> Option<Result<WalkEntry, crate::Error>> {
+        match self.opts.on_error {
+            OnError::Skip => None,
+            OnError::Report => Some(Err(err)),
+            OnError::Stop => {
+                self.is_done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Opens the subkey `name` of `parent`, at `path`, reading its values if requested.
+fn open_child(
+    parent: &RegKey,
+    path: RegPath,
+    name: &U16CString,
+    depth: usize,
+    opts: &WalkOptions,
+) -> Result<(WalkEntry, RegKey), crate::Error> {
+    let key = parent.open(name, opts.security)?;
+    let values = if opts.include_values {
+        values(&key)?
+    } else {
+        vec![]
+    };
+
+    let entry = WalkEntry {
+        path,
+        depth,
+        values,
+    };
+    Ok((entry, key))
+}
+
+fn subkey_names(key: &RegKey) -> Result<Vec<U16CString>, crate::Error> {
+    key.keys()
+        .map(|x| Ok(x?.into_name()))
+        .collect::<Result<Vec<_>, crate::Error>>()
+}
+
+fn values(key: &RegKey) -> Result<Vec<(U16CString, Data)>, crate::Error> {
+    key.values()
+        .map(|x| Ok(x?.into_inner()))
+        .collect::<Result<Vec<_>, crate::Error>>()
+}
+
+impl Iterator for Walk<'_> {
+    type Item = Result<WalkEntry, crate::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.is_done {
+            let frame = self.stack.last_mut()?;
+
+            // List the subkeys of a key the first time the walk descends into it.
+            if frame.names.is_none() {
+                let key = frame.key.as_ref().unwrap_or(self.root);
+                match subkey_names(key) {
+                    Ok(names) => frame.names = Some(names.into_iter()),
+                    Err(e) => {
+                        self.stack.pop();
+                        match self.fail(e) {
+                            Some(item) => return Some(item),
+                            None => continue,
+                        }
+                    }
+                }
+            }
+
+            let depth = self.stack.len();
+            let frame = self.stack.last_mut()?;
+            let name = match frame.names.as_mut().and_then(Iterator::next) {
+                Some(name) => name,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+
+            let path = frame.path.join(name.to_string_lossy());
+            if self.opts.is_pruned(&path) {
+                continue;
+            }
+
+            let parent = frame.key.as_ref().unwrap_or(self.root);
+            let (entry, key) = match open_child(parent, path, &name, depth, &self.opts) {
+                Ok(v) => v,
+                Err(e) => match self.fail(e) {
+                    Some(item) => return Some(item),
+                    None => continue,
+                },
+            };
+
+            // A key at the maximum depth is left with no subkeys to visit.
+            let names = if self.opts.is_too_deep(depth + 1) {
+                Some(vec![].into_iter())
+            } else {
+                None
+            };
+
+            self.stack.push(Frame {
+                path: entry.path.clone(),
+                key: Some(key),
+                names,
+            });
+            return Some(Ok(entry));
+        }
+
+        None
+    }
+}
+
+#[cfg(feature = "rayon")]
+enum Node<'a> {
+    Root(&'a RegKey),
+    Key(WalkEntry, RegKey),
+    Error(crate::Error),
+}
+
+/// Walks the subtree of `root` in parallel, for
+/// [`RegKey::par_walk`](../../struct.RegKey.html#method.par_walk).
+#[cfg(feature = "rayon")]
+pub(crate) fn par_walk(
+    root: &RegKey,
+    opts: WalkOptions,
+) -> impl rayon::iter::ParallelIterator<Item = Result<WalkEntry, crate::Error>> + '_ {
+    use rayon::iter::ParallelIterator;
+
+    let children = move |node: &Node<'_>| {
+        let (parent, path, depth) = match node {
+            Node::Root(key) => (*key, RegPath::default(), 1),
+            Node::Key(entry, key) => (key, entry.path.clone(), entry.depth + 1),
+            Node::Error(_) => return vec![],
+        };
+
+        if opts.is_too_deep(depth) {
+            return vec![];
+        }
+
+        let fail = |e| match opts.on_error {
+            OnError::Skip => None,
+            OnError::Report | OnError::Stop => Some(Node::Error(e)),
+        };
+
+        match subkey_names(parent) {
+            Ok(names) => names
+                .iter()
+                .filter_map(|name| {
+                    let path = path.join(name.to_string_lossy());
+                    if opts.is_pruned(&path) {
+                        return None;
+                    }
+
+                    match open_child(parent, path, name, depth, &opts) {
+                        Ok((entry, key)) => Some(Node::Key(entry, key)),
+                        Err(e) => fail(e),
+                    }
+                })
+                .collect(),
+            Err(e) => fail(e).into_iter().collect(),
+        }
+    };
+
+    rayon::iter::walk_tree_prefix(Node::Root(root), children).filter_map(|node| match node {
+        Node::Root(_) => None,
+        Node::Key(entry, _) => Some(Ok(entry)),
+        Node::Error(e) => Some(Err(e)),
+    })
+}
@@ -0,0 +1,274 @@
+//! Conversions between [`Data`](../enum.Data.html) and `serde_json::Value`.
+//!
+//! The plain conversions map data to its natural JSON form, so strings become JSON
+//! strings, numbers become JSON numbers, multi strings become arrays of strings and binary
+//! data becomes an array of bytes. This loses the distinction between types with the same
+//! form, such as `REG_SZ` and `REG_EXPAND_SZ`, which the typed form keeps:
+//!
+//! ```ignore
+//! let json = data.to_json_typed();        // {"type": "REG_EXPAND_SZ", "data": "%TEMP%"}
+//! let data = Data::from_json_typed(&json)?;
+//! ```
+
+use std::convert::TryFrom;
+
+use serde_json::{json, Value};
+use utfx::U16CString;
+
+use crate::{Data, RegType};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("JSON value cannot be converted to registry data: {0}")]
+    Unsupported(Value),
+
+    #[error("Unknown registry type: {0:?}")]
+    UnknownType(String),
+
+    #[error("Invalid null found in string")]
+    InvalidNul(#[from] utfx::NulError<u16>),
+}
+
+impl From<Data> for Value {
+    fn from(data: Data) -> Self {
+        match data {
+            Data::None(x) if x.is_empty() => Value::Null,
+            Data::String(x) | Data::ExpandString(x) => Value::String(x.to_string_lossy()),
+            Data::U32(x) | Data::U32BE(x) => Value::from(x),
+            Data::U64(x) => Value::from(x),
+            Data::MultiString(x) => x.iter().map(|x| x.to_string_lossy()).collect(),
+            Data::None(x)
+            | Data::Binary(x)
+            | Data::Link(x)
+            | Data::ResourceList(x)
+            | Data::FullResourceDescriptor(x)
+            | Data::ResourceRequirementsList(x) => x.into_iter().collect(),
+        }
+    }
+}
+
+/// Converts from the natural JSON form, choosing the closest registry type.
+///
+/// `null` becomes an empty `Data::None`, booleans become a `Data::U32` of 0 or 1, and integers
+/// become `Data::U32` if they fit in one, with negative numbers stored as their two's
+/// complement bits, or `Data::U64` otherwise. Arrays of strings become `Data::MultiString`
+/// and arrays of bytes become `Data::Binary`, with an empty array becoming an empty
+/// `Data::MultiString`. Floats, objects and other arrays fail with `Error::Unsupported`.
+impl TryFrom<Value> for Data {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Null => Ok(Data::None(vec![])),
+            Value::Bool(x) => Ok(Data::U32(x as u32)),
+            Value::Number(ref x) => {
+                if let Some(x) = x.as_u64() {
+                    return Ok(match u32::try_from(x) {
+                        Ok(x) => Data::U32(x),
+                        Err(_) => Data::U64(x),
+                    });
+                }
+
+                match x.as_i64() {
+                    Some(x) => Ok(match i32::try_from(x) {
+                        Ok(x) => Data::U32(x as u32),
+                        Err(_) => Data::U64(x as u64),
+                    }),
+                    None => Err(Error::Unsupported(value)),
+                }
+            }
+            Value::String(x) => Ok(Data::String(U16CString::from_str(x)?)),
+            Value::Array(ref items) => {
+                if let Some(strings) = items.iter().map(Value::as_str).collect::<Option<Vec<_>>>() {
+                    let strings = strings
+                        .into_iter()
+                        .map(U16CString::from_str)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    return Ok(Data::MultiString(strings));
+                }
+
+                match items.iter().map(as_byte).collect::<Option<Vec<_>>>() {
+                    Some(bytes) => Ok(Data::Binary(bytes)),
+                    None => Err(Error::Unsupported(value)),
+                }
+            }
+            Value::Object(_) => Err(Error::Unsupported(value)),
+        }
+    }
+}
+
+fn as_byte(value: &Value) -> Option<u8> {
+    value.as_u64().and_then(|x| u8::try_from(x).ok())
+}
+
+fn type_name(ty: RegType) -> &'static str {
+    match ty {
+        RegType::None => "REG_NONE",
+        RegType::String => "REG_SZ",
+        RegType::ExpandString => "REG_EXPAND_SZ",
+        RegType::Binary => "REG_BINARY",
+        RegType::U32 => "REG_DWORD",
+        RegType::U32BE => "REG_DWORD_BIG_ENDIAN",
+        RegType::Link => "REG_LINK",
+        RegType::MultiString => "REG_MULTI_SZ",
+        RegType::ResourceList => "REG_RESOURCE_LIST",
+        RegType::FullResourceDescriptor => "REG_FULL_RESOURCE_DESCRIPTOR",
+        RegType::ResourceRequirementsList => "REG_RESOURCE_REQUIREMENTS_LIST",
+        RegType::U64 => "REG_QWORD",
+    }
+}
+
+const TYPES: [RegType; 12] = [
+    RegType::None,
+    RegType::String,
+    RegType::ExpandString,
+    RegType::Binary,
+    RegType::U32,
+    RegType::U32BE,
+    RegType::Link,
+    RegType::MultiString,
+    RegType::ResourceList,
+    RegType::FullResourceDescriptor,
+    RegType::ResourceRequirementsList,
+    RegType::U64,
+];
+
+impl Data {
+    /// Converts to a JSON object holding the registry type name, such as `REG_SZ`, as
+    /// `type` and the natural JSON form of the data as `data`.
+    pub fn to_json_typed(&self) -> Value {
+        json!({
+            "type": type_name(self.as_type()),
+            "data": Value::from(self.clone()),
+        })
+    }
+
+    /// Converts from the typed JSON form produced by `to_json_typed`.
+    pub fn from_json_typed(value: &Value) -> Result<Data, Error> {
+        let name = value
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| Error::Unsupported(value.clone()))?;
+        let ty = TYPES
+            .iter()
+            .copied()
+            .find(|ty| type_name(*ty) == name)
+            .ok_or_else(|| Error::UnknownType(name.to_string()))?;
+        let data = value.get("data").cloned().unwrap_or(Value::Null);
+
+        let unsupported = || Error::Unsupported(data.clone());
+        let bytes = || {
+            data.as_array()
+                .and_then(|x| x.iter().map(as_byte).collect::<Option<Vec<_>>>())
+                .ok_or_else(unsupported)
+        };
+        let string = || {
+            data.as_str()
+                .ok_or_else(unsupported)
+                .and_then(|x| Ok(U16CString::from_str(x)?))
+        };
+        let number = || data.as_u64().or_else(|| data.as_i64().map(|x| x as u64));
+
+        Ok(match ty {
+            RegType::None if data.is_null() => Data::None(vec![]),
+            RegType::None => Data::None(bytes()?),
+            RegType::String => Data::String(string()?),
+            RegType::ExpandString => Data::ExpandString(string()?),
+            RegType::Binary => Data::Binary(bytes()?),
+            RegType::U32 | RegType::U32BE => {
+                let x = number()
+                    .and_then(|x| u32::try_from(x).ok())
+                    .ok_or_else(unsupported)?;
+                match ty {
+                    RegType::U32 => Data::U32(x),
+                    _ => Data::U32BE(x),
+                }
+            }
+            RegType::U64 => Data::U64(number().ok_or_else(unsupported)?),
+            RegType::MultiString => Data::MultiString(
+                data.as_array()
+                    .and_then(|x| x.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+                    .ok_or_else(unsupported)?
+                    .into_iter()
+                    .map(U16CString::from_str)
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            RegType::Link => Data::Link(bytes()?),
+            RegType::ResourceList => Data::ResourceList(bytes()?),
+            RegType::FullResourceDescriptor => Data::FullResourceDescriptor(bytes()?),
+            RegType::ResourceRequirementsList => Data::ResourceRequirementsList(bytes()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wide(s: &str) -> U16CString {
+        U16CString::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn natural_form() {
+        assert_eq!(Value::from(Data::String(wide("a"))), json!("a"));
+        assert_eq!(Value::from(Data::U32(1)), json!(1));
+        assert_eq!(
+            Value::from(Data::MultiString(vec![wide("a"), wide("b")])),
+            json!(["a", "b"])
+        );
+        assert_eq!(Value::from(Data::Binary(vec![1, 255])), json!([1, 255]));
+
+        assert_eq!(Data::try_from(json!(null)).unwrap(), Data::None(vec![]));
+        assert_eq!(Data::try_from(json!(true)).unwrap(), Data::U32(1));
+        assert_eq!(Data::try_from(json!(-1)).unwrap(), Data::U32(u32::MAX));
+        assert_eq!(
+            Data::try_from(json!(1u64 << 40)).unwrap(),
+            Data::U64(1 << 40)
+        );
+        assert_eq!(
+            Data::try_from(json!(["a"])).unwrap(),
+            Data::MultiString(vec![wide("a")])
+        );
+        assert_eq!(
+            Data::try_from(json!([1, 2])).unwrap(),
+            Data::Binary(vec![1, 2])
+        );
+        assert!(Data::try_from(json!(0.5)).is_err());
+        assert!(Data::try_from(json!({})).is_err());
+        assert!(Data::try_from(json!([1, "a"])).is_err());
+    }
+
+    #[test]
+    fn typed_form() {
+        let values = vec![
+            Data::None(vec![]),
+            Data::None(vec![7]),
+            Data::String(wide("a")),
+            Data::ExpandString(wide("%TEMP%")),
+            Data::Binary(vec![1, 2]),
+            Data::U32(1),
+            Data::U32BE(2),
+            Data::Link(vec![3]),
+            Data::MultiString(vec![wide("a"), wide("b")]),
+            Data::ResourceList(vec![4]),
+            Data::FullResourceDescriptor(vec![5]),
+            Data::ResourceRequirementsList(vec![6]),
+            Data::U64(u64::MAX),
+        ];
+
+        for data in values {
+            let json = data.to_json_typed();
+            assert_eq!(Data::from_json_typed(&json).unwrap(), data);
+        }
+
+        assert_eq!(
+            Data::String(wide("a")).to_json_typed(),
+            json!({"type": "REG_SZ", "data": "a"})
+        );
+        assert!(matches!(
+            Data::from_json_typed(&json!({"type": "REG_FOO", "data": 1})),
+            Err(Error::UnknownType(_))
+        ));
+    }
+}
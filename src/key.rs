@@ -1,20 +1,55 @@
 use std::{
-    convert::{Infallible, TryInto},
+    convert::{Infallible, TryFrom, TryInto},
+    ffi::OsString,
     fmt::Display,
     io,
+    path::PathBuf,
     ptr::null_mut,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use utfx::{U16CStr, U16CString};
-use winapi::shared::minwindef::HKEY;
+use winapi::shared::minwindef::{FARPROC, FILETIME, HKEY};
+use winapi::shared::winerror::{
+    ERROR_ALREADY_EXISTS, ERROR_CALL_NOT_IMPLEMENTED, ERROR_INVALID_PARAMETER, ERROR_NOT_SUPPORTED,
+    ERROR_NO_MORE_ITEMS,
+};
+use winapi::um::handleapi::{CloseHandle, DuplicateHandle};
+use winapi::um::libloaderapi::{GetModuleHandleA, GetProcAddress};
+use winapi::um::processthreadsapi::{GetCurrentProcess, OpenProcessToken};
+use winapi::um::securitybaseapi::GetTokenInformation;
+use winapi::um::winnt::{
+    TokenVirtualizationEnabled, DUPLICATE_SAME_ACCESS, HANDLE, REG_CREATED_NEW_KEY, REG_LINK,
+    TOKEN_QUERY,
+};
 use winapi::um::winreg::{
-    RegCloseKey, RegCreateKeyExW, RegDeleteKeyW, RegDeleteTreeW, RegOpenCurrentUser, RegOpenKeyExW,
-    RegSaveKeyExW,
+    RegCloseKey, RegConnectRegistryW, RegCopyTreeW, RegCreateKeyExW, RegCreateKeyTransactedW,
+    RegDeleteKeyExW, RegDeleteKeyTransactedW, RegDeleteTreeW, RegDisableReflectionKey,
+    RegEnableReflectionKey, RegEnumKeyExW, RegFlushKey, RegOpenCurrentUser, RegOpenKeyExW,
+    RegOpenKeyTransactedW, RegOpenUserClassesRoot, RegQueryInfoKeyW, RegQueryReflectionKey,
+    RegRestoreKeyW, RegSaveKeyExW,
 };
 
 use crate::iter;
-use crate::sec::Security;
-use crate::{value, Hive};
+use crate::sec::{
+    CreateOptions, Options, ReadOptions, RestoreFlags, Security, SecurityDescriptor, TypeFilter,
+    View,
+};
+use crate::{value, Coercion, Hive, RegType, Transaction, ValueReader, ValueWriter};
+
+#[link(name = "ntdll")]
+extern "system" {
+    fn NtQueryKey(
+        key_handle: HANDLE,
+        key_information_class: u32,
+        key_information: *mut std::ffi::c_void,
+        length: u32,
+        result_length: *mut u32,
+    ) -> i32;
+
+    fn RtlNtStatusToDosError(status: i32) -> u32;
+}
 
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
@@ -30,20 +65,21 @@ pub enum Error {
 
     #[error("An unknown IO error occurred for given path: {0:?}")]
     Unknown(String, #[source] io::Error),
+
+    #[error("Invalid security descriptor: {0:?}")]
+    InvalidSecurityDescriptor(String, #[source] io::Error),
 }
 
 impl Error {
-    #[cfg(test)]
     pub(crate) fn is_not_found(&self) -> bool {
-        match self {
-            Error::NotFound(_, _) => true,
-            _ => false,
-        }
+        matches!(self, Error::NotFound(..))
     }
 
-    fn from_code(code: i32, value_name: String) -> Self {
-        let err = io::Error::from_raw_os_error(code);
+    pub(crate) fn from_code(code: i32, value_name: String) -> Self {
+        Self::from_io(io::Error::from_raw_os_error(code), value_name)
+    }
 
+    pub(crate) fn from_io(err: io::Error, value_name: String) -> Self {
         return match err.kind() {
             io::ErrorKind::NotFound => Error::NotFound(value_name, err),
             io::ErrorKind::PermissionDenied => Error::PermissionDenied(value_name, err),
@@ -58,12 +94,185 @@ impl From<Infallible> for Error {
     }
 }
 
+/// Whether a key was created, or an existing key was opened, by a call to `create`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Disposition {
+    CreatedNewKey,
+    OpenedExistingKey,
+}
+
+impl Disposition {
+    #[inline]
+    fn from_raw(disposition: u32) -> Self {
+        if disposition == REG_CREATED_NEW_KEY {
+            Disposition::CreatedNewKey
+        } else {
+            Disposition::OpenedExistingKey
+        }
+    }
+}
+
+/// Where an entry is added to a list, such as by
+/// [`RegKey::multi_string_append_with`](struct.RegKey.html#method.multi_string_append_with).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Position {
+    Front,
+    Back,
+}
+
+/// Metadata about a key, returned by [`RegKey::info`](struct.RegKey.html#method.info).
+///
+/// Lengths of names are in UTF-16 code units, excluding the NUL terminator.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KeyInfo {
+    pub subkey_count: u32,
+    pub max_subkey_name_len: u32,
+    pub value_count: u32,
+    pub max_value_name_len: u32,
+    /// The size in bytes of the largest value's data.
+    pub max_value_data_len: u32,
+    /// The size in bytes of the key's security descriptor.
+    pub security_descriptor_len: u32,
+    pub last_write_time: SystemTime,
+}
+
+/// A uniquely named key created with [`RegKey::create_temp`](struct.RegKey.html#method.create_temp),
+/// which is deleted along with all of its subkeys when dropped.
+///
+/// Dereferences to the `RegKey` of the temporary key.
+#[derive(Debug)]
+pub struct TempKey {
+    parent: RegKey,
+    name: U16CString,
+    regkey: Option<RegKey>,
+}
+
+impl TempKey {
+    /// Deletes the key, returning any error that occurred.
+    pub fn delete(mut self) -> Result<(), Error> {
+        self.delete_inner()
+    }
+
+    /// Keeps the key rather than deleting it on drop, returning it.
+    pub fn persist(mut self) -> RegKey {
+        self.regkey.take().unwrap()
+    }
+
+    fn delete_inner(&mut self) -> Result<(), Error> {
+        match self.regkey.take() {
+            Some(regkey) => drop(regkey),
+            None => return Ok(()),
+        }
+
+        self.parent.delete(&self.name, true)
+    }
+}
+
+impl std::ops::Deref for TempKey {
+    type Target = RegKey;
+
+    fn deref(&self) -> &RegKey {
+        self.regkey.as_ref().unwrap()
+    }
+}
+
+impl Drop for TempKey {
+    fn drop(&mut self) {
+        // No point checking the return value here.
+        let _ = self.delete_inner();
+    }
+}
+
+/// The UAC registry virtualization state of a key, returned by
+/// [`RegKey::virtualization`](struct.RegKey.html#method.virtualization).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Virtualization {
+    /// The key is in a part of the registry that can be virtualized.
+    pub is_candidate: bool,
+    /// Virtualization is enabled for the key, so writes by processes without
+    /// administrator rights are redirected to the VirtualStore.
+    pub is_enabled: bool,
+    /// The key is a virtual key, stored in the VirtualStore.
+    pub is_virtual_target: bool,
+    /// The key is part of a VirtualStore.
+    pub is_virtual_store: bool,
+    /// The key has been virtualized at least once.
+    pub is_virtual_source: bool,
+}
+
+/// Totals for a key's tree, returned by [`RegKey::stats`](struct.RegKey.html#method.stats).
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    /// The number of keys in the tree, including its root.
+    pub key_count: u64,
+    pub value_count: u64,
+    /// The total size in bytes of all value data in the tree.
+    pub data_len: u64,
+}
+
+/// The number of 100 nanosecond `FILETIME` intervals from 1601-01-01 to the Unix epoch.
+const UNIX_EPOCH_INTERVALS: u64 = 116_444_736_000_000_000;
+
+/// Converts a `FILETIME` in 100 nanosecond intervals since 1601-01-01 to a `SystemTime`,
+/// or `None` if it is out of range.
+pub(crate) fn filetime_to_system_time(filetime: u64) -> Option<SystemTime> {
+    let duration = |x: u64| Duration::new(x / 10_000_000, (x % 10_000_000) as u32 * 100);
+
+    if filetime >= UNIX_EPOCH_INTERVALS {
+        UNIX_EPOCH.checked_add(duration(filetime - UNIX_EPOCH_INTERVALS))
+    } else {
+        UNIX_EPOCH.checked_sub(duration(UNIX_EPOCH_INTERVALS - filetime))
+    }
+}
+
+/// Converts a `SystemTime` to a `FILETIME`, or `None` if it is before 1601 or too far in
+/// the future.
+pub(crate) fn system_time_to_filetime(time: SystemTime) -> Option<u64> {
+    let intervals = |x: Duration| {
+        x.as_secs()
+            .checked_mul(10_000_000)?
+            .checked_add(x.subsec_nanos() as u64 / 100)
+    };
+
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(x) => UNIX_EPOCH_INTERVALS.checked_add(intervals(x)?),
+        Err(e) => UNIX_EPOCH_INTERVALS.checked_sub(intervals(e.duration())?),
+    }
+}
+
+/// The file format used when saving a key to a hive file.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SaveFormat {
+    /// The format supported by all versions of Windows since Windows 2000.
+    Standard = 1,
+    /// The latest format, which cannot be loaded by older versions of Windows.
+    Latest = 2,
+    /// The latest format without compression. Only supported for the roots of
+    /// `Hive::LocalMachine` and `Hive::Users` and of hives loaded under them.
+    NoCompression = 4,
+}
+
 /// The safe representation of a Windows registry key.
+///
+/// # Local and detached keys
+///
+/// A key opened from one of this process's predefined hives, such as
+/// `Hive::LocalMachine`, is local: its path is relative to that hive's root, so the key
+/// can be reopened or deleted by its path. Keys from `Hive::connect`,
+/// `Hive::open_current_user`, `Hive::open_user_classes_root`, `Hive::load_app_key` or
+/// `RegKey::from_raw` are detached, as their paths, if known at all, are relative to some
+/// other root. Methods that need a key's path only have its handle to work with for
+/// these.
 #[derive(Debug)]
 pub struct RegKey {
     pub(crate) hive: Hive,
     pub(crate) handle: HKEY,
     pub(crate) path: U16CString,
+    pub(crate) view: View,
+    /// Whether the key's path is relative to a root other than the process's own
+    /// predefined `hive`, such as a hive on a remote machine or of an impersonated user.
+    pub(crate) is_detached: bool,
 }
 
 impl Display for RegKey {
@@ -80,35 +289,149 @@ impl Display for RegKey {
     }
 }
 
+// SAFETY: registry key handles are kernel handles, which may be used and closed from
+// any thread, and the registry serializes concurrent calls on the same handle. The
+// other fields are only mutated through `&mut self`, and with `handle-tracking` the
+// handle is recorded in a `Mutex`-guarded map, so opening and dropping keys on
+// different threads is also sound.
+unsafe impl Send for RegKey {}
+unsafe impl Sync for RegKey {}
+
 impl Drop for RegKey {
     fn drop(&mut self) {
+        #[cfg(feature = "handle-tracking")]
+        crate::tracking::untrack(self);
+
         // No point checking the return value here.
         unsafe { RegCloseKey(self.handle) };
     }
 }
 
 impl RegKey {
+    pub(crate) fn new(
+        hive: Hive,
+        handle: HKEY,
+        path: U16CString,
+        view: View,
+        is_detached: bool,
+    ) -> RegKey {
+        let key = RegKey {
+            hive,
+            handle,
+            path,
+            view,
+            is_detached,
+        };
+
+        #[cfg(feature = "handle-tracking")]
+        crate::tracking::track(&key);
+
+        key
+    }
+
+    /// Takes ownership of a raw `HKEY`, closing it when the returned key is dropped.
+    ///
+    /// As the hive and path of the handle cannot be known, the key displays as `<Unknown>`.
+    ///
+    /// # Safety
+    ///
+    /// The handle must be a valid, open registry key handle that is not owned or
+    /// closed by anything else.
+    #[inline]
+    pub unsafe fn from_raw(handle: HKEY) -> RegKey {
+        RegKey::new(
+            Hive::Unknown,
+            handle,
+            U16CString::default(),
+            View::Default,
+            false,
+        )
+    }
+
+    /// Returns the raw `HKEY` of this key. The handle remains owned by this `RegKey`.
+    #[inline]
+    pub fn as_raw(&self) -> HKEY {
+        self.handle
+    }
+
+    /// Consumes this key, returning the raw `HKEY` without closing it.
+    ///
+    /// The caller becomes responsible for closing the handle with `RegCloseKey`.
+    #[inline]
+    pub fn into_raw(self) -> HKEY {
+        #[cfg(feature = "handle-tracking")]
+        crate::tracking::untrack(&self);
+
+        let mut this = std::mem::ManuallyDrop::new(self);
+        // SAFETY: `this` is never used again and will not be dropped, so the path
+        // must be dropped here to avoid leaking it.
+        unsafe { std::ptr::drop_in_place(&mut this.path) };
+        this.handle
+    }
+
+    /// Creates a new `RegKey` for the same key by duplicating this key's handle, which
+    /// unlike reopening it by path cannot fail if the key has since been renamed or its
+    /// security changed.
+    ///
+    /// Keys from `Hive::connect` cannot be duplicated.
+    pub fn try_clone(&self) -> Result<RegKey, Error> {
+        let process = unsafe { GetCurrentProcess() };
+        let mut handle = null_mut();
+        let result = unsafe {
+            DuplicateHandle(
+                process,
+                self.handle as HANDLE,
+                process,
+                &mut handle,
+                0,
+                0,
+                DUPLICATE_SAME_ACCESS,
+            )
+        };
+
+        if result == 0 {
+            return Err(Error::from_io(io::Error::last_os_error(), self.to_string()));
+        }
+
+        Ok(RegKey::new(
+            self.hive,
+            handle as HKEY,
+            self.path.clone(),
+            self.view,
+            self.is_detached,
+        ))
+    }
+
     #[inline]
     pub fn open<P>(&self, path: P, sec: Security) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        self.open_with_options(path, sec, Options::empty())
+    }
+
+    /// Opens a subkey with the given options, such as `Options::BackupRestore`.
+    #[inline]
+    pub fn open_with_options<P>(
+        &self,
+        path: P,
+        sec: Security,
+        options: Options,
+    ) -> Result<RegKey, Error>
     where
         P: TryInto<U16CString>,
         P::Error: Into<Error>,
     {
         let path = path.try_into().map_err(Into::into)?;
-        open_hkey(self.handle, &path, sec).map(|handle| {
-            let joined_path = format!(
-                r"{}\{}",
-                self.path.to_string().unwrap(),
-                path.to_string().unwrap()
-            );
-            RegKey {
-                hive: self.hive,
-                handle,
-                path: joined_path.try_into().unwrap(),
-            }
-        })
+        let sec = self.view.apply(sec);
+        open_hkey(self.handle, &path, sec, options).map(|handle| self.subkey(handle, &path, sec))
     }
 
+    /// Saves this key to a hive file without compression.
+    ///
+    /// This format is only supported for the roots of hives, so use
+    /// [`RegKey::save_to`](#method.save_to) to save any other key.
     #[inline]
     pub fn write<P>(&self, file_path: P) -> Result<(), Error>
     where
@@ -116,28 +439,195 @@ impl RegKey {
         P::Error: Into<Error>,
     {
         let path = file_path.try_into().map_err(Into::into)?;
-        save_hkey(self.handle, &path)
+        save_hkey(self.handle, &path, SaveFormat::NoCompression)
+    }
+
+    /// Saves this key and its subkeys to a hive file in the latest format, so that a
+    /// single subtree can be backed up without saving its whole hive. The file must
+    /// not already exist, and can be loaded again with `Hive::load_app_key` or
+    /// [`RegKey::restore`](#method.restore).
+    ///
+    /// The calling process must hold `SeBackupPrivilege`.
+    pub fn save_to<P: AsRef<std::path::Path>>(&self, file_path: P) -> Result<(), Error> {
+        let path = U16CString::from_os_str(file_path.as_ref().as_os_str())?;
+        save_hkey(self.handle, &path, SaveFormat::Latest)
+    }
+
+    /// Saves this key and its subkeys to a hive file in the given format, using
+    /// `RegSaveKeyExW`. The file must not already exist.
+    ///
+    /// The calling process must hold `SeBackupPrivilege`.
+    #[inline]
+    pub fn save<P>(&self, file_path: P, format: SaveFormat) -> Result<(), Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = file_path.try_into().map_err(Into::into)?;
+        save_hkey(self.handle, &path, format)
+    }
+
+    /// Replaces this key and its subkeys with the contents of a hive file, such as one
+    /// written by [`RegKey::save`](#method.save), using `RegRestoreKeyW`.
+    ///
+    /// The calling process must hold `SeRestorePrivilege` and `SeBackupPrivilege`.
+    #[inline]
+    pub fn restore<P>(&self, file_path: P, flags: RestoreFlags) -> Result<(), Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = file_path.try_into().map_err(Into::into)?;
+        let result = unsafe { RegRestoreKeyW(self.handle, path.as_ptr(), flags.bits()) };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        Err(Error::from_code(result, path.to_string_lossy()))
     }
 
     #[inline]
     pub fn create<P>(&self, path: P, sec: Security) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        self.create_with_options(path, sec, Options::empty())
+    }
+
+    /// Creates or opens a subkey with the given options, such as `Options::Volatile` or
+    /// `Options::BackupRestore`.
+    #[inline]
+    pub fn create_with_options<P>(
+        &self,
+        path: P,
+        sec: Security,
+        options: Options,
+    ) -> Result<RegKey, Error>
     where
         P: TryInto<U16CString>,
         P::Error: Into<Error>,
     {
         let path = path.try_into().map_err(Into::into)?;
-        create_hkey(self.handle, &path, sec).map(|handle| {
-            let joined_path = format!(
-                r"{}\{}",
-                self.path.to_string().unwrap(),
-                path.to_string().unwrap()
-            );
-            RegKey {
-                hive: self.hive,
-                handle,
-                path: joined_path.try_into().unwrap(),
+        let sec = self.view.apply(sec);
+        create_hkey(self.handle, &path, sec, options)
+            .map(|(handle, _)| self.subkey(handle, &path, sec))
+    }
+
+    /// Creates or opens a subkey, also returning whether it was newly created.
+    #[inline]
+    pub fn create_with_disposition<P>(
+        &self,
+        path: P,
+        sec: Security,
+    ) -> Result<(RegKey, Disposition), Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let sec = self.view.apply(sec);
+        create_hkey(self.handle, &path, sec, Options::empty())
+            .map(|(handle, disposition)| (self.subkey(handle, &path, sec), disposition))
+    }
+
+    /// Creates or opens a subkey, creating any missing intermediate keys with the
+    /// separate access rights and security descriptor given in the options.
+    pub fn create_with<P>(&self, path: P, opts: &CreateOptions) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let sec = self.view.apply(opts.security);
+        create_hkey_with(self.handle, &path, opts, self.view)
+            .map(|(handle, _)| self.subkey(handle, &path, sec))
+    }
+
+    /// Creates a uniquely named subkey that is deleted, along with all of its subkeys,
+    /// when the returned `TempKey` is dropped, even if the thread panics.
+    pub fn create_temp(&self, sec: Security) -> Result<TempKey, Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.subsec_nanos());
+        // Cloned first, so that failing to clone cannot leave the created key behind.
+        let parent = self.try_clone()?;
+
+        loop {
+            let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+            let name = format!("tmp-{}-{}-{}", std::process::id(), nanos, count);
+            let name = U16CString::from_str(name)?;
+            let (regkey, disposition) = self.create_with_disposition(&name, sec)?;
+
+            if disposition == Disposition::CreatedNewKey {
+                return Ok(TempKey {
+                    parent,
+                    name,
+                    regkey: Some(regkey),
+                });
             }
-        })
+        }
+    }
+
+    /// Creates a subkey that is a symbolic link to the given target, which must be an
+    /// absolute NT registry path such as `\REGISTRY\MACHINE\SOFTWARE\Example`.
+    ///
+    /// Opening the link, or paths beneath it, opens the target instead.
+    pub fn create_link<P, T>(&self, path: P, target: T) -> Result<RegKey, crate::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+        T: TryInto<U16CString>,
+        T::Error: Into<Error>,
+    {
+        let target = target.try_into().map_err(Into::into)?;
+        let regkey = self.create_with_options(
+            path,
+            Security::CreateLink | Security::SetValue,
+            Options::CreateLink,
+        )?;
+
+        let bytes = target
+            .as_slice()
+            .iter()
+            .flat_map(|x| x.to_le_bytes().to_vec())
+            .collect::<Vec<u8>>();
+        value::set_value_raw(regkey.handle, &link_value_name(), REG_LINK, &bytes)?;
+        Ok(regkey)
+    }
+
+    /// Reads the target of the symbolic link at the given subkey path.
+    pub fn read_link<P>(&self, path: P) -> Result<U16CString, crate::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let regkey = self.open_with_options(path, Security::QueryValue, Options::OpenLink)?;
+
+        // Link targets are not usually NUL terminated, but stop at one if present.
+        let (_, buf, _) = value::query_value_raw(regkey.handle, &link_value_name())?;
+        let len = buf.iter().position(|x| *x == 0).unwrap_or(buf.len());
+        Ok(U16CString::new(&buf[..len]).map_err(Error::InvalidNul)?)
+    }
+
+    /// Checks whether the subkey at the given path is a symbolic link, without following
+    /// it. Use [`Hive::split_nt_path`](enum.Hive.html#method.split_nt_path) to map the
+    /// target returned by `read_link` back to a hive and path.
+    pub fn is_link<P>(&self, path: P) -> Result<bool, crate::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let regkey = self.open_with_options(path, Security::QueryValue, Options::OpenLink)?;
+
+        match value::query_value_info(regkey.handle, &link_value_name()) {
+            Ok((ty, _)) => Ok(ty == REG_LINK),
+            Err(e) if e.is_not_found() => Ok(false),
+            Err(e) => Err(e.into()),
+        }
     }
 
     #[inline]
@@ -147,12 +637,89 @@ impl RegKey {
         P::Error: Into<Error>,
     {
         let path = path.try_into().map_err(Into::into)?;
-        delete_hkey(self.handle, path, is_recursive)
+        delete_hkey(self.handle, path, self.view, is_recursive)
+    }
+
+    /// Deletes a subkey and all of its subkeys one at a time, calling `progress` with
+    /// the path of each key as it is deleted, relative to this key. Subkeys are deleted
+    /// before their parents.
+    ///
+    /// Returning `false` from `progress` stops the deletion, leaving any remaining keys
+    /// in place, in which case this returns `Ok(false)`.
+    pub fn delete_with_progress<P, F>(&self, path: P, mut progress: F) -> Result<bool, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+        F: FnMut(&U16CStr) -> bool,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        delete_hkey_with_progress(self.handle, &path, self.view, &mut progress)
+    }
+
+    /// Opens a subkey as part of the given transaction.
+    #[inline]
+    pub fn open_transacted<P>(
+        &self,
+        path: P,
+        sec: Security,
+        transaction: &Transaction,
+    ) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let sec = self.view.apply(sec);
+        open_hkey_transacted(self.handle, &path, sec, transaction)
+            .map(|handle| self.subkey(handle, &path, sec))
+    }
+
+    /// Creates or opens a subkey as part of the given transaction.
+    #[inline]
+    pub fn create_transacted<P>(
+        &self,
+        path: P,
+        sec: Security,
+        transaction: &Transaction,
+    ) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        let sec = self.view.apply(sec);
+        create_hkey_transacted(self.handle, &path, sec, transaction)
+            .map(|handle| self.subkey(handle, &path, sec))
     }
 
+    /// Deletes a subkey as part of the given transaction.
     #[inline]
+    pub fn delete_transacted<P>(
+        &self,
+        path: P,
+        is_recursive: bool,
+        transaction: &Transaction,
+    ) -> Result<(), Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        delete_hkey_transacted(self.handle, path, self.view, is_recursive, transaction)
+    }
+
+    /// Deletes this key, consuming it. The key is deleted in the same view it was opened with.
+    ///
+    /// [Local keys](#local-and-detached-keys) are deleted by their path relative to the
+    /// hive root, so no handle to the parent is needed, and detached keys through their
+    /// own handle.
     pub fn delete_self(self, is_recursive: bool) -> Result<(), Error> {
-        delete_hkey(self.handle, U16CString::default(), is_recursive)
+        match self.root_hkey() {
+            Some(root) if !self.path.is_empty() => {
+                delete_hkey(root, &self.path, self.view, is_recursive)
+            }
+            _ => delete_hkey(self.handle, U16CString::default(), self.view, is_recursive),
+        }
     }
 
     #[inline]
@@ -164,152 +731,4450 @@ impl RegKey {
         value::query_value(self.handle, value_name)
     }
 
+    /// Reads the unnamed default value of this key, shown as `(Default)` in regedit.
+    ///
+    /// Under `HKEY_CLASSES_ROOT`, this is where ProgIDs, verbs and CLSIDs keep their payload.
     #[inline]
-    pub fn delete_value<S>(&self, value_name: S) -> Result<(), value::Error>
-    where
-        S: TryInto<U16CString>,
-        S::Error: Into<value::Error>,
-    {
-        value::delete_value(self.handle, value_name)
+    pub fn default_value(&self) -> Result<value::Data, value::Error> {
+        self.value("")
     }
 
+    /// Writes the unnamed default value of this key.
     #[inline]
-    pub fn set_value<S>(&self, value_name: S, data: &value::Data) -> Result<(), value::Error>
+    pub fn set_default_value<T>(&self, data: &T) -> Result<(), value::Error>
     where
-        S: TryInto<U16CString>,
-        S::Error: Into<value::Error>,
+        T: value::ToRegValue + ?Sized,
     {
-        value::set_value(self.handle, value_name, data)
+        self.set_value("", data)
     }
 
+    /// Opens a subkey, only creating it if it does not exist.
+    ///
+    /// Unlike `create`, this does not need `Security::CreateSubKey` access to this key
+    /// when the subkey already exists.
     #[inline]
-    pub fn keys(&self) -> iter::Keys<'_> {
-        match iter::Keys::new(self) {
-            Ok(v) => v,
-            Err(e) => unreachable!(e),
+    pub fn open_or_create<P>(&self, path: P, sec: Security) -> Result<RegKey, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let path = path.try_into().map_err(Into::into)?;
+        match self.open(&path, sec) {
+            Err(e) if e.is_not_found() => self.create(path, sec),
+            result => result,
         }
     }
 
+    /// Returns whether a subkey exists at the given path.
+    ///
+    /// Errors other than the subkey not being found, such as being denied access, are
+    /// returned as errors.
     #[inline]
-    pub fn values(&self) -> iter::Values<'_> {
-        match iter::Values::new(self) {
-            Ok(v) => v,
-            Err(e) => unreachable!(e),
+    pub fn subkey_exists<P>(&self, path: P) -> Result<bool, Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        match self.open(path, Security::QueryValue) {
+            Ok(_) => Ok(true),
+            Err(e) if e.is_not_found() => Ok(false),
+            Err(e) => Err(e),
         }
     }
 
-    pub fn open_current_user(sec: Security) -> Result<RegKey, Error> {
-        let mut hkey = null_mut();
+    /// Deletes every subkey that has no values and no subkeys, returning the number of
+    /// keys deleted. This key itself is never deleted.
+    ///
+    /// If `is_recursive` is true, the whole tree is pruned from the bottom up, so keys
+    /// that only contain empty subkeys are deleted as well.
+    pub fn prune_empty(&self, is_recursive: bool) -> Result<usize, crate::Error> {
+        // Deleting a subkey shifts the indices of the rest, so collect their names first.
+        let names = self
+            .keys()
+            .map(|key| key.map(|key| key.name().to_ucstring()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut count = 0;
 
-        let result = unsafe { RegOpenCurrentUser(sec.bits(), &mut hkey) };
+        for name in names {
+            let child = self.open(&name, Security::Read)?;
 
-        if result == 0 {
-            // TODO: use NT API to query path
-            return Ok(RegKey {
-                hive: Hive::CurrentUser,
-                handle: hkey,
-                path: "".try_into().unwrap(),
-            });
+            if is_recursive {
+                count += child.prune_empty(true)?;
+            }
+
+            let info = child.info()?;
+            if info.subkey_count == 0 && info.value_count == 0 {
+                drop(child);
+                self.delete(&name, false)?;
+                count += 1;
+            }
         }
 
-        let path = "<current user>".to_string();
-        Err(Error::from_code(result, path))
+        Ok(count)
     }
-}
 
-#[inline]
-pub(crate) fn open_hkey<'a, P>(base: HKEY, path: P, sec: Security) -> Result<HKEY, Error>
-where
-    P: AsRef<U16CStr>,
-{
-    let path = path.as_ref();
-    let mut hkey = std::ptr::null_mut();
-    let result = unsafe { RegOpenKeyExW(base, path.as_ptr(), 0, sec.bits(), &mut hkey) };
+    /// Finds a subkey whose name matches the given name case-insensitively, returning
+    /// its name as stored in the registry.
+    pub fn find_subkey<S: AsRef<str>>(&self, name: S) -> Result<Option<U16CString>, crate::Error> {
+        let name = name.as_ref().encode_utf16().collect::<Vec<_>>();
+        let mut keys = self.keys();
 
-    if result == 0 {
-        return Ok(hkey);
+        while let Some(key) = keys.next_name() {
+            let key = key?;
+            if cmp_ignore_case(key.as_slice(), &name) == std::cmp::Ordering::Equal {
+                return Ok(Some(key.to_ucstring()));
+            }
+        }
+
+        Ok(None)
     }
 
-    let path = path.to_string_lossy();
-    Err(Error::from_code(result, path))
-}
+    /// Finds a value whose name matches the given name case-insensitively, returning
+    /// its name as stored in the registry.
+    pub fn find_value<S: AsRef<str>>(&self, name: S) -> Result<Option<U16CString>, crate::Error> {
+        let name = name.as_ref().encode_utf16().collect::<Vec<_>>();
+        let mut names = self.value_names();
 
-#[inline]
-pub(crate) fn save_hkey<'a, P>(hkey: HKEY, path: P) -> Result<(), Error>
-where
-    P: AsRef<U16CStr>,
-{
-    let path = path.as_ref();
-    let result = unsafe { RegSaveKeyExW(hkey, path.as_ptr(), std::ptr::null_mut(), 4) };
+        while let Some(value_name) = names.next_name() {
+            let value_name = value_name?;
+            if cmp_ignore_case(value_name.as_slice(), &name) == std::cmp::Ordering::Equal {
+                return Ok(Some(value_name.to_ucstring()));
+            }
+        }
 
-    if result == 0 {
-        return Ok(());
+        Ok(None)
     }
 
-    let path = path.to_string_lossy();
-    Err(Error::from_code(result, path))
+    /// Returns whether a value with the given name exists on this key.
+    #[inline]
+    pub fn value_exists<S>(&self, value_name: S) -> Result<bool, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::value_exists(self.handle, value_name)
+    }
+
+    #[inline]
+    pub fn delete_value<S>(&self, value_name: S) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::delete_value(self.handle, value_name)
+    }
+
+    /// Renames a value by writing its type and data under the new name and then deleting
+    /// the old one.
+    ///
+    /// Fails with `value::Error::AlreadyExists` if a value with the new name exists, unless
+    /// `overwrite` is set. The registry has no native rename for values, so other readers may
+    /// briefly see both names.
+    pub fn rename_value<S, T>(
+        &self,
+        old_name: S,
+        new_name: T,
+        overwrite: bool,
+    ) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: TryInto<U16CString>,
+        T::Error: Into<value::Error>,
+    {
+        let old_name = old_name.try_into().map_err(Into::into)?;
+        let new_name = new_name.try_into().map_err(Into::into)?;
+
+        // Names are case-insensitive, so this may only change the case.
+        let same_name =
+            cmp_ignore_case(old_name.as_slice(), new_name.as_slice()) == std::cmp::Ordering::Equal;
+
+        let (ty, data) = self.value_raw(&*old_name)?;
+        if !overwrite && !same_name && self.value_exists(&*new_name)? {
+            return Err(value::Error::AlreadyExists(new_name.to_string_lossy()));
+        }
+
+        if same_name {
+            return self.change_value_name_case(
+                &old_name,
+                &new_name,
+                ty,
+                &data,
+                value::set_value_raw,
+            );
+        }
+
+        value::set_value_raw(self.handle, &new_name, ty, &data)?;
+        value::delete_value(self.handle, &*old_name)
+    }
+
+    /// Changes only the case of a value's name. Writing the value under the new name
+    /// would keep the old case, so it is deleted first, and if `write` then fails, it is
+    /// written back under its old name.
+    fn change_value_name_case<F>(
+        &self,
+        old_name: &U16CStr,
+        new_name: &U16CStr,
+        ty: u32,
+        data: &[u8],
+        write: F,
+    ) -> Result<(), value::Error>
+    where
+        F: Fn(HKEY, &U16CStr, u32, &[u8]) -> Result<(), value::Error>,
+    {
+        value::delete_value(self.handle, old_name)?;
+
+        if let Err(e) = write(self.handle, new_name, ty, data) {
+            let _ = value::set_value_raw(self.handle, old_name, ty, data);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Reads a value of the given subkey, using `RegGetValueW`, without opening the
+    /// subkey first.
+    #[inline]
+    pub fn get_value_at<P, S>(&self, subkey: P, value_name: S) -> Result<value::Data, value::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<value::Error>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_value_at_with_filter(subkey, value_name, TypeFilter::Any)
+    }
+
+    /// Reads a value of the given subkey, failing with `value::Error::TypeMismatch`
+    /// if it is not one of the types in the filter.
+    pub fn get_value_at_with_filter<P, S>(
+        &self,
+        subkey: P,
+        value_name: S,
+        filter: TypeFilter,
+    ) -> Result<value::Data, value::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<value::Error>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_value_at_with_options(subkey, value_name, &filter.into())
+    }
+
+    /// Reads a value of the subkey at the given path with `RegGetValueW`, restricting its
+    /// type and expanding environment variables as given in the options.
+    pub fn get_value_at_with_options<P, S>(
+        &self,
+        subkey: P,
+        value_name: S,
+        opts: &ReadOptions,
+    ) -> Result<value::Data, value::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<value::Error>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let subkey = subkey.try_into().map_err(Into::into)?;
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let flags = opts.rrf_flags() | self.view.rrf_flags();
+        value::get_value(self.handle, &subkey, &value_name, flags)
+    }
+
+    /// Reads a value with `RegGetValueW`, restricting its type and expanding environment
+    /// variables as given in the options.
+    ///
+    /// ```ignore
+    /// let opts = ReadOptions { types: TypeFilter::U32, ..Default::default() };
+    /// let port = regkey.value_with_options("Port", &opts)?;
+    /// ```
+    #[inline]
+    pub fn value_with_options<S>(
+        &self,
+        value_name: S,
+        opts: &ReadOptions,
+    ) -> Result<value::Data, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_value_at_with_options(U16CString::default(), value_name, opts)
+    }
+
+    /// Replaces the data of a value, returning its previous data if it existed.
+    ///
+    /// The new data is first written to a temporary value beside it, named after the
+    /// value with a `~replace` suffix that no other value has, and only then over the
+    /// value itself, after which the temporary value is deleted. A write that fails, for
+    /// example by exceeding the registry quota, therefore fails before the value is
+    /// touched. A single write to a registry value is atomic, so readers see either the
+    /// old data or the new data in full, never a partially written value.
+    ///
+    /// [Local keys](#local-and-detached-keys) are reopened in the same view as part of one
+    /// `Transaction`, so the temporary value is never visible outside it and is not left
+    /// behind if any step fails. Detached keys, and keys whose path now refers to a
+    /// different key, are written through their own handle without one. In either case
+    /// other writers are not blocked, so a change to the value made between reading the
+    /// previous data and writing the new data is lost.
+    pub fn replace_value<S>(
+        &self,
+        value_name: S,
+        data: &value::Data,
+    ) -> Result<Option<value::Data>, crate::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let to_error = |err| Error::from_io(err, self.to_string());
+        let transaction = Transaction::new().map_err(to_error)?;
+        let sec = Security::QueryValue | Security::SetValue;
+        let handle = match self.reopen_transacted(sec, &transaction)? {
+            Some(handle) => handle,
+            None => return replace_value_hkey(self.handle, &value_name, data),
+        };
+
+        let result = replace_value_hkey(handle, &value_name, data);
+        unsafe { RegCloseKey(handle) };
+
+        let old = result?;
+        transaction.commit().map_err(to_error)?;
+        Ok(old)
+    }
+
+    /// Writes several values, stopping at the first that fails. Values written before the
+    /// failure are kept; use `set_values_transacted` to write all of them or none.
+    ///
+    /// ```ignore
+    /// regkey.set_values(vec![
+    ///     ("DisplayName", Data::String("My App".try_into()?)),
+    ///     ("EstimatedSize", Data::U32(1024)),
+    /// ])?;
+    /// ```
+    pub fn set_values<I, S, T>(&self, values: I) -> Result<(), value::Error>
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: value::ToRegValue,
+    {
+        for (value_name, data) in values {
+            self.set_value(value_name, &data)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes several values as part of one `Transaction`, so either all of them are
+    /// written or, if any fails, none are.
+    ///
+    /// The key is reopened in the same view as part of the transaction. [Detached
+    /// keys](#local-and-detached-keys) cannot be reopened, nor can keys whose path now
+    /// refers to a different key, for example because they were renamed. These fail with
+    /// `key::Error::Unknown` without writing anything.
+    pub fn set_values_transacted<I, S, T>(&self, values: I) -> Result<(), crate::Error>
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: value::ToRegValue,
+    {
+        let values = values
+            .into_iter()
+            .map(|(value_name, data)| {
+                let value_name = value_name.try_into().map_err(Into::into)?;
+                Ok((value_name, data.to_reg_value()?))
+            })
+            .collect::<Result<Vec<_>, value::Error>>()?;
+
+        let to_error = |err| Error::from_io(err, self.to_string());
+        let transaction = Transaction::new().map_err(to_error)?;
+        let handle = self
+            .reopen_transacted(Security::SetValue, &transaction)?
+            .ok_or_else(|| Error::from_code(ERROR_NOT_SUPPORTED as i32, self.to_string()))?;
+
+        let result = values
+            .iter()
+            .try_for_each(|(value_name, data)| value::set_value(handle, &**value_name, data));
+        unsafe { RegCloseKey(handle) };
+
+        result?;
+        transaction.commit().map_err(to_error)?;
+        Ok(())
+    }
+
+    /// Reopens this key by its path in the same view as part of the given transaction.
+    ///
+    /// Returns `None` if this key has no local root, or if the reopened handle refers to
+    /// a different key than this one.
+    fn reopen_transacted(
+        &self,
+        sec: Security,
+        transaction: &Transaction,
+    ) -> Result<Option<HKEY>, Error> {
+        let root = match self.root_hkey() {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+
+        let handle = open_hkey_transacted(root, &self.path, self.view.apply(sec), transaction)?;
+        let is_same = query_key_name(self.handle, &self.path)
+            .and_then(|name| Ok(name == query_key_name(handle, &self.path)?));
+
+        match is_same {
+            Ok(true) => Ok(Some(handle)),
+            result => {
+                unsafe { RegCloseKey(handle) };
+                result.map(|_| None)
+            }
+        }
+    }
+
+    /// Reads several values of this key at once, using `RegQueryMultipleValuesW`, so that
+    /// no other writer can change them between reads. The data is returned in the same
+    /// order as the names.
+    ///
+    /// If any of the values does not exist, this fails with `value::Error::NotFound`.
+    pub fn get_values<S>(&self, value_names: &[S]) -> Result<Vec<value::Data>, value::Error>
+    where
+        S: AsRef<str>,
+    {
+        let value_names = value_names
+            .iter()
+            .map(U16CString::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        value::query_values(self.handle, &value_names)
+    }
+
+    /// Sets a value of the given subkey, using `RegSetKeyValueW`, without opening the
+    /// subkey first. The subkey is created if it does not exist.
+    #[inline]
+    pub fn set_value_at<P, S>(
+        &self,
+        subkey: P,
+        value_name: S,
+        data: &value::Data,
+    ) -> Result<(), value::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<value::Error>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::set_value_at(self.handle, subkey, value_name, data)
+    }
+
+    /// Deletes a value of the given subkey, using `RegDeleteKeyValueW`, without
+    /// opening the subkey first.
+    #[inline]
+    pub fn delete_value_at<P, S>(&self, subkey: P, value_name: S) -> Result<(), value::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<value::Error>,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::delete_value_at(self.handle, subkey, value_name)
+    }
+
+    /// Deletes every value of this key, including the default value, leaving its
+    /// subkeys intact.
+    #[inline]
+    pub fn clear_values(&self) -> Result<(), value::Error> {
+        value::clear_values(self.handle)
+    }
+
+    /// Reads a value's raw type code, such as `REG_RESOURCE_LIST`, and its data exactly
+    /// as stored, including types that `Data` does not know about.
+    pub fn value_raw<S>(&self, value_name: S) -> Result<(u32, Vec<u8>), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let (ty, buf, len) = value::query_value_raw(self.handle, &value_name)?;
+        let mut bytes = value::u16_to_u8_vec(buf);
+        bytes.truncate(len);
+        Ok((ty, bytes))
+    }
+
+    /// Returns a value's type and the length of its data in bytes, without reading the
+    /// data, so large values can be skipped or read in a suitable way.
+    ///
+    /// Values of types unknown to this crate fail with `value::Error::UnhandledType`; use
+    /// `value_raw` to read those.
+    pub fn value_info<S>(&self, value_name: S) -> Result<(RegType, usize), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let (ty, len) = value::query_value_info(self.handle, &value_name)?;
+        let ty = RegType::try_from(ty).map_err(|_| value::Error::UnhandledType(ty))?;
+        Ok((ty, len))
+    }
+
+    /// Reads a value's raw data into `buf`, replacing its contents, and returns the
+    /// value's type and the length of its data in bytes.
+    ///
+    /// The allocation of `buf` is reused and only grows when a value does not fit, so one
+    /// buffer can be used to read many values without allocating for each. Values of types
+    /// unknown to this crate fail with `value::Error::UnhandledType` after their data has
+    /// been read.
+    pub fn read_value_into<S>(
+        &self,
+        value_name: S,
+        buf: &mut Vec<u8>,
+    ) -> Result<(RegType, usize), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let ty = value::query_value_into(self.handle, &value_name, buf)?;
+        let ty = RegType::try_from(ty).map_err(|_| value::Error::UnhandledType(ty))?;
+        Ok((ty, buf.len()))
+    }
+
+    /// Opens a value's raw data for reading through `std::io::Read`.
+    #[inline]
+    pub fn open_value_reader<S>(&self, value_name: S) -> Result<ValueReader, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.open_value_reader_with_buffer(value_name, Vec::new())
+    }
+
+    /// Opens a value's raw data for reading, reusing the allocation of `buf`, such as one
+    /// returned by `ValueReader::into_buffer`.
+    pub fn open_value_reader_with_buffer<S>(
+        &self,
+        value_name: S,
+        mut buf: Vec<u8>,
+    ) -> Result<ValueReader, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let ty = value::query_value_into(self.handle, &value_name, &mut buf)?;
+        Ok(ValueReader::new(ty, buf))
+    }
+
+    /// Creates a writer for a `REG_BINARY` value, which is set when `ValueWriter::finish`
+    /// is called.
+    pub fn create_value_writer<S>(&self, value_name: S) -> Result<ValueWriter<'_>, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        Ok(ValueWriter::new(self, value_name, Vec::new()))
+    }
+
+    /// Writes a value with the given raw type code and data, without any conversion.
+    #[inline]
+    pub fn set_value_raw<S>(&self, value_name: S, ty: u32, data: &[u8]) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        value::set_value_raw(self.handle, &value_name, ty, data)
+    }
+
+    /// Replaces a value's raw data, keeping its existing type, so values written by other
+    /// software can be patched without, for example, turning a `REG_EXPAND_SZ` into a
+    /// `REG_SZ`. Returns the raw type code the data was written with.
+    ///
+    /// Fails with `value::Error::NotFound` if the value does not exist. The type is read
+    /// and the data written in separate calls, so a concurrent writer may change the type
+    /// in between.
+    pub fn update_value<S>(&self, value_name: S, data: &[u8]) -> Result<u32, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let (ty, _) = value::query_value_info(self.handle, &value_name)?;
+        value::set_value_raw(self.handle, &value_name, ty, data)?;
+        Ok(ty)
+    }
+
+    /// Reads a `REG_SZ` or `REG_EXPAND_SZ` value as an `OsString`, which never fails on
+    /// UTF-16 that is not valid Unicode, such as unpaired surrogates written by other tools.
+    /// Values of other types fail with `value::Error::TypeMismatch`.
+    #[inline]
+    pub fn get_os_string<S>(&self, value_name: S) -> Result<OsString, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_value(value_name)
+    }
+
+    /// Reads a `REG_SZ` or `REG_EXPAND_SZ` value as a `String`, replacing unpaired
+    /// surrogates and a dangling odd byte with U+FFFD instead of failing, for displaying
+    /// values that other software has left slightly corrupt. The string ends at the first
+    /// nul, where reading it as `Data` fails instead. Values of other types fail with
+    /// `value::Error::TypeMismatch`.
+    #[inline]
+    pub fn get_string_lossy<S>(&self, value_name: S) -> Result<String, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        value::query_string_lossy(self.handle, &value_name)
+    }
+
+    /// Reads a `REG_SZ` or `REG_EXPAND_SZ` value as a `PathBuf`, like `get_os_string`.
+    /// Environment variables in `REG_EXPAND_SZ` values are not expanded.
+    #[inline]
+    pub fn get_path<S>(&self, value_name: S) -> Result<PathBuf, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_value(value_name)
+    }
+
+    /// Writes a value of the given type with no data, as some Windows components use the
+    /// presence of such a value as a flag. Use `RegType::None` for a `REG_NONE` value.
+    ///
+    /// Reading such a value back gives empty data of its type, or zero for numbers, so use
+    /// `value_info` to tell it apart from a value holding zero.
+    #[inline]
+    pub fn set_empty_value<S>(&self, value_name: S, ty: RegType) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.set_value_raw(value_name, ty as u32, &[])
+    }
+
+    /// Reads a `REG_DWORD` or `REG_DWORD_BIG_ENDIAN` value, decoded with its byte order.
+    /// Values of other types fail with `value::Error::TypeMismatch`.
+    #[inline]
+    pub fn get_u32<S>(&self, value_name: S) -> Result<u32, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::query_u32(self.handle, value_name)
+    }
+
+    /// Reads a `REG_QWORD` value, also accepting a `REG_DWORD` or an 8-byte little-endian
+    /// `REG_BINARY` value as written by some tools. Values of other types fail with
+    /// `value::Error::TypeMismatch`.
+    #[inline]
+    pub fn get_u64<S>(&self, value_name: S) -> Result<u64, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::query_u64(self.handle, value_name)
+    }
+
+    /// Writes a `REG_QWORD` value.
+    #[inline]
+    pub fn set_u64<S>(&self, value_name: S, value: u64) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.set_value(value_name, &value::Data::U64(value))
+    }
+
+    /// Reads a `REG_DWORD` value as the `i32` with the same two's complement bits, so
+    /// `0xFFFFFFFF` reads as -1. Values of other types fail with
+    /// `value::Error::TypeMismatch`.
+    #[inline]
+    pub fn get_i32<S>(&self, value_name: S) -> Result<i32, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_value(value_name)
+    }
+
+    /// Writes an `i32` as a `REG_DWORD` with the same two's complement bits.
+    #[inline]
+    pub fn set_i32<S>(&self, value_name: S, value: i32) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.set_value(value_name, &value)
+    }
+
+    /// Reads a `REG_QWORD` value as the `i64` with the same two's complement bits. A
+    /// `REG_DWORD` value is read as an `i32` and sign-extended. Values of other types fail
+    /// with `value::Error::TypeMismatch`.
+    #[inline]
+    pub fn get_i64<S>(&self, value_name: S) -> Result<i64, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_value(value_name)
+    }
+
+    /// Writes an `i64` as a `REG_QWORD` with the same two's complement bits.
+    #[inline]
+    pub fn set_i64<S>(&self, value_name: S, value: i64) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.set_value(value_name, &value)
+    }
+
+    /// Reads a boolean flag from a `REG_DWORD` or `REG_QWORD` value, where any non-zero
+    /// number is true, or from a `REG_SZ` value of `true`, `false`, `1` or `0` in any case.
+    /// Other values fail with `value::Error::TypeMismatch`.
+    #[inline]
+    pub fn get_bool<S>(&self, value_name: S) -> Result<bool, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::query_bool(self.handle, value_name)
+    }
+
+    /// Writes a boolean flag as a `REG_DWORD` of 0 or 1.
+    #[inline]
+    pub fn set_bool<S>(&self, value_name: S, value: bool) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.set_value(value_name, &value)
+    }
+
+    /// Reads a GUID, such as a CLSID, from a `REG_SZ` value with or without braces, or
+    /// from a 16-byte `REG_BINARY` value in the layout of a Windows `GUID`. Values of other
+    /// types fail with `value::Error::TypeMismatch`.
+    #[cfg(feature = "uuid")]
+    #[inline]
+    pub fn get_guid<S>(&self, value_name: S) -> Result<uuid::Uuid, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::query_guid(self.handle, value_name)
+    }
+
+    /// Writes a GUID in the given format.
+    #[cfg(feature = "uuid")]
+    #[inline]
+    pub fn set_guid<S>(
+        &self,
+        value_name: S,
+        guid: uuid::Uuid,
+        format: value::GuidFormat,
+    ) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.set_value(value_name, &value::guid_data(&guid, format))
+    }
+
+    /// Reads a timestamp stored as a `FILETIME` in an 8-byte `REG_BINARY` or a `REG_QWORD`
+    /// value, as many Windows components do. Values of other types fail with
+    /// `value::Error::TypeMismatch`.
+    pub fn get_filetime<S>(&self, value_name: S) -> Result<SystemTime, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let filetime = value::query_filetime(self.handle, &value_name)?;
+        filetime_to_system_time(filetime)
+            .ok_or_else(|| value::Error::InvalidFiletime(value_name.to_string_lossy()))
+    }
+
+    /// Reads a `FILETIME` timestamp, like `get_filetime`, as a `chrono::DateTime`.
+    #[cfg(feature = "chrono")]
+    #[inline]
+    pub fn get_filetime_utc<S>(
+        &self,
+        value_name: S,
+    ) -> Result<chrono::DateTime<chrono::Utc>, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        self.get_filetime(value_name).map(Into::into)
+    }
+
+    /// Writes a timestamp as a `FILETIME` in an 8-byte `REG_BINARY` value. This accepts a
+    /// `chrono::DateTime` as well as a `SystemTime`.
+    ///
+    /// Times before 1601 fail with `value::Error::InvalidFiletime`.
+    pub fn set_filetime<S, T>(&self, value_name: S, time: T) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: Into<SystemTime>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let filetime = system_time_to_filetime(time.into())
+            .ok_or_else(|| value::Error::InvalidFiletime(value_name.to_string_lossy()))?;
+        self.set_value(value_name, &filetime.to_le_bytes()[..])
+    }
+
+    /// Reads a value as a `RegEnum`, failing with `value::Error::UnknownVariant`, which
+    /// lists the accepted data, if it matches no variant.
+    pub fn get_enum<T, S>(&self, value_name: S) -> Result<T, value::Error>
+    where
+        T: value::RegEnum,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        value::query_enum(self.handle, &value_name)
+    }
+
+    /// Writes a `RegEnum` variant as declared by its mapping. Variants missing from the
+    /// mapping fail with `value::Error::UnknownVariant`.
+    pub fn set_enum<T, S>(&self, value_name: S, variant: T) -> Result<(), value::Error>
+    where
+        T: value::RegEnum,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        match value::enum_data(&variant) {
+            Some(data) => self.set_value(value_name, &data),
+            None => Err(value::Error::UnknownVariant(
+                value_name.to_string_lossy(),
+                "an unmapped variant".into(),
+                T::MAPPING
+                    .iter()
+                    .map(|(_, repr)| repr.to_string())
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Reads a `REG_EXPAND_SZ` or `REG_SZ` value as a string. With `Expand::Yes`,
+    /// environment variables in a `REG_EXPAND_SZ` value are expanded using
+    /// `ExpandEnvironmentStringsW`. Values of other types fail with
+    /// `value::Error::TypeMismatch`.
+    #[inline]
+    pub fn get_expand_string<S>(
+        &self,
+        value_name: S,
+        expand: value::Expand,
+    ) -> Result<String, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::query_expand_string(self.handle, value_name, expand)
+    }
+
+    /// Reads a `REG_MULTI_SZ` value as a list of strings, failing with
+    /// `value::Error::TypeMismatch` if the value has another type.
+    ///
+    /// Missing terminators are tolerated, and empty strings within the list are kept.
+    #[inline]
+    pub fn get_multi_string<S>(&self, value_name: S) -> Result<Vec<String>, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        value::query_multi_string(self.handle, value_name)
+    }
+
+    /// Writes a list of strings as a `REG_MULTI_SZ` value. An empty list is written as
+    /// a single terminator.
+    ///
+    /// Empty strings are written as given, but many readers, including `regedit`,
+    /// treat them as the end of the list.
+    #[inline]
+    pub fn set_multi_string<S, T>(&self, value_name: S, strings: &[T]) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: AsRef<str>,
+    {
+        value::set_multi_string(self.handle, value_name, strings)
+    }
+
+    /// Checks whether a `REG_MULTI_SZ` value contains the given string, comparing exactly.
+    /// A missing value contains nothing, while a value of another type fails with
+    /// `value::Error::TypeMismatch`.
+    pub fn multi_string_contains<S, T>(&self, value_name: S, item: T) -> Result<bool, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: AsRef<str>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let item = U16CString::from_str(item.as_ref())?;
+        let strings = self.query_multi_string_or_empty(&value_name)?;
+        Ok(strings.contains(&item))
+    }
+
+    /// Appends a string to the end of a `REG_MULTI_SZ` value, unless it is already
+    /// present. See `multi_string_append_with` for details.
+    #[inline]
+    pub fn multi_string_append<S, T>(&self, value_name: S, item: T) -> Result<bool, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: AsRef<str>,
+    {
+        self.multi_string_append_with(value_name, item, Position::Back, false)
+    }
+
+    /// Adds a string to a `REG_MULTI_SZ` value at the given position, creating the value
+    /// if it does not exist, and returns whether it was added. Unless `allow_duplicates` is
+    /// set, nothing is written if the string is already present.
+    ///
+    /// The value is read and written in separate calls, so concurrent edits by another
+    /// writer may be lost. Strings in the list are kept as stored, even if they are not
+    /// valid UTF-16.
+    pub fn multi_string_append_with<S, T>(
+        &self,
+        value_name: S,
+        item: T,
+        position: Position,
+        allow_duplicates: bool,
+    ) -> Result<bool, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: AsRef<str>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let item = U16CString::from_str(item.as_ref())?;
+        let mut strings = self.query_multi_string_or_empty(&value_name)?;
+
+        if !allow_duplicates && strings.contains(&item) {
+            return Ok(false);
+        }
+
+        match position {
+            Position::Front => strings.insert(0, item),
+            Position::Back => strings.push(item),
+        }
+        value::set_value(self.handle, &value_name, &value::Data::MultiString(strings))?;
+        Ok(true)
+    }
+
+    /// Removes every occurrence of a string from a `REG_MULTI_SZ` value, keeping the
+    /// order of the rest, and returns how many were removed. Nothing is written if the
+    /// string is not present, including when the value does not exist.
+    pub fn multi_string_remove<S, T>(&self, value_name: S, item: T) -> Result<usize, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: AsRef<str>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let item = U16CString::from_str(item.as_ref())?;
+        let mut strings = self.query_multi_string_or_empty(&value_name)?;
+
+        let len = strings.len();
+        strings.retain(|x| *x != item);
+        let removed = len - strings.len();
+
+        if removed > 0 {
+            value::set_value(self.handle, &value_name, &value::Data::MultiString(strings))?;
+        }
+        Ok(removed)
+    }
+
+    /// Adds an entry to a semicolon separated list, such as `Path` in
+    /// `HKEY_CURRENT_USER\Environment`, and returns whether it was added. Nothing is
+    /// written if the entry is already present, compared case-insensitively.
+    ///
+    /// The value keeps its type of `REG_SZ` or `REG_EXPAND_SZ`, and is created as a
+    /// `REG_EXPAND_SZ` if it does not exist. Empty entries are dropped when the list is
+    /// written back. Entries that are empty or contain a semicolon fail with
+    /// `value::Error::InvalidInput`.
+    pub fn path_list_add<S, T>(
+        &self,
+        value_name: S,
+        entry: T,
+        position: Position,
+    ) -> Result<bool, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: AsRef<str>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let (is_expand, mut entries) = self.query_path_list(&value_name)?;
+
+        let entry = entry.as_ref();
+        if entry.is_empty() || entry.contains(';') {
+            return Err(value::Error::InvalidInput(format!(
+                "path list entry {:?}",
+                entry
+            )));
+        }
+
+        let entry = entry.encode_utf16().collect::<Vec<_>>();
+        if entries.iter().any(|x| path_entry_eq(x, &entry)) {
+            return Ok(false);
+        }
+
+        match position {
+            Position::Front => entries.insert(0, entry),
+            Position::Back => entries.push(entry),
+        }
+        self.set_path_list(&value_name, is_expand, &entries)?;
+        Ok(true)
+    }
+
+    /// Removes every entry matching `entry` case-insensitively from a semicolon separated
+    /// list, keeping the value's type, and returns how many were removed. Nothing is written
+    /// if no entry matches, including when the value does not exist.
+    pub fn path_list_remove<S, T>(&self, value_name: S, entry: T) -> Result<usize, value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: AsRef<str>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let (is_expand, mut entries) = self.query_path_list(&value_name)?;
+
+        let entry = entry.as_ref().encode_utf16().collect::<Vec<_>>();
+        let len = entries.len();
+        entries.retain(|x| !path_entry_eq(x, &entry));
+        let removed = len - entries.len();
+
+        if removed > 0 {
+            self.set_path_list(&value_name, is_expand, &entries)?;
+        }
+        Ok(removed)
+    }
+
+    /// Reads a semicolon separated list as whether it is a `REG_EXPAND_SZ` and its
+    /// non-empty entries.
+    fn query_path_list(&self, value_name: &U16CStr) -> Result<(bool, Vec<Vec<u16>>), value::Error> {
+        let (is_expand, s) = match value::query_value(self.handle, value_name) {
+            Ok(value::Data::ExpandString(s)) => (true, s),
+            Ok(value::Data::String(s)) => (false, s),
+            Ok(_) => return Err(value::Error::unexpected_type().with_value_name(value_name)),
+            Err(e) if e.is_not_found() => return Ok((true, vec![])),
+            Err(e) => return Err(e),
+        };
+
+        let entries = s
+            .as_slice()
+            .split(|x| *x == b';' as u16)
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_vec())
+            .collect();
+        Ok((is_expand, entries))
+    }
+
+    fn set_path_list(
+        &self,
+        value_name: &U16CStr,
+        is_expand: bool,
+        entries: &[Vec<u16>],
+    ) -> Result<(), value::Error> {
+        let s = U16CString::new(entries.join(&(b';' as u16)))?;
+        let data = if is_expand {
+            value::Data::ExpandString(s)
+        } else {
+            value::Data::String(s)
+        };
+        value::set_value(self.handle, value_name, &data)
+    }
+
+    fn query_multi_string_or_empty(
+        &self,
+        value_name: &U16CStr,
+    ) -> Result<Vec<U16CString>, value::Error> {
+        match value::query_value(self.handle, value_name) {
+            Ok(value::Data::MultiString(strings)) => Ok(strings),
+            Ok(_) => Err(value::Error::unexpected_type().with_value_name(value_name)),
+            Err(e) if e.is_not_found() => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes a value, which may be `Data` or any type implementing `ToRegValue`, such
+    /// as `u32`, `String` or `Vec<String>`.
+    #[inline]
+    pub fn set_value<S, T>(&self, value_name: S, data: &T) -> Result<(), value::Error>
+    where
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+        T: value::ToRegValue + ?Sized,
+    {
+        value::set_value(self.handle, value_name, &data.to_reg_value()?)
+    }
+
+    /// Reads a value as any type implementing `FromRegValue`, such as `u32`, `String` or
+    /// `Vec<String>`, failing with `value::Error::TypeMismatch` if the value's type
+    /// does not match.
+    pub fn get_value<T, S>(&self, value_name: S) -> Result<T, value::Error>
+    where
+        T: value::FromRegValue,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let data = value::query_value(self.handle, &value_name)?;
+        T::from_reg_value(data).map_err(|e| e.with_value_name(&value_name))
+    }
+
+    /// Reads a value like `get_value`, matching the value's type to `T` with the given
+    /// coercion policy. `Coercion::Strict` only accepts `T`'s own registry type, while
+    /// `Coercion::Lenient` also converts between string and number types.
+    pub fn get_value_with_coercion<T, S>(
+        &self,
+        value_name: S,
+        coercion: Coercion,
+    ) -> Result<T, value::Error>
+    where
+        T: value::FromRegValue,
+        S: TryInto<U16CString>,
+        S::Error: Into<value::Error>,
+    {
+        let value_name = value_name.try_into().map_err(Into::into)?;
+        let data = value::query_value(self.handle, &value_name)?;
+        T::from_reg_value_with(data, coercion).map_err(|e| e.with_value_name(&value_name))
+    }
+
+    /// Queries metadata about this key, such as its number of subkeys and values, using
+    /// `RegQueryInfoKeyW`.
+    pub fn info(&self) -> Result<KeyInfo, Error> {
+        let mut subkey_count = 0u32;
+        let mut max_subkey_name_len = 0u32;
+        let mut value_count = 0u32;
+        let mut max_value_name_len = 0u32;
+        let mut max_value_data_len = 0u32;
+        let mut security_descriptor_len = 0u32;
+        let mut last_write_time = FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+
+        let result = unsafe {
+            RegQueryInfoKeyW(
+                self.handle,
+                null_mut(),
+                null_mut(),
+                null_mut(),
+                &mut subkey_count,
+                &mut max_subkey_name_len,
+                null_mut(),
+                &mut value_count,
+                &mut max_value_name_len,
+                &mut max_value_data_len,
+                &mut security_descriptor_len,
+                &mut last_write_time,
+            )
+        };
+
+        if result != 0 {
+            return Err(Error::from_code(result, self.to_string()));
+        }
+
+        let last_write_time =
+            (last_write_time.dwHighDateTime as u64) << 32 | last_write_time.dwLowDateTime as u64;
+
+        Ok(KeyInfo {
+            subkey_count,
+            max_subkey_name_len,
+            value_count,
+            max_value_name_len,
+            max_value_data_len,
+            security_descriptor_len,
+            last_write_time: filetime_to_system_time(last_write_time).unwrap_or(UNIX_EPOCH),
+        })
+    }
+
+    /// The time this key, or any of its values, was last modified.
+    #[inline]
+    pub fn last_write_time(&self) -> Result<SystemTime, Error> {
+        self.info().map(|info| info.last_write_time)
+    }
+
+    /// The time this key, or any of its values, was last modified, as a `chrono::DateTime`.
+    #[cfg(feature = "chrono")]
+    #[inline]
+    pub fn last_write_time_utc(&self) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+        self.last_write_time().map(Into::into)
+    }
+
+    /// Queries the UAC registry virtualization state of this key, using `NtQueryKey`.
+    pub fn virtualization(&self) -> Result<Virtualization, Error> {
+        const KEY_VIRTUALIZATION_INFORMATION: u32 = 12;
+
+        let mut flags = 0u32;
+        let mut len = 0u32;
+        let status = unsafe {
+            NtQueryKey(
+                self.handle as HANDLE,
+                KEY_VIRTUALIZATION_INFORMATION,
+                &mut flags as *mut u32 as *mut _,
+                std::mem::size_of::<u32>() as u32,
+                &mut len,
+            )
+        };
+
+        if status < 0 {
+            let code = unsafe { RtlNtStatusToDosError(status) };
+            return Err(Error::from_code(code as i32, self.to_string()));
+        }
+
+        Ok(Virtualization {
+            is_candidate: flags & 0x1 != 0,
+            is_enabled: flags & 0x2 != 0,
+            is_virtual_target: flags & 0x4 != 0,
+            is_virtual_store: flags & 0x8 != 0,
+            is_virtual_source: flags & 0x10 != 0,
+        })
+    }
+
+    /// Whether writes to this key by the current process are redirected to the
+    /// VirtualStore by UAC registry virtualization, which needs it enabled both for the
+    /// key and for the process token.
+    pub fn is_virtualized(&self) -> Result<bool, Error> {
+        let virtualization = self.virtualization()?;
+
+        if !(virtualization.is_candidate && virtualization.is_enabled) {
+            return Ok(false);
+        }
+
+        token_virtualization_enabled().map_err(|e| Error::from_io(e, self.to_string()))
+    }
+
+    /// Writes all changes to this key to disk, using `RegFlushKey`.
+    ///
+    /// The registry normally writes changes lazily, so this should only be used when
+    /// a write must be persisted before continuing, as it can be slow.
+    #[inline]
+    pub fn flush(&self) -> Result<(), Error> {
+        let result = unsafe { RegFlushKey(self.handle) };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        Err(Error::from_code(result, self.to_string()))
+    }
+
+    /// Disables WOW64 registry reflection for this key, using `RegDisableReflectionKey`.
+    #[inline]
+    pub fn disable_reflection(&self) -> Result<(), Error> {
+        let result = unsafe { RegDisableReflectionKey(self.handle) };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        Err(Error::from_code(result, self.to_string()))
+    }
+
+    /// Restores WOW64 registry reflection for this key, using `RegEnableReflectionKey`.
+    #[inline]
+    pub fn enable_reflection(&self) -> Result<(), Error> {
+        let result = unsafe { RegEnableReflectionKey(self.handle) };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        Err(Error::from_code(result, self.to_string()))
+    }
+
+    /// Returns whether WOW64 registry reflection is disabled for this key, using
+    /// `RegQueryReflectionKey`.
+    #[inline]
+    pub fn is_reflection_disabled(&self) -> Result<bool, Error> {
+        let mut is_disabled = 0;
+        let result = unsafe { RegQueryReflectionKey(self.handle, &mut is_disabled) };
+
+        if result == 0 {
+            return Ok(is_disabled != 0);
+        }
+
+        Err(Error::from_code(result, self.to_string()))
+    }
+
+    /// Iterates over this key's immediate subkeys using `RegEnumKeyExW`, yielding each
+    /// subkey's name and last write time.
+    #[inline]
+    pub fn keys(&self) -> iter::Keys<'_> {
+        iter::Keys::new(self).unwrap_or_else(|_| iter::Keys::unbounded(self))
+    }
+
+    /// Iterates over this key's immediate subkeys, opening each with `sec`.
+    ///
+    /// ```ignore
+    /// for service in services.open_keys(Security::Read) {
+    ///     let service = service?;
+    ///     println!("{}: {:?}", service, service.value("ImagePath"));
+    /// }
+    /// ```
+    #[inline]
+    pub fn open_keys(&self, sec: Security) -> iter::OpenKeys<'_> {
+        iter::OpenKeys::new(self, sec)
+    }
+
+    #[inline]
+    pub fn values(&self) -> iter::Values<'_> {
+        iter::Values::new(self).unwrap_or_else(|_| iter::Values::unbounded(self))
+    }
+
+    /// Iterates over the names of this key's values without reading their data, which is
+    /// faster when the data is not needed.
+    #[inline]
+    pub fn value_names(&self) -> iter::ValueNames<'_> {
+        iter::ValueNames::new(self)
+    }
+
+    /// Iterates over this key's values as their names, raw type codes and raw data,
+    /// including values of types unknown to this crate.
+    #[inline]
+    pub fn raw_values(&self) -> iter::RawValues<'_> {
+        iter::RawValues::new(self)
+    }
+
+    /// Iterates over this key's values as their names, types and sizes, without reading
+    /// their data until asked, so large values can be skipped cheaply.
+    ///
+    /// ```ignore
+    /// for value in key.lazy_values() {
+    ///     let value = value?;
+    ///     if value.size() < 1024 {
+    ///         println!("{}: {}", value.name().to_string_lossy(), value.read()?);
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn lazy_values(&self) -> iter::LazyValues<'_> {
+        iter::LazyValues::new(self)
+    }
+
+    /// Iterates over the paths of the keys beneath this one matching a case-insensitive
+    /// glob pattern, where `*` matches any run of characters and `?` any one character.
+    ///
+    /// Each backslash separated component of the pattern matches the subkeys at that
+    /// depth, so `Uninstall\*` yields every subkey of `Uninstall`. Paths are relative to
+    /// this key, and subkeys that cannot be opened are reported as errors.
+    pub fn keys_matching(&self, pattern: &str) -> iter::KeysMatching<'_> {
+        let components = pattern
+            .split('\\')
+            .filter(|x| !x.is_empty())
+            .map(|x| iter::Matcher::Glob(iter::Glob::new(x)))
+            .collect();
+        iter::KeysMatching::new(self, components)
+    }
+
+    /// Iterates over the paths of the keys beneath this one matching a regular expression
+    /// per path component, like `keys_matching`, so the components `^Uninstall$` and `.*`
+    /// yield every subkey of `Uninstall`.
+    ///
+    /// ```ignore
+    /// let components = [Regex::new("^Uninstall$")?, Regex::new("^App")?];
+    /// for path in key.keys_matching_regex(&components) {
+    ///     println!("{}", path?);
+    /// }
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn keys_matching_regex(&self, components: &[regex::Regex]) -> iter::KeysMatching<'_> {
+        let components = components
+            .iter()
+            .cloned()
+            .map(iter::Matcher::Regex)
+            .collect();
+        iter::KeysMatching::new(self, components)
+    }
+
+    /// Iterates over the values of this key whose names match a case-insensitive glob
+    /// pattern, like `keys_matching`. The data of other values is never read.
+    pub fn values_matching(&self, pattern: &str) -> iter::ValuesMatching<'_> {
+        iter::ValuesMatching::new(self, iter::Matcher::Glob(iter::Glob::new(pattern)))
+    }
+
+    /// Iterates over the values of this key whose names match a regular expression.
+    #[cfg(feature = "regex")]
+    pub fn values_matching_regex(&self, regex: &regex::Regex) -> iter::ValuesMatching<'_> {
+        iter::ValuesMatching::new(self, iter::Matcher::Regex(regex.clone()))
+    }
+
+    /// Iterates depth-first over every key beneath this one, optionally with their values.
+    /// Subkeys that cannot be opened or read, such as for lack of access, are skipped or
+    /// reported according to `opts.on_error`.
+    #[inline]
+    pub fn walk(&self, opts: crate::WalkOptions) -> iter::Walk<'_> {
+        iter::Walk::new(self, opts)
+    }
+
+    /// Searches the keys beneath this one for key names, value names or value data
+    /// containing text, like regedit's Find, yielding matches as they are found.
+    ///
+    /// ```ignore
+    /// let query = Query {
+    ///     value_names: false,
+    ///     max_depth: Some(2),
+    ///     ..Query::new("python")
+    /// };
+    /// for found in key.search(query) {
+    ///     println!("{:?}", found?);
+    /// }
+    /// ```
+    #[inline]
+    pub fn search(&self, query: crate::Query) -> iter::Search<'_> {
+        iter::Search::new(self, query)
+    }
+
+    /// Walks the subtree beneath this key like `walk`, opening and reading subkeys on
+    /// multiple threads with [`rayon`](https://docs.rs/rayon).
+    ///
+    /// Keys are yielded in no particular order, and `OnError::Stop` reports errors like
+    /// `OnError::Report` rather than ending the walk, as other threads may have already
+    /// moved on.
+    #[cfg(feature = "rayon")]
+    #[inline]
+    pub fn par_walk(
+        &self,
+        opts: crate::WalkOptions,
+    ) -> impl rayon::iter::ParallelIterator<Item = Result<crate::WalkEntry, crate::Error>> + '_
+    {
+        iter::walk::par_walk(self, opts)
+    }
+
+    /// Wraps a handle opened relative to this key, with its path joined onto this key's.
+    fn subkey(&self, handle: HKEY, path: &U16CStr, sec: Security) -> RegKey {
+        RegKey::new(
+            self.hive,
+            handle,
+            join_path(&self.path, path),
+            View::from_security(sec),
+            self.is_detached,
+        )
+    }
+
+    /// The name of this key, which is the last component of its path. This is empty for
+    /// the root of a hive.
+    pub fn name(&self) -> &U16CStr {
+        let start = match self.separator_index() {
+            Some(i) => i + 1,
+            None => 0,
+        };
+
+        // SAFETY: this is a suffix of the path, so is still NUL terminated.
+        unsafe { U16CStr::from_slice_with_nul_unchecked(&self.path.as_slice_with_nul()[start..]) }
+    }
+
+    /// Opens the parent of this key by its path, in the same view.
+    ///
+    /// Returns `None` if this key is the root of its hive, or was not opened from a
+    /// predefined hive, such as keys from `Hive::load_app_key` or `RegKey::from_raw`.
+    /// Keys from `Hive::connect` also have no parent, as their paths are relative
+    /// to the remote machine's hive.
+    pub fn parent(&self, sec: Security) -> Result<Option<RegKey>, Error> {
+        if self.path.is_empty() || self.root_hkey().is_none() {
+            return Ok(None);
+        }
+
+        self.hive
+            .open(self.parent_path(), self.view.apply(sec))
+            .map(Some)
+    }
+
+    /// The handle of the local predefined hive this key's path is relative to, if any.
+    ///
+    /// This is `None` for keys beneath `Hive::open_current_user` and
+    /// `Hive::open_user_classes_root`, as their root is not the process's own hive.
+    #[inline]
+    fn root_hkey(&self) -> Option<HKEY> {
+        match self.hive {
+            _ if self.is_detached => None,
+            hive => hive.as_hkey(),
+        }
+    }
+
+    /// The path of this key's parent, which is empty if the parent is the hive root.
+    fn parent_path(&self) -> U16CString {
+        let end = self.separator_index().unwrap_or(0);
+
+        // SAFETY: this is a prefix of the path, so cannot contain a NUL.
+        unsafe { U16CString::from_vec_unchecked(&self.path.as_slice()[..end]) }
+    }
+
+    #[inline]
+    fn separator_index(&self) -> Option<usize> {
+        self.path
+            .as_slice()
+            .iter()
+            .rposition(|x| *x == b'\\' as u16)
+    }
+
+    /// Copies all values and subkeys of this key into the destination key, using
+    /// `RegCopyTreeW`. Existing values in the destination with the same names are
+    /// overwritten.
+    ///
+    /// This key must be opened with `Security::Read`, and the destination with
+    /// `Security::Write`.
+    pub fn copy_tree_to(&self, dest: &RegKey) -> Result<(), Error> {
+        let result = unsafe { RegCopyTreeW(self.handle, std::ptr::null(), dest.handle) };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        Err(Error::from_code(result, self.to_string()))
+    }
+
+    /// Walks this key's tree, counting its keys and values and the total size of their
+    /// data.
+    pub fn stats(&self) -> Result<TreeStats, crate::Error> {
+        let info = self.info()?;
+        let mut stats = TreeStats {
+            key_count: 1,
+            value_count: info.value_count as u64,
+            data_len: value::total_data_len(self.handle)?,
+        };
+
+        for key in self.keys() {
+            let child = key?.open(Security::Read)?.stats()?;
+            stats.key_count += child.key_count;
+            stats.value_count += child.value_count;
+            stats.data_len += child.data_len;
+        }
+
+        Ok(stats)
+    }
+
+    /// Merges the tree of `src` into this key, copying any values and subkeys that are
+    /// missing here. Values that exist in both with different data are resolved by the
+    /// policy. Nothing is deleted from this key.
+    #[inline]
+    pub fn merge_from(&self, src: &RegKey, policy: crate::MergePolicy) -> Result<(), crate::Error> {
+        crate::diff::merge(self, src, policy)
+    }
+
+    /// Moves this key and all of its values and subkeys to a new path, which may be in
+    /// a different hive, returning the key at its new location. The destination is
+    /// created in the same view as this key.
+    ///
+    /// As no single Win32 call can do this, the key is copied with `RegCopyTreeW`, the
+    /// copy is checked to have the same [`stats`](#method.stats), and then the
+    /// original is deleted. If any step fails, the copy is deleted again, and if the
+    /// original was partially deleted, it is first restored from the copy.
+    ///
+    /// The destination must not already exist. [Detached keys](#local-and-detached-keys)
+    /// cannot be restored if deleting them fails, so fail with `key::Error::Unknown`
+    /// without moving anything.
+    pub fn move_to<P>(self, dest_hive: Hive, dest_path: P) -> Result<RegKey, crate::Error>
+    where
+        P: TryInto<U16CString>,
+        P::Error: Into<Error>,
+    {
+        let dest_path = dest_path.try_into().map_err(Into::into)?;
+        let root = self
+            .root_hkey()
+            .ok_or_else(|| Error::from_code(ERROR_NOT_SUPPORTED as i32, self.to_string()))?;
+
+        if Some(root) == dest_hive.as_hkey() && self.contains_path(&dest_path, self.view) {
+            let err = Error::from_code(ERROR_INVALID_PARAMETER as i32, dest_path.to_string_lossy());
+            return Err(err.into());
+        }
+
+        let sec = self.view.apply(Security::AllAccess);
+        let (dest, disposition) = dest_hive.create_with_disposition(&dest_path, sec)?;
+        if disposition == Disposition::OpenedExistingKey {
+            let err = Error::from_code(ERROR_ALREADY_EXISTS as i32, dest.to_string());
+            return Err(err.into());
+        }
+
+        if let Err(err) = self.copy_and_verify(&dest) {
+            let _ = dest.delete_self(true);
+            return Err(err);
+        }
+
+        let path = self.path.clone();
+
+        if let Err(err) = self.delete_self(true) {
+            if let Ok((handle, _)) = create_hkey(root, &path, sec, Options::empty()) {
+                unsafe { RegCopyTreeW(dest.handle, std::ptr::null(), handle) };
+                unsafe { RegCloseKey(handle) };
+            }
+
+            let _ = dest.delete_self(true);
+            return Err(err.into());
+        }
+
+        Ok(dest)
+    }
+
+    /// Whether the given path, relative to the hive root and in the given view, is this
+    /// key or one beneath it.
+    fn contains_path(&self, path: &U16CStr, view: View) -> bool {
+        const SEPARATOR: u16 = b'\\' as u16;
+
+        if view != self.view {
+            return false;
+        }
+
+        let (this, path) = (self.path.as_slice(), path.as_slice());
+        if this.is_empty() {
+            return true;
+        }
+
+        path.len() >= this.len()
+            && cmp_ignore_case(&path[..this.len()], this) == std::cmp::Ordering::Equal
+            && matches!(path.get(this.len()), None | Some(&SEPARATOR))
+    }
+
+    fn copy_and_verify(&self, dest: &RegKey) -> Result<(), crate::Error> {
+        self.copy_tree_to(dest)?;
+
+        if self.stats()? != dest.stats()? {
+            let err = io::Error::other("copied tree does not match");
+            return Err(Error::from_io(err, dest.to_string()).into());
+        }
+
+        Ok(())
+    }
+
+    /// Renames this key within its parent, using `RegRenameKey`.
+    ///
+    /// If `RegRenameKey` is not available, the key is instead copied to the new name
+    /// with `RegCopyTreeW` and then deleted, which requires its parent to be openable
+    /// with [`RegKey::parent`](#method.parent). This key then refers to the copy.
+    pub fn rename<N>(&mut self, new_name: N) -> Result<(), Error>
+    where
+        N: TryInto<U16CString>,
+        N::Error: Into<Error>,
+    {
+        let new_name = new_name.try_into().map_err(Into::into)?;
+        let rename_key = match reg_rename_key() {
+            Some(v) => v,
+            None => return self.rename_by_copy(&new_name),
+        };
+        let result = unsafe { rename_key(self.handle, std::ptr::null(), new_name.as_ptr()) };
+
+        if result != 0 {
+            return Err(Error::from_code(result, self.to_string()));
+        }
+
+        self.path = join_path(&self.parent_path(), &new_name);
+        Ok(())
+    }
+
+    fn rename_by_copy(&mut self, new_name: &U16CStr) -> Result<(), Error> {
+        let parent = match self.parent(Security::AllAccess)? {
+            Some(v) => v,
+            None => {
+                let code = ERROR_CALL_NOT_IMPLEMENTED as i32;
+                return Err(Error::from_code(code, self.to_string()));
+            }
+        };
+
+        let (new_key, disposition) =
+            parent.create_with_disposition(new_name, Security::AllAccess)?;
+        if disposition != Disposition::CreatedNewKey {
+            let code = ERROR_ALREADY_EXISTS as i32;
+            return Err(Error::from_code(code, new_key.to_string()));
+        }
+
+        if let Err(e) = self.copy_tree_to(&new_key) {
+            let _ = delete_hkey(parent.handle, new_name, parent.view, true);
+            return Err(e);
+        }
+
+        delete_hkey(parent.handle, self.name(), parent.view, true)?;
+        *self = new_key;
+        Ok(())
+    }
+
+    /// The WOW64 view this key was opened with, which is inherited by its subkeys.
+    #[inline]
+    pub fn view(&self) -> View {
+        self.view
+    }
+
+    /// Opens the `HKEY_CURRENT_USER` hive of the user the current thread is
+    /// impersonating. See [`Hive::open_current_user`](enum.Hive.html#method.open_current_user).
+    pub fn open_current_user(sec: Security) -> Result<RegKey, Error> {
+        let mut hkey = null_mut();
+
+        let result = unsafe { RegOpenCurrentUser(sec.bits(), &mut hkey) };
+
+        if result == 0 {
+            // TODO: use NT API to query path
+            return Ok(RegKey::new(
+                Hive::CurrentUser,
+                hkey,
+                U16CString::default(),
+                View::from_security(sec),
+                true,
+            ));
+        }
+
+        let path = "<current user>".to_string();
+        Err(Error::from_code(result, path))
+    }
+
+    /// Opens the merged `HKEY_CLASSES_ROOT` view of the user identified by the
+    /// given access token. See [`Hive::open_user_classes_root`](enum.Hive.html#method.open_user_classes_root).
+    ///
+    /// # Safety
+    ///
+    /// `token` must be a valid access token handle opened with `TOKEN_QUERY`,
+    /// `TOKEN_DUPLICATE` and `TOKEN_IMPERSONATE` access.
+    pub unsafe fn open_user_classes_root(token: HANDLE, sec: Security) -> Result<RegKey, Error> {
+        let mut hkey = null_mut();
+
+        let result = RegOpenUserClassesRoot(token, 0, sec.bits(), &mut hkey);
+
+        if result == 0 {
+            return Ok(RegKey::new(
+                Hive::ClassesRoot,
+                hkey,
+                U16CString::default(),
+                View::from_security(sec),
+                true,
+            ));
+        }
+
+        let path = "<user classes root>".to_string();
+        Err(Error::from_code(result, path))
+    }
+}
+
+#[inline]
+pub(crate) fn open_hkey<P>(
+    base: HKEY,
+    path: P,
+    sec: Security,
+    options: Options,
+) -> Result<HKEY, Error>
+where
+    P: AsRef<U16CStr>,
+{
+    let path = path.as_ref();
+    let mut hkey = std::ptr::null_mut();
+    let result =
+        unsafe { RegOpenKeyExW(base, path.as_ptr(), options.bits(), sec.bits(), &mut hkey) };
+
+    if result == 0 {
+        return Ok(hkey);
+    }
+
+    let path = path.to_string_lossy();
+    Err(Error::from_code(result, path))
+}
+
+fn query_optional_value(
+    handle: HKEY,
+    value_name: &U16CStr,
+) -> Result<Option<value::Data>, crate::Error> {
+    match value::query_value(handle, value_name) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.is_not_found() => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The full NT name of the key a handle refers to, such as `\REGISTRY\MACHINE\SOFTWARE`,
+/// using `NtQueryKey`. `path` is only used for errors.
+fn query_key_name(handle: HKEY, path: &U16CStr) -> Result<Vec<u16>, Error> {
+    const KEY_NAME_INFORMATION: u32 = 3;
+    const STATUS_BUFFER_OVERFLOW: i32 = 0x8000_0005_u32 as i32;
+    const STATUS_BUFFER_TOO_SMALL: i32 = 0xc000_0023_u32 as i32;
+
+    // A `KEY_NAME_INFORMATION` is the length of the name in bytes, followed by the name.
+    let mut buf = vec![0u32; 128];
+
+    loop {
+        let mut len = 0u32;
+        let status = unsafe {
+            NtQueryKey(
+                handle as HANDLE,
+                KEY_NAME_INFORMATION,
+                buf.as_mut_ptr() as *mut _,
+                (buf.len() * std::mem::size_of::<u32>()) as u32,
+                &mut len,
+            )
+        };
+
+        if status == STATUS_BUFFER_OVERFLOW || status == STATUS_BUFFER_TOO_SMALL {
+            buf.resize(len as usize / std::mem::size_of::<u32>() + 1, 0);
+            continue;
+        }
+
+        if status < 0 {
+            let code = unsafe { RtlNtStatusToDosError(status) };
+            return Err(Error::from_code(code as i32, path.to_string_lossy()));
+        }
+
+        let name_len = buf[0] as usize / std::mem::size_of::<u16>();
+        // SAFETY: the name directly follows its length, and is `name_len` units long.
+        let name = unsafe { std::slice::from_raw_parts(buf[1..].as_ptr() as *const u16, name_len) };
+        return Ok(name.to_vec());
+    }
+}
+
+type RegRenameKeyFn = unsafe extern "system" fn(HKEY, *const u16, *const u16) -> i32;
+
+/// Looks up `RegRenameKey` when it is needed rather than linking it, as it is not
+/// exported by every version of Windows, and a missing import stops the whole binary
+/// from loading.
+fn reg_rename_key() -> Option<RegRenameKeyFn> {
+    let module = unsafe { GetModuleHandleA(b"advapi32.dll\0".as_ptr() as *const _) };
+
+    if module.is_null() {
+        return None;
+    }
+
+    let proc = unsafe { GetProcAddress(module, b"RegRenameKey\0".as_ptr() as *const _) };
+
+    if proc.is_null() {
+        return None;
+    }
+
+    // SAFETY: `RegRenameKey` has this signature wherever it is exported.
+    Some(unsafe { std::mem::transmute::<FARPROC, RegRenameKeyFn>(proc) })
+}
+
+/// Whether UAC registry virtualization is enabled for the current process token.
+fn token_virtualization_enabled() -> Result<bool, io::Error> {
+    let mut token = null_mut();
+    let result = unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) };
+
+    if result == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut enabled = 0u32;
+    let mut len = 0u32;
+    let result = unsafe {
+        GetTokenInformation(
+            token,
+            TokenVirtualizationEnabled,
+            &mut enabled as *mut u32 as *mut _,
+            std::mem::size_of::<u32>() as u32,
+            &mut len,
+        )
+    };
+    let err = io::Error::last_os_error();
+    unsafe { CloseHandle(token) };
+
+    if result == 0 {
+        return Err(err);
+    }
+
+    Ok(enabled != 0)
+}
+
+/// Replaces a value by way of a temporary value. See `RegKey::replace_value`.
+fn replace_value_hkey(
+    handle: HKEY,
+    value_name: &U16CStr,
+    data: &value::Data,
+) -> Result<Option<value::Data>, crate::Error> {
+    // Use the first of `~replace`, `~replace2` and so on that no value has, so that no
+    // existing value is overwritten.
+    let mut attempt = 1u32;
+    let temp_name = loop {
+        let suffix = match attempt {
+            1 => "~replace".to_string(),
+            n => format!("~replace{}", n),
+        };
+        let mut temp_name = value_name.as_slice().to_vec();
+        temp_name.extend(suffix.encode_utf16());
+        // SAFETY: the value name cannot contain a NUL, and neither does the suffix.
+        let temp_name = unsafe { U16CString::from_vec_unchecked(temp_name) };
+
+        if !value::value_exists(handle, &*temp_name)? {
+            break temp_name;
+        }
+
+        attempt += 1;
+    };
+
+    let old = query_optional_value(handle, value_name)?;
+    value::set_value(handle, &temp_name, data)?;
+
+    let result = value::set_value(handle, value_name, data);
+    let cleanup = value::delete_value(handle, &temp_name);
+    result?;
+    cleanup?;
+    Ok(old)
+}
+
+#[inline]
+fn path_entry_eq(a: &[u16], b: &[u16]) -> bool {
+    cmp_ignore_case(a, b) == std::cmp::Ordering::Equal
+}
+
+/// Converts a UTF-16 code unit to upper case, mapping each unit to exactly one other as
+/// the registry does when comparing names. Surrogates, and characters whose upper case
+/// is longer, such as `ß`, are left as they are.
+fn upcase_unit(unit: u16) -> u16 {
+    let c = match std::char::from_u32(unit as u32) {
+        Some(c) => c,
+        None => return unit,
+    };
+
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) if (u as u32) <= 0xffff => u as u16,
+        _ => unit,
+    }
+}
+
+/// Compares two names ignoring case, by UTF-16 code unit after `upcase_unit`.
+pub(crate) fn cmp_ignore_case(a: &[u16], b: &[u16]) -> std::cmp::Ordering {
+    a.iter()
+        .map(|x| upcase_unit(*x))
+        .cmp(b.iter().map(|x| upcase_unit(*x)))
+}
+
+/// Joins two registry paths with a backslash, omitting it if either path is empty.
+pub(crate) fn join_path(base: &U16CStr, path: &U16CStr) -> U16CString {
+    if base.is_empty() {
+        return path.to_ucstring();
+    }
+
+    if path.is_empty() {
+        return base.to_ucstring();
+    }
+
+    let mut chars = Vec::with_capacity(base.len() + path.len() + 1);
+    chars.extend_from_slice(base.as_slice());
+    chars.push(b'\\' as u16);
+    chars.extend_from_slice(path.as_slice());
+
+    // SAFETY: neither path can contain a NUL, as they are both `U16CStr`s.
+    unsafe { U16CString::from_vec_unchecked(chars) }
+}
+
+#[inline(always)]
+fn link_value_name() -> U16CString {
+    U16CString::from_str("SymbolicLinkValue").unwrap()
+}
+
+#[inline]
+pub(crate) fn connect_hkey<M>(base: HKEY, machine_name: M) -> Result<HKEY, Error>
+where
+    M: AsRef<U16CStr>,
+{
+    let machine_name = machine_name.as_ref();
+    let mut hkey = std::ptr::null_mut();
+    let result = unsafe { RegConnectRegistryW(machine_name.as_ptr(), base, &mut hkey) };
+
+    if result == 0 {
+        return Ok(hkey);
+    }
+
+    let machine_name = machine_name.to_string_lossy();
+    Err(Error::from_code(result, machine_name))
+}
+
+#[inline]
+pub(crate) fn save_hkey<P>(hkey: HKEY, path: P, format: SaveFormat) -> Result<(), Error>
+where
+    P: AsRef<U16CStr>,
+{
+    let path = path.as_ref();
+    let result = unsafe { RegSaveKeyExW(hkey, path.as_ptr(), std::ptr::null_mut(), format as u32) };
+
+    if result == 0 {
+        return Ok(());
+    }
+
+    let path = path.to_string_lossy();
+    Err(Error::from_code(result, path))
+}
+
+#[inline]
+pub(crate) fn delete_hkey<P>(
+    base: HKEY,
+    path: P,
+    view: View,
+    is_recursive: bool,
+) -> Result<(), Error>
+where
+    P: AsRef<U16CStr>,
+{
+    let path = path.as_ref();
+
+    if is_recursive {
+        // RegDeleteTreeW has no view parameter, so clear the key's contents through a
+        // handle opened in the right view before deleting the key itself.
+        let sec = view.apply(Security::Delete | Security::EnumerateSubKeys | Security::QueryValue);
+        let hkey = open_hkey(base, path, sec, Options::empty())?;
+        let result = unsafe { RegDeleteTreeW(hkey, std::ptr::null()) };
+        unsafe { RegCloseKey(hkey) };
+
+        if result != 0 {
+            return Err(Error::from_code(result, path.to_string_lossy()));
+        }
+    }
+
+    let sam = view.apply(Security::empty()).bits();
+    let result = unsafe { RegDeleteKeyExW(base, path.as_ptr(), sam, 0) };
+
+    if result == 0 {
+        return Ok(());
+    }
+
+    let path = path.to_string_lossy();
+    Err(Error::from_code(result, path))
+}
+
+/// Deletes a key and its subkeys one at a time, children first, calling `progress`
+/// with the path of each deleted key relative to `base`. Returns `false` if `progress`
+/// cancelled the deletion.
+pub(crate) fn delete_hkey_with_progress(
+    base: HKEY,
+    path: &U16CStr,
+    view: View,
+    progress: &mut dyn FnMut(&U16CStr) -> bool,
+) -> Result<bool, Error> {
+    let sec = view.apply(Security::EnumerateSubKeys);
+    let hkey = open_hkey(base, path, sec, Options::empty())?;
+    // Key names are limited to 255 characters, plus the NUL terminator.
+    let mut name = [0u16; 256];
+
+    loop {
+        // Deleting a subkey shifts the indices of the rest, so always take the first.
+        let mut name_len = name.len() as u32;
+        let result = unsafe {
+            RegEnumKeyExW(
+                hkey,
+                0,
+                name.as_mut_ptr(),
+                &mut name_len,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+
+        if result == ERROR_NO_MORE_ITEMS as i32 {
+            break;
+        }
+
+        if result != 0 {
+            unsafe { RegCloseKey(hkey) };
+            return Err(Error::from_code(result, path.to_string_lossy()));
+        }
+
+        // SAFETY: the name is NUL terminated at `name_len`.
+        let child = unsafe { U16CStr::from_slice_with_nul_unchecked(&name[..=name_len as usize]) };
+        let child = join_path(path, child);
+
+        match delete_hkey_with_progress(base, &child, view, progress) {
+            Ok(true) => {}
+            result => {
+                unsafe { RegCloseKey(hkey) };
+                return result;
+            }
+        }
+    }
+
+    unsafe { RegCloseKey(hkey) };
+    delete_hkey(base, path, view, false)?;
+    Ok(progress(path))
+}
+
+#[inline]
+pub(crate) fn create_hkey<P>(
+    base: HKEY,
+    path: P,
+    sec: Security,
+    options: Options,
+) -> Result<(HKEY, Disposition), Error>
+where
+    P: AsRef<U16CStr>,
+{
+    create_hkey_with_descriptor(base, path.as_ref(), sec, options, None)
+}
+
+fn create_hkey_with_descriptor(
+    base: HKEY,
+    path: &U16CStr,
+    sec: Security,
+    options: Options,
+    descriptor: Option<&SecurityDescriptor>,
+) -> Result<(HKEY, Disposition), Error> {
+    let mut attributes = descriptor.map(SecurityDescriptor::attributes);
+    let attributes = attributes
+        .as_mut()
+        .map_or(std::ptr::null_mut(), |x| x as *mut _);
+    let mut hkey = std::ptr::null_mut();
+    let mut disposition = 0u32;
+    let result = unsafe {
+        RegCreateKeyExW(
+            base,
+            path.as_ptr(),
+            0,
+            std::ptr::null_mut(),
+            options.bits(),
+            sec.bits(),
+            attributes,
+            &mut hkey,
+            &mut disposition,
+        )
+    };
+
+    if result == 0 {
+        return Ok((hkey, Disposition::from_raw(disposition)));
+    }
+
+    let path = path.to_string_lossy();
+    Err(Error::from_code(result, path))
+}
+
+/// Creates each missing key along the path in turn, so that intermediate keys can be
+/// given different access rights and security descriptors to the final key.
+pub(crate) fn create_hkey_with(
+    base: HKEY,
+    path: &U16CStr,
+    opts: &CreateOptions,
+    view: View,
+) -> Result<(HKEY, Disposition), Error> {
+    // Intermediate keys are created in the final key's view, even if only its access
+    // rights select one.
+    let view = View::from_security(view.apply(opts.security));
+    let descriptor = opts
+        .security_descriptor
+        .as_deref()
+        .map(SecurityDescriptor::from_sddl)
+        .transpose()?;
+    let intermediate_descriptor = opts
+        .intermediate_security_descriptor
+        .as_deref()
+        .map(SecurityDescriptor::from_sddl)
+        .transpose()?;
+
+    let slice = path.as_slice();
+    let separators = slice
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| **x == b'\\' as u16);
+
+    for (end, _) in separators {
+        if end == 0 || slice[end - 1] == b'\\' as u16 {
+            continue;
+        }
+
+        // SAFETY: this is a prefix of the path, so cannot contain a NUL.
+        let prefix = unsafe { U16CString::from_vec_unchecked(&slice[..end]) };
+        let (hkey, _) = create_hkey_with_descriptor(
+            base,
+            &prefix,
+            view.apply(opts.intermediate_security),
+            Options::empty(),
+            intermediate_descriptor.as_ref(),
+        )?;
+        unsafe { RegCloseKey(hkey) };
+    }
+
+    create_hkey_with_descriptor(
+        base,
+        path,
+        view.apply(opts.security),
+        opts.options,
+        descriptor.as_ref(),
+    )
+}
+
+#[inline]
+pub(crate) fn open_hkey_transacted<P>(
+    base: HKEY,
+    path: P,
+    sec: Security,
+    transaction: &Transaction,
+) -> Result<HKEY, Error>
+where
+    P: AsRef<U16CStr>,
+{
+    let path = path.as_ref();
+    let mut hkey = std::ptr::null_mut();
+    let result = unsafe {
+        RegOpenKeyTransactedW(
+            base,
+            path.as_ptr(),
+            0,
+            sec.bits(),
+            &mut hkey,
+            transaction.as_handle(),
+            std::ptr::null_mut(),
+        )
+    };
+
+    if result == 0 {
+        return Ok(hkey);
+    }
+
+    let path = path.to_string_lossy();
+    Err(Error::from_code(result, path))
+}
+
+#[inline]
+pub(crate) fn create_hkey_transacted<P>(
+    base: HKEY,
+    path: P,
+    sec: Security,
+    transaction: &Transaction,
+) -> Result<HKEY, Error>
+where
+    P: AsRef<U16CStr>,
+{
+    let path = path.as_ref();
+    let mut hkey = std::ptr::null_mut();
+    let result = unsafe {
+        RegCreateKeyTransactedW(
+            base,
+            path.as_ptr(),
+            0,
+            std::ptr::null_mut(),
+            0,
+            sec.bits(),
+            std::ptr::null_mut(),
+            &mut hkey,
+            std::ptr::null_mut(),
+            transaction.as_handle(),
+            std::ptr::null_mut(),
+        )
+    };
+
+    if result == 0 {
+        return Ok(hkey);
+    }
+
+    let path = path.to_string_lossy();
+    Err(Error::from_code(result, path))
+}
+
+/// Deletes a key as part of the given transaction. If `is_recursive`, its subkeys are
+/// first deleted one at a time, children first, each with `RegDeleteKeyTransactedW`, so
+/// the whole deletion is part of the transaction.
+pub(crate) fn delete_hkey_transacted<P>(
+    base: HKEY,
+    path: P,
+    view: View,
+    is_recursive: bool,
+    transaction: &Transaction,
+) -> Result<(), Error>
+where
+    P: AsRef<U16CStr>,
+{
+    let path = path.as_ref();
+
+    if is_recursive {
+        let sec = view.apply(Security::Delete | Security::EnumerateSubKeys | Security::QueryValue);
+        let hkey = open_hkey_transacted(base, path, sec, transaction)?;
+        // Key names are limited to 255 characters, plus the NUL terminator.
+        let mut name = [0u16; 256];
+
+        loop {
+            // Deleting a subkey shifts the indices of the rest, so always take the first.
+            let mut name_len = name.len() as u32;
+            let result = unsafe {
+                RegEnumKeyExW(
+                    hkey,
+                    0,
+                    name.as_mut_ptr(),
+                    &mut name_len,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                )
+            };
+
+            if result == ERROR_NO_MORE_ITEMS as i32 {
+                break;
+            }
+
+            if result != 0 {
+                unsafe { RegCloseKey(hkey) };
+                return Err(Error::from_code(result, path.to_string_lossy()));
+            }
+
+            // SAFETY: the name is NUL terminated at `name_len`.
+            let child =
+                unsafe { U16CStr::from_slice_with_nul_unchecked(&name[..=name_len as usize]) };
+
+            if let Err(e) = delete_hkey_transacted(hkey, child, view, true, transaction) {
+                unsafe { RegCloseKey(hkey) };
+                return Err(e);
+            }
+        }
+
+        unsafe { RegCloseKey(hkey) };
+    }
+
+    let result = unsafe {
+        RegDeleteKeyTransactedW(
+            base,
+            path.as_ptr(),
+            view.apply(Security::empty()).bits(),
+            0,
+            transaction.as_handle(),
+            std::ptr::null_mut(),
+        )
+    };
+
+    if result == 0 {
+        return Ok(());
+    }
+
+    let path = path.to_string_lossy();
+    Err(Error::from_code(result, path))
 }
 
-#[inline]
-pub(crate) fn delete_hkey<P>(base: HKEY, path: P, is_recursive: bool) -> Result<(), Error>
-where
-    P: AsRef<U16CStr>,
-{
-    let path = path.as_ref();
+#[cfg(test)]
+mod tests {
+    use crate::Hive;
+
+    #[test]
+    fn test_paths() {
+        let key = Hive::CurrentUser
+            .open("SOFTWARE\\Microsoft", crate::Security::Read)
+            .unwrap();
+        assert_eq!(key.to_string(), "HKEY_CURRENT_USER\\SOFTWARE\\Microsoft")
+    }
+
+    #[test]
+    fn raw_handle_round_trip() {
+        let key = Hive::CurrentUser
+            .open("SOFTWARE\\Microsoft", crate::Security::Read)
+            .unwrap();
+        let handle = key.into_raw();
+        let key = unsafe { crate::RegKey::from_raw(handle) };
+        assert_eq!(key.as_raw(), handle);
+        assert_eq!(key.to_string(), "<Unknown>");
+        assert!(key.keys().next().is_some());
+    }
+
+    #[test]
+    fn relative_paths() {
+        let key = crate::RegKey::open_current_user(crate::Security::Read).unwrap();
+        assert_eq!(key.to_string(), "HKEY_CURRENT_USER");
+
+        let key = key.open("SOFTWARE", crate::Security::Read).unwrap();
+        assert_eq!(key.to_string(), "HKEY_CURRENT_USER\\SOFTWARE");
+
+        let key = key.open("Microsoft", crate::Security::Read).unwrap();
+        assert_eq!(key.to_string(), "HKEY_CURRENT_USER\\SOFTWARE\\Microsoft");
+    }
+
+    #[test]
+    fn parent_and_name() {
+        let key = Hive::CurrentUser
+            .open("SOFTWARE\\Microsoft", crate::Security::Read)
+            .unwrap();
+        assert_eq!(key.name().to_string_lossy(), "Microsoft");
+
+        let parent = key.parent(crate::Security::Read).unwrap().unwrap();
+        assert_eq!(parent.to_string(), "HKEY_CURRENT_USER\\SOFTWARE");
+        assert_eq!(parent.name().to_string_lossy(), "SOFTWARE");
+
+        let root = parent.parent(crate::Security::Read).unwrap().unwrap();
+        assert_eq!(root.to_string(), "HKEY_CURRENT_USER");
+        assert!(root.name().is_empty());
+        assert!(root.parent(crate::Security::Read).unwrap().is_none());
+    }
+
+    #[test]
+    fn exists() {
+        assert!(Hive::CurrentUser.exists("SOFTWARE\\Microsoft").unwrap());
+        assert!(!Hive::CurrentUser
+            .exists("SOFTWARE\\95d8c7a6-a36c-4a1c-9ba8-1a4b0a0e5e8d")
+            .unwrap());
+
+        let key = Hive::CurrentUser
+            .open("SOFTWARE", crate::Security::Read)
+            .unwrap();
+        assert!(key.subkey_exists("Microsoft").unwrap());
+        assert!(!key
+            .subkey_exists("95d8c7a6-a36c-4a1c-9ba8-1a4b0a0e5e8d")
+            .unwrap());
+        assert!(!key
+            .value_exists("95d8c7a6-a36c-4a1c-9ba8-1a4b0a0e5e8d")
+            .unwrap());
+    }
+
+    #[test]
+    fn try_clone() {
+        let key = Hive::CurrentUser
+            .open("SOFTWARE\\Microsoft", crate::Security::Read)
+            .unwrap();
+        let clone = key.try_clone().unwrap();
+        drop(key);
+
+        assert_eq!(clone.to_string(), "HKEY_CURRENT_USER\\SOFTWARE\\Microsoft");
+        let handle = std::thread::spawn(move || clone.keys().count());
+        assert!(handle.join().unwrap() > 0);
+    }
+
+    #[test]
+    fn rename() {
+        const KEY: &str = r"Test\registry-rust-crate-rename";
+
+        let parent = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let mut key = parent.create("old", crate::Security::AllAccess).unwrap();
+        key.rename("new").unwrap();
+
+        assert_eq!(key.name().to_string_lossy(), "new");
+        assert!(!parent.subkey_exists("old").unwrap());
+        assert!(parent.subkey_exists("new").unwrap());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn rename_by_copy_existing() {
+        const KEY: &str = r"Test\registry-rust-crate-rename-by-copy-existing";
+
+        let parent = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let mut key = parent.create("old", crate::Security::AllAccess).unwrap();
+        let other = parent.create("new", crate::Security::AllAccess).unwrap();
+        other.set_value("kept", &crate::Data::U32(1)).unwrap();
+
+        let name = utfx::U16CString::from_str("new").unwrap();
+        assert!(key.rename_by_copy(&name).is_err());
+        assert!(parent.subkey_exists("old").unwrap());
+        assert!(other.value_exists("kept").unwrap());
+
+        key.rename_by_copy(&utfx::U16CString::from_str("copied").unwrap())
+            .unwrap();
+        assert_eq!(key.name().to_string_lossy(), "copied");
+        assert!(!parent.subkey_exists("old").unwrap());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn delete_self() {
+        const KEY: &str = r"Test\registry-rust-crate-delete-self";
+
+        let parent = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let key = parent
+            .create(r"child\nested", crate::Security::AllAccess)
+            .unwrap();
+        key.delete_self(false).unwrap();
+        assert!(!parent.subkey_exists(r"child\nested").unwrap());
+
+        let key = parent.open("child", crate::Security::AllAccess).unwrap();
+        key.set_value("u32", &crate::Data::U32(42)).unwrap();
+        parent
+            .create(r"child\other", crate::Security::AllAccess)
+            .unwrap();
+        key.delete_self(true).unwrap();
+        assert!(!parent.subkey_exists("child").unwrap());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn delete_self_force32() {
+        use crate::{Security, View};
+
+        // CLSID is redirected to Wow6432Node in the 32-bit view.
+        const KEY: &str = r"Software\Classes\CLSID\registry-rust-crate-delete-self-force32";
+
+        let native = Hive::CurrentUser
+            .create_with_view(KEY, Security::AllAccess, View::Force64)
+            .unwrap();
+        let key = Hive::CurrentUser
+            .create_with_view(KEY, Security::AllAccess, View::Force32)
+            .unwrap();
+        key.create("child", Security::AllAccess).unwrap();
+        key.delete_self(true).unwrap();
+
+        let result = Hive::CurrentUser.open_with_view(KEY, Security::Read, View::Force32);
+        assert!(result.unwrap_err().is_not_found());
+        assert!(Hive::CurrentUser
+            .open_with_view(KEY, Security::Read, View::Force64)
+            .is_ok());
+
+        native.delete_self(false).unwrap();
+    }
+
+    #[test]
+    fn clear_values() {
+        const KEY: &str = r"Test\registry-rust-crate-clear-values";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.create("child", crate::Security::AllAccess).unwrap();
+        key.set_value("", &crate::Data::U32(0)).unwrap();
+        for i in 0..10u32 {
+            key.set_value(format!("value{}", i), &crate::Data::U32(i))
+                .unwrap();
+        }
+
+        key.clear_values().unwrap();
+        assert_eq!(key.values().count(), 0);
+        assert!(key.subkey_exists("child").unwrap());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn create_with() {
+        const KEY: &str = r"Test\registry-rust-crate-create-with";
+
+        let opts = crate::CreateOptions {
+            security: crate::Security::AllAccess,
+            security_descriptor: Some("D:(A;;KA;;;WD)".into()),
+            ..Default::default()
+        };
+        let key = Hive::CurrentUser
+            .create_with(format!(r"{}\a\b\c", KEY), &opts)
+            .unwrap();
+        assert_eq!(key.name().to_string_lossy(), "c");
+        assert!(Hive::CurrentUser
+            .open(format!(r"{}\a\b", KEY), crate::Security::Read)
+            .is_ok());
+
+        let opts = crate::CreateOptions {
+            intermediate_security_descriptor: Some("not sddl".into()),
+            ..Default::default()
+        };
+        let result = key.create_with(r"d\e", &opts);
+        assert!(matches!(
+            result,
+            Err(super::Error::InvalidSecurityDescriptor(..))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn create_with_force32() {
+        use crate::{CreateOptions, Security, View};
+
+        // CLSID is redirected to Wow6432Node in the 32-bit view.
+        const KEY: &str = r"Software\Classes\CLSID\registry-rust-crate-create-with-force32";
+
+        let opts = CreateOptions {
+            security: Security::AllAccess | Security::Wow6432Key,
+            intermediate_security_descriptor: Some("D:(A;;KA;;;WD)".into()),
+            ..Default::default()
+        };
+        Hive::CurrentUser
+            .create_with(format!(r"{}\a\b", KEY), &opts)
+            .unwrap();
+
+        let key = Hive::CurrentUser
+            .open_with_view(KEY, Security::AllAccess, View::Force32)
+            .unwrap();
+        assert!(key.subkey_exists("a").unwrap());
+        let result = Hive::CurrentUser.open_with_view(KEY, Security::Read, View::Force64);
+        assert!(result.unwrap_err().is_not_found());
+
+        key.delete_self(true).unwrap();
+    }
+
+    #[test]
+    fn set_value_at() {
+        const KEY: &str = r"Test\registry-rust-crate-set-value-at";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value_at(r"a\b", "u32", &crate::Data::U32(42))
+            .unwrap();
+
+        let child = key.open(r"a\b", crate::Security::Read).unwrap();
+        assert!(matches!(child.value("u32").unwrap(), crate::Data::U32(42)));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn get_value_at() {
+        const KEY: &str = r"Test\registry-rust-crate-get-value-at";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let child = key.create("child", crate::Security::AllAccess).unwrap();
+        child
+            .set_value(
+                "path",
+                &crate::Data::ExpandString(utfx::U16CString::from_str("%TEMP%").unwrap()),
+            )
+            .unwrap();
+
+        let data = key.get_value_at("child", "path").unwrap();
+        assert!(matches!(data, crate::Data::ExpandString(s) if s.to_string_lossy() == "%TEMP%"));
+
+        let data = Hive::CurrentUser
+            .get_value(format!(r"{}\child", KEY), "path")
+            .unwrap();
+        assert!(matches!(data, crate::Data::ExpandString(_)));
+
+        let result = key.get_value_at_with_filter("child", "path", crate::TypeFilter::U32);
+        assert!(matches!(result, Err(crate::value::Error::TypeMismatch(..))));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn get_values() {
+        const KEY: &str = r"Test\registry-rust-crate-get-values";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("a", &crate::Data::U32(1)).unwrap();
+        key.set_value("b", &crate::Data::Binary(vec![1, 2, 3]))
+            .unwrap();
+        key.set_value("c", &crate::Data::U64(3)).unwrap();
+
+        let data = key.get_values(&["c", "b", "a"]).unwrap();
+        assert!(matches!(data[0], crate::Data::U64(3)));
+        assert!(matches!(&data[1], crate::Data::Binary(x) if x == &[1, 2, 3]));
+        assert!(matches!(data[2], crate::Data::U32(1)));
+
+        assert!(key
+            .get_values(&["a", "missing"])
+            .unwrap_err()
+            .is_not_found());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn get_values_empty() {
+        const KEY: &str = r"Test\registry-rust-crate-get-values-empty";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("a", &crate::Data::Binary(vec![])).unwrap();
+        key.set_value(
+            "b",
+            &crate::Data::String(utfx::U16CString::from_str("").unwrap()),
+        )
+        .unwrap();
+
+        let data = key.get_values(&["a", "b"]).unwrap();
+        assert!(matches!(&data[0], crate::Data::Binary(x) if x.is_empty()));
+        assert!(matches!(&data[1], crate::Data::None(x) if x.is_empty()));
+
+        let names: [&str; 0] = [];
+        assert!(key.get_values(&names).unwrap().is_empty());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn delete_with_progress() {
+        const KEY: &str = r"Test\registry-rust-crate-delete-with-progress";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.create(r"a\b", crate::Security::AllAccess).unwrap();
+        key.create(r"a\c", crate::Security::AllAccess).unwrap();
+
+        let mut deleted = vec![];
+        let completed = key
+            .delete_with_progress("a", |path| {
+                deleted.push(path.to_string_lossy());
+                deleted.len() < 2
+            })
+            .unwrap();
+        assert!(!completed);
+        assert_eq!(deleted, [r"a\b", r"a\c"]);
+        assert!(key.subkey_exists("a").unwrap());
+
+        assert!(key.delete_with_progress("a", |_| true).unwrap());
+        assert!(!key.subkey_exists("a").unwrap());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn delete_value_at() {
+        const KEY: &str = r"Test\registry-rust-crate-delete-value-at";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let child = key.create(r"a\b", crate::Security::AllAccess).unwrap();
+        child.set_value("u32", &crate::Data::U32(42)).unwrap();
+
+        key.delete_value_at(r"a\b", "u32").unwrap();
+        assert!(!child.value_exists("u32").unwrap());
+        assert!(key
+            .delete_value_at(r"a\b", "u32")
+            .unwrap_err()
+            .is_not_found());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn move_to() {
+        const KEY: &str = r"Test\registry-rust-crate-move-to";
+
+        let parent = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let src = parent
+            .create(r"src\nested", crate::Security::AllAccess)
+            .unwrap();
+        src.set_value("u32", &crate::Data::U32(42)).unwrap();
+        let src = parent.open("src", crate::Security::AllAccess).unwrap();
+
+        assert!(src
+            .try_clone()
+            .unwrap()
+            .move_to(Hive::CurrentUser, format!(r"{}\src\inner", KEY))
+            .is_err());
+
+        // Keys without a local root cannot be restored if deleting them fails.
+        let detached = Hive::open_current_user(crate::Security::AllAccess)
+            .unwrap()
+            .open(format!(r"{}\src", KEY), crate::Security::AllAccess)
+            .unwrap();
+        assert!(detached
+            .move_to(Hive::CurrentUser, format!(r"{}\other", KEY))
+            .is_err());
+        assert!(!parent.subkey_exists("other").unwrap());
+
+        let dest = src
+            .move_to(Hive::CurrentUser, format!(r"{}\dest", KEY))
+            .unwrap();
+        assert_eq!(dest.name().to_string_lossy(), "dest");
+        assert!(!parent.subkey_exists("src").unwrap());
+        let nested = dest.open("nested", crate::Security::Read).unwrap();
+        assert!(matches!(nested.value("u32").unwrap(), crate::Data::U32(42)));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn prune_empty() {
+        const KEY: &str = r"Test\registry-rust-crate-prune-empty";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.create(r"empty\nested", crate::Security::AllAccess)
+            .unwrap();
+        key.create("full", crate::Security::AllAccess)
+            .unwrap()
+            .set_value("u32", &crate::Data::U32(42))
+            .unwrap();
+
+        assert_eq!(key.prune_empty(false).unwrap(), 0);
+        assert_eq!(key.prune_empty(true).unwrap(), 2);
+        assert!(!key.subkey_exists("empty").unwrap());
+        assert!(key.subkey_exists("full").unwrap());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn stats() {
+        const KEY: &str = r"Test\registry-rust-crate-stats";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let child = key.create(r"a\b", crate::Security::AllAccess).unwrap();
+        key.set_value("u32", &crate::Data::U32(42)).unwrap();
+        child.set_value("u64", &crate::Data::U64(42)).unwrap();
+
+        let stats = key.stats().unwrap();
+        assert_eq!(stats.key_count, 3);
+        assert_eq!(stats.value_count, 2);
+        assert_eq!(stats.data_len, 12);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn create_temp() {
+        const KEY: &str = r"Test\registry-rust-crate-create-temp";
+
+        let parent = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let temp = parent.create_temp(crate::Security::AllAccess).unwrap();
+        let other = parent.create_temp(crate::Security::AllAccess).unwrap();
+        temp.create("child", crate::Security::AllAccess).unwrap();
+        assert_ne!(temp.name(), other.name());
+
+        let name = temp.name().to_ucstring();
+        drop(temp);
+        assert!(!parent.subkey_exists(&name).unwrap());
+
+        let name = other.name().to_ucstring();
+        let kept = other.persist();
+        assert!(parent.subkey_exists(&name).unwrap());
+        kept.delete_self(true).unwrap();
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn replace_value() {
+        const KEY: &str = r"Test\registry-rust-crate-replace-value";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let old = key
+            .replace_value("blob", &crate::Data::Binary(vec![1, 2, 3]))
+            .unwrap();
+        assert_eq!(old, None);
+
+        let old = key
+            .replace_value("blob", &crate::Data::Binary(vec![4, 5]))
+            .unwrap();
+        assert_eq!(old, Some(crate::Data::Binary(vec![1, 2, 3])));
+        assert_eq!(key.value("blob").unwrap(), crate::Data::Binary(vec![4, 5]));
+        assert!(key.value("blob~replace").unwrap_err().is_not_found());
+
+        key.set_value("blob~replace", &crate::Data::U32(1)).unwrap();
+        key.replace_value("blob", &crate::Data::Binary(vec![6]))
+            .unwrap();
+        assert_eq!(key.value("blob").unwrap(), crate::Data::Binary(vec![6]));
+        assert_eq!(key.value("blob~replace").unwrap(), crate::Data::U32(1));
+        assert!(key.value("blob~replace2").unwrap_err().is_not_found());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn virtualization() {
+        // HKEY_CURRENT_USER is never virtualized.
+        let key = Hive::CurrentUser
+            .open("SOFTWARE", crate::Security::Read)
+            .unwrap();
+        assert!(!key.virtualization().unwrap().is_candidate);
+        assert!(!key.is_virtualized().unwrap());
+    }
+
+    #[test]
+    fn find_case_insensitive() {
+        const KEY: &str = r"Test\registry-rust-crate-find";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.create("MixedCase", crate::Security::AllAccess).unwrap();
+        key.set_value("ValueName", &crate::Data::U32(1)).unwrap();
+
+        let found = key.find_subkey("mixedcase").unwrap().unwrap();
+        assert_eq!(found.to_string_lossy(), "MixedCase");
+        let found = key.find_value("VALUENAME").unwrap().unwrap();
+        assert_eq!(found.to_string_lossy(), "ValueName");
+        assert!(key.find_subkey("missing").unwrap().is_none());
+        assert!(key.find_value("missing").unwrap().is_none());
+
+        // Names are compared as the registry does, so the Kelvin sign is not a `K`.
+        key.create("Kelvin", crate::Security::AllAccess).unwrap();
+        assert!(key.find_subkey("\u{212a}elvin").unwrap().is_none());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn raw_values() {
+        const KEY: &str = r"Test\registry-rust-crate-raw-values";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value_raw("custom", 0x1234, &[1, 2, 3]).unwrap();
+        assert_eq!(key.value_raw("custom").unwrap(), (0x1234, vec![1, 2, 3]));
+
+        key.set_value_raw("list", 8, &[1, 0, 0, 0]).unwrap();
+        assert_eq!(
+            key.value("list").unwrap(),
+            crate::Data::ResourceList(vec![1, 0, 0, 0])
+        );
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn hardware_resource_list() {
+        let key = Hive::LocalMachine
+            .open(
+                r"HARDWARE\RESOURCEMAP\System Resources\Physical Memory",
+                crate::Security::Read,
+            )
+            .unwrap();
+        let data = key.value(".Translated").unwrap();
+        assert!(matches!(data, crate::Data::ResourceList(x) if !x.is_empty()));
+    }
+
+    #[test]
+    fn default_value() {
+        const KEY: &str = r"Test\registry-rust-crate-default-value";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        assert!(key.default_value().unwrap_err().is_not_found());
+
+        key.set_default_value("txtfile").unwrap();
+        assert_eq!(
+            key.default_value().unwrap(),
+            crate::Data::String(utfx::U16CString::from_str("txtfile").unwrap())
+        );
+        assert_eq!(key.get_value::<String, _>("").unwrap(), "txtfile");
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn value_info() {
+        const KEY: &str = r"Test\registry-rust-crate-value-info";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("blob", &vec![0u8; 1000]).unwrap();
+        key.set_value("string", "abc").unwrap();
+        key.set_value("u32", &1u32).unwrap();
+        key.set_value_raw("unknown", 0x1234, &[1]).unwrap();
+
+        assert_eq!(
+            key.value_info("blob").unwrap(),
+            (crate::RegType::Binary, 1000)
+        );
+        assert_eq!(
+            key.value_info("string").unwrap(),
+            (crate::RegType::String, 8)
+        );
+        assert_eq!(key.value_info("u32").unwrap(), (crate::RegType::U32, 4));
+        assert!(matches!(
+            key.value_info("unknown"),
+            Err(crate::value::Error::UnhandledType(0x1234))
+        ));
+        assert!(key.value_info("missing").unwrap_err().is_not_found());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn value_reader_and_writer() {
+        use std::io::{Read, Write};
+
+        const KEY: &str = r"Test\registry-rust-crate-value-stream";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let data = (0..100_000u32).map(|x| x as u8).collect::<Vec<_>>();
+
+        let mut writer = key.create_value_writer("blob").unwrap();
+        for chunk in data.chunks(4096) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mut reader = key.open_value_reader("blob").unwrap();
+        assert_eq!(reader.value_type(), winapi::um::winnt::REG_BINARY);
+        assert_eq!(reader.len(), data.len());
+        let mut read = vec![];
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+
+        key.set_value("small", &vec![1u8, 2]).unwrap();
+        let reader = key
+            .open_value_reader_with_buffer("small", reader.into_buffer())
+            .unwrap();
+        assert_eq!(reader.into_buffer(), [1, 2]);
+
+        key.set_value("empty", &Vec::<u8>::new()).unwrap();
+        assert!(key.open_value_reader("empty").unwrap().is_empty());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn set_os_str_and_path() {
+        use std::os::windows::ffi::OsStringExt;
+
+        const KEY: &str = r"Test\registry-rust-crate-os-str";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+
+        let unpaired = std::ffi::OsString::from_wide(&[0xD800, b'a' as u16]);
+        key.set_value("unpaired", &unpaired).unwrap();
+        let (ty, bytes) = key.value_raw("unpaired").unwrap();
+        assert_eq!(ty, winapi::um::winnt::REG_SZ);
+        assert_eq!(bytes, [0x00, 0xD8, b'a', 0, 0, 0]);
+        assert_eq!(key.get_os_string("unpaired").unwrap(), unpaired);
+        assert_eq!(
+            key.get_path("unpaired").unwrap(),
+            std::path::PathBuf::from(&unpaired)
+        );
+
+        key.set_value("path", std::path::Path::new(r"C:\Windows"))
+            .unwrap();
+        assert_eq!(key.get_value::<String, _>("path").unwrap(), r"C:\Windows");
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn value_with_options() {
+        use crate::{Data, Expand, ReadOptions, TypeFilter};
+        use utfx::U16CString;
+
+        const KEY: &str = r"Test\registry-rust-crate-read-options";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("u32", &1u32).unwrap();
+        key.set_value("string", "1").unwrap();
+        key.set_value(
+            "expand",
+            &Data::ExpandString(U16CString::from_str("%SystemRoot%").unwrap()),
+        )
+        .unwrap();
+
+        let u32_only = ReadOptions {
+            types: TypeFilter::U32,
+            ..Default::default()
+        };
+        assert_eq!(
+            key.value_with_options("u32", &u32_only).unwrap(),
+            Data::U32(1)
+        );
+        assert!(matches!(
+            key.value_with_options("string", &u32_only),
+            Err(crate::value::Error::TypeMismatch(..))
+        ));
+
+        let expanded = key
+            .value_with_options(
+                "expand",
+                &ReadOptions {
+                    expand: Expand::Yes,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            expanded,
+            Data::String(U16CString::from_os_str(std::env::var_os("SystemRoot").unwrap()).unwrap())
+        );
+        assert!(matches!(
+            key.value_with_options("expand", &ReadOptions::default())
+                .unwrap(),
+            Data::ExpandString(_)
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn empty_values() {
+        use crate::{Data, RegType};
+        use utfx::U16CString;
+
+        const KEY: &str = r"Test\registry-rust-crate-empty-values";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let expected = [
+            (RegType::None, Data::None(vec![])),
+            (RegType::String, Data::String(U16CString::default())),
+            (
+                RegType::ExpandString,
+                Data::ExpandString(U16CString::default()),
+            ),
+            (RegType::Binary, Data::Binary(vec![])),
+            (RegType::U32, Data::U32(0)),
+            (RegType::MultiString, Data::MultiString(vec![])),
+            (RegType::U64, Data::U64(0)),
+        ];
+
+        for (i, (ty, data)) in expected.iter().enumerate() {
+            let name = format!("empty{}", i);
+            key.set_empty_value(&name, *ty).unwrap();
+            assert_eq!(key.value_info(&name).unwrap(), (*ty, 0));
+            assert_eq!(&key.value(&name).unwrap(), data);
+        }
+
+        key.set_value("none", &Data::None(vec![])).unwrap();
+        assert_eq!(key.value_info("none").unwrap(), (RegType::None, 0));
+
+        key.set_value("bytes", &Data::None(vec![1, 2, 3])).unwrap();
+        assert_eq!(key.value("bytes").unwrap(), Data::None(vec![1, 2, 3]));
+
+        key.set_value_raw("unterminated", winapi::um::winnt::REG_SZ, &[b'a', 0])
+            .unwrap();
+        assert_eq!(key.get_value::<String, _>("unterminated").unwrap(), "a");
+
+        let embedded = [b'a', 0, 0, 0, b'b', 0, 0, 0];
+        key.set_value_raw("embedded", winapi::um::winnt::REG_SZ, &embedded)
+            .unwrap();
+        assert!(matches!(
+            key.value("embedded"),
+            Err(crate::value::Error::InvalidNul(_))
+        ));
+        assert_eq!(key.get_string_lossy("embedded").unwrap(), "a");
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn rename_value() {
+        const KEY: &str = r"Test\registry-rust-crate-rename-value";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value_raw("old", 0x1234, &[1, 2, 3]).unwrap();
+        key.set_value("other", &1u32).unwrap();
+
+        key.rename_value("old", "new", false).unwrap();
+        assert!(!key.value_exists("old").unwrap());
+        assert_eq!(key.value_raw("new").unwrap(), (0x1234, vec![1, 2, 3]));
+
+        assert!(matches!(
+            key.rename_value("new", "other", false),
+            Err(super::value::Error::AlreadyExists(_))
+        ));
+        assert!(key.value_exists("new").unwrap());
+
+        key.rename_value("new", "other", true).unwrap();
+        assert_eq!(key.value_raw("other").unwrap(), (0x1234, vec![1, 2, 3]));
+
+        key.rename_value("other", "Other", false).unwrap();
+        let names = key
+            .values()
+            .map(|x| x.unwrap().name().to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Other"]);
+
+        // If writing under the new name fails, the value is restored under the old one.
+        let old_name = utfx::U16CString::from_str("Other").unwrap();
+        let new_name = utfx::U16CString::from_str("OTHER").unwrap();
+        let result =
+            key.change_value_name_case(&old_name, &new_name, 0x1234, &[1, 2, 3], |_, _, _, _| {
+                Err(super::value::Error::AlreadyExists("OTHER".into()))
+            });
+        assert!(result.is_err());
+        assert_eq!(key.value_raw("Other").unwrap(), (0x1234, vec![1, 2, 3]));
+        let names = key
+            .values()
+            .map(|x| x.unwrap().name().to_string_lossy())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Other"]);
+
+        assert!(key
+            .rename_value("missing", "x", false)
+            .unwrap_err()
+            .is_not_found());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn get_and_set_u64() {
+        const KEY: &str = r"Test\registry-rust-crate-u64";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_u64("qword", 0x1234_5678_9abc_def0).unwrap();
+        assert_eq!(
+            key.value("qword").unwrap(),
+            crate::Data::U64(0x1234_5678_9abc_def0)
+        );
+        assert_eq!(key.get_u64("qword").unwrap(), 0x1234_5678_9abc_def0);
+
+        key.set_value("binary", &42u64.to_le_bytes()[..]).unwrap();
+        assert_eq!(key.get_u64("binary").unwrap(), 42);
+        key.set_value("dword", &7u32).unwrap();
+        assert_eq!(key.get_u64("dword").unwrap(), 7);
+
+        key.set_value("short", &[1u8, 2][..]).unwrap();
+        assert!(matches!(
+            key.get_u64("short"),
+            Err(crate::value::Error::TypeMismatch(..))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn get_and_set_bool() {
+        const KEY: &str = r"Test\registry-rust-crate-bool";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_bool("on", true).unwrap();
+        key.set_bool("off", false).unwrap();
+        assert_eq!(key.value("on").unwrap(), crate::Data::U32(1));
+        assert!(key.get_bool("on").unwrap());
+        assert!(!key.get_bool("off").unwrap());
+
+        key.set_value("dword", &2u32).unwrap();
+        assert!(key.get_bool("dword").unwrap());
+
+        for (s, expected) in &[
+            ("True", true),
+            (" 0 ", false),
+            ("1", true),
+            ("FALSE", false),
+        ] {
+            key.set_value("string", *s).unwrap();
+            assert_eq!(key.get_bool("string").unwrap(), *expected);
+        }
+
+        key.set_value("string", "maybe").unwrap();
+        assert!(matches!(
+            key.get_bool("string"),
+            Err(crate::value::Error::TypeMismatch(..))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn get_and_set_guid() {
+        use crate::value::GuidFormat;
+
+        const KEY: &str = r"Test\registry-rust-crate-guid";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let guid = uuid::Uuid::parse_str("6b29fc40-ca47-1067-b31d-00dd010662da").unwrap();
+
+        key.set_guid("braced", guid, GuidFormat::Braced).unwrap();
+        assert_eq!(
+            key.get_value::<String, _>("braced").unwrap(),
+            "{6B29FC40-CA47-1067-B31D-00DD010662DA}"
+        );
+        assert_eq!(key.get_guid("braced").unwrap(), guid);
+
+        key.set_guid("binary", guid, GuidFormat::Binary).unwrap();
+        let (_, bytes) = key.value_raw("binary").unwrap();
+        assert_eq!(&bytes[..4], [0x40, 0xfc, 0x29, 0x6b]);
+        assert_eq!(key.get_guid("binary").unwrap(), guid);
+
+        key.set_value("plain", "6b29fc40-ca47-1067-b31d-00dd010662da")
+            .unwrap();
+        assert_eq!(key.get_guid("plain").unwrap(), guid);
+
+        key.set_value("invalid", "{not a guid}").unwrap();
+        assert!(matches!(
+            key.get_guid("invalid"),
+            Err(crate::value::Error::InvalidGuid(..))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn get_and_set_filetime() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        const KEY: &str = r"Test\registry-rust-crate-filetime";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let time = UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_700);
+
+        key.set_filetime("time", time).unwrap();
+        let (ty, bytes) = key.value_raw("time").unwrap();
+        assert_eq!(ty, winapi::um::winnt::REG_BINARY);
+        assert_eq!(
+            u64::from_le_bytes(std::convert::TryInto::try_into(&bytes[..]).unwrap()),
+            132_444_736_001_234_567
+        );
+        assert_eq!(key.get_filetime("time").unwrap(), time);
+
+        key.set_u64("qword", 116_444_736_000_000_000).unwrap();
+        assert_eq!(key.get_filetime("qword").unwrap(), UNIX_EPOCH);
+
+        key.set_value("short", &[1u8][..]).unwrap();
+        assert!(matches!(
+            key.get_filetime("short"),
+            Err(crate::value::Error::TypeMismatch(..))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn filetime_conversion() {
+        use std::time::UNIX_EPOCH;
+
+        assert_eq!(
+            super::filetime_to_system_time(116_444_736_000_000_000),
+            Some(UNIX_EPOCH)
+        );
+        assert_eq!(
+            super::system_time_to_filetime(UNIX_EPOCH),
+            Some(116_444_736_000_000_000)
+        );
+        assert_eq!(
+            super::system_time_to_filetime(super::filetime_to_system_time(0).unwrap()),
+            Some(0)
+        );
+        assert!(super::filetime_to_system_time(u64::MAX).is_none());
+    }
+
+    #[test]
+    fn get_and_set_enum() {
+        use crate::value::{EnumRepr, Error, RegEnum};
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum Mode {
+            Fast,
+            Slow,
+            Off,
+        }
+
+        impl RegEnum for Mode {
+            const MAPPING: &'static [(Self, EnumRepr)] = &[
+                (Mode::Fast, EnumRepr::String("fast")),
+                (Mode::Slow, EnumRepr::U32(2)),
+                (Mode::Slow, EnumRepr::String("slow")),
+            ];
+        }
+
+        const KEY: &str = r"Test\registry-rust-crate-enum";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_enum("fast", Mode::Fast).unwrap();
+        assert_eq!(key.get_value::<String, _>("fast").unwrap(), "fast");
+        assert_eq!(key.get_enum::<Mode, _>("fast").unwrap(), Mode::Fast);
+
+        key.set_enum("slow", Mode::Slow).unwrap();
+        assert_eq!(key.value("slow").unwrap(), crate::Data::U32(2));
+        key.set_value("alias", "SLOW").unwrap();
+        assert_eq!(key.get_enum::<Mode, _>("alias").unwrap(), Mode::Slow);
+
+        key.set_value("unknown", "medium").unwrap();
+        match key.get_enum::<Mode, _>("unknown") {
+            Err(Error::UnknownVariant(name, data, allowed)) => {
+                assert_eq!(name, "unknown");
+                assert_eq!(data, "\"medium\"");
+                assert_eq!(allowed, ["\"fast\"", "2", "\"slow\""]);
+            }
+            x => panic!("{:?}", x),
+        }
+
+        assert!(matches!(
+            key.set_enum("off", Mode::Off),
+            Err(Error::UnknownVariant(..))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn signed_integers() {
+        use crate::Data;
+
+        const KEY: &str = r"Test\registry-rust-crate-signed";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_i32("i32", -2).unwrap();
+        assert_eq!(key.value("i32").unwrap(), Data::U32(0xFFFF_FFFE));
+        assert_eq!(key.get_i32("i32").unwrap(), -2);
+        assert_eq!(key.get_i64("i32").unwrap(), -2);
+
+        key.set_i64("i64", i64::MIN).unwrap();
+        assert_eq!(key.value("i64").unwrap(), Data::U64(1 << 63));
+        assert_eq!(key.get_i64("i64").unwrap(), i64::MIN);
+        assert!(matches!(
+            key.get_i32("i64"),
+            Err(crate::value::Error::TypeMismatch(..))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn set_values() {
+        use crate::Data;
+
+        const KEY: &str = r"Test\registry-rust-crate-set-values";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_values(vec![("a", Data::U32(1)), ("b", Data::U64(2))])
+            .unwrap();
+        assert_eq!(key.value("a").unwrap(), Data::U32(1));
+        assert_eq!(key.value("b").unwrap(), Data::U64(2));
+
+        key.set_values_transacted(vec![("c", "x"), ("d", "y")])
+            .unwrap();
+        assert_eq!(key.get_value::<String, _>("d").unwrap(), "y");
+
+        // Value names are limited to 16,383 characters, so the second write fails.
+        let too_long = "x".repeat(20_000);
+        assert!(key
+            .set_values_transacted(vec![("f", Data::U32(1)), (too_long.as_str(), Data::U32(2))])
+            .is_err());
+        assert!(!key.value_exists("f").unwrap());
+
+        let readonly = Hive::CurrentUser.open(KEY, crate::Security::Read).unwrap();
+        assert!(readonly.set_values(vec![("e", Data::U32(1))]).is_err());
+
+        // Once renamed away, the key's path refers to a new key, which is not written.
+        let child = key.create("child", crate::Security::AllAccess).unwrap();
+        let mut renamed = key.open("child", crate::Security::AllAccess).unwrap();
+        renamed.rename("renamed").unwrap();
+        let new_child = key.create("child", crate::Security::AllAccess).unwrap();
+        assert!(child
+            .set_values_transacted(vec![("g", Data::U32(1))])
+            .is_err());
+        assert!(!new_child.value_exists("g").unwrap());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn string_lossy() {
+        use winapi::um::winnt::{REG_DWORD, REG_EXPAND_SZ, REG_SZ};
+
+        const KEY: &str = r"Test\registry-rust-crate-string-lossy";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+
+        // "a", an unpaired high surrogate, then "b" with no terminator.
+        key.set_value_raw("surrogate", REG_SZ, &[b'a', 0, 0x00, 0xd8, b'b', 0])
+            .unwrap();
+        assert!(key.get_value::<String, _>("surrogate").is_err());
+        assert_eq!(key.get_string_lossy("surrogate").unwrap(), "a\u{fffd}b");
+
+        key.set_value_raw("odd", REG_EXPAND_SZ, &[b'a', 0, b'b'])
+            .unwrap();
+        assert_eq!(key.get_string_lossy("odd").unwrap(), "a\u{fffd}");
+
+        key.set_value_raw("nul", REG_SZ, &[b'a', 0, 0, 0, b'b', 0])
+            .unwrap();
+        assert_eq!(key.get_string_lossy("nul").unwrap(), "a");
+
+        key.set_value_raw("number", REG_DWORD, &[1, 0, 0, 0])
+            .unwrap();
+        assert!(matches!(
+            key.get_string_lossy("number"),
+            Err(super::value::Error::TypeMismatch(..))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn read_value_into() {
+        use crate::RegType;
+
+        const KEY: &str = r"Test\registry-rust-crate-read-value-into";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("big", &vec![7u8; 1000]).unwrap();
+        key.set_value("small", &1u32).unwrap();
+        key.set_value_raw("unknown", 0x1234, &[1, 2]).unwrap();
+
+        let mut buf = Vec::new();
+        assert_eq!(
+            key.read_value_into("big", &mut buf).unwrap(),
+            (RegType::Binary, 1000)
+        );
+        assert_eq!(buf, vec![7u8; 1000]);
+        let capacity = buf.capacity();
+
+        assert_eq!(
+            key.read_value_into("small", &mut buf).unwrap(),
+            (RegType::U32, 4)
+        );
+        assert_eq!(buf, [1, 0, 0, 0]);
+        assert_eq!(buf.capacity(), capacity);
+
+        assert!(matches!(
+            key.read_value_into("unknown", &mut buf),
+            Err(super::value::Error::UnhandledType(0x1234))
+        ));
+        assert!(key.read_value_into("missing", &mut buf).is_err());
+        assert!(buf.is_empty());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn is_link() {
+        const KEY: &str = r"Test\registry-rust-crate-is-link";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.create("plain", crate::Security::AllAccess).unwrap();
+        assert!(!key.is_link("plain").unwrap());
+        assert!(key.is_link("missing").is_err());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn coercion() {
+        use crate::{Coercion, Data};
+
+        const KEY: &str = r"Test\registry-rust-crate-coercion";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("dword", &Data::U32(5)).unwrap();
+        key.set_value("qword", &Data::U64(6)).unwrap();
+        key.set_value("big", &Data::U64(1 << 40)).unwrap();
+        key.set_value("decimal", " 7 ").unwrap();
+        key.set_value("hex", "0x10").unwrap();
+        key.set_value("negative", "-1").unwrap();
+        key.set_value(
+            "expand",
+            &Data::ExpandString(utfx::U16CString::from_str("%TEMP%").unwrap()),
+        )
+        .unwrap();
+        key.set_value("flag", "True").unwrap();
+
+        let strict = |name| key.get_value_with_coercion::<u64, _>(name, Coercion::Strict);
+        assert_eq!(strict("qword").unwrap(), 6);
+        assert!(strict("dword").is_err());
+        assert!(strict("decimal").is_err());
+        assert!(key
+            .get_value_with_coercion::<String, _>("expand", Coercion::Strict)
+            .is_err());
+
+        let lenient = Coercion::Lenient;
+        assert_eq!(
+            key.get_value_with_coercion::<u32, _>("qword", lenient)
+                .unwrap(),
+            6
+        );
+        assert!(key
+            .get_value_with_coercion::<u32, _>("big", lenient)
+            .is_err());
+        assert_eq!(
+            key.get_value_with_coercion::<u64, _>("decimal", lenient)
+                .unwrap(),
+            7
+        );
+        assert_eq!(
+            key.get_value_with_coercion::<u32, _>("hex", lenient)
+                .unwrap(),
+            16
+        );
+        assert_eq!(
+            key.get_value_with_coercion::<i32, _>("negative", lenient)
+                .unwrap(),
+            -1
+        );
+        assert!(key
+            .get_value_with_coercion::<u32, _>("negative", lenient)
+            .is_err());
+        assert!(key
+            .get_value_with_coercion::<bool, _>("flag", lenient)
+            .unwrap());
+        assert_eq!(
+            key.get_value_with_coercion::<String, _>("dword", lenient)
+                .unwrap(),
+            "5"
+        );
+        assert_eq!(
+            key.get_value_with_coercion::<String, _>("expand", lenient)
+                .unwrap(),
+            "%TEMP%"
+        );
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn update_value() {
+        use winapi::um::winnt::REG_EXPAND_SZ;
+
+        const KEY: &str = r"Test\registry-rust-crate-update-value";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value_raw("expand", REG_EXPAND_SZ, &[b'a', 0, 0, 0])
+            .unwrap();
+        key.set_value_raw("custom", 0x1234, &[1]).unwrap();
+
+        let bytes = [b'%', 0, b'b', 0, b'%', 0, 0, 0];
+        assert_eq!(key.update_value("expand", &bytes).unwrap(), REG_EXPAND_SZ);
+        assert_eq!(
+            key.value_raw("expand").unwrap(),
+            (REG_EXPAND_SZ, bytes.to_vec())
+        );
+
+        assert_eq!(key.update_value("custom", &[2, 3]).unwrap(), 0x1234);
+        assert_eq!(key.value_raw("custom").unwrap(), (0x1234, vec![2, 3]));
+
+        assert!(matches!(
+            key.update_value("missing", &[1]),
+            Err(super::value::Error::NotFound(..))
+        ));
+        assert!(!key.value_exists("missing").unwrap());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn multi_string_editing() {
+        use crate::key::Position;
+
+        const KEY: &str = r"Test\registry-rust-crate-multi-string-editing";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        assert!(!key.multi_string_contains("list", "a").unwrap());
+        assert_eq!(key.multi_string_remove("list", "a").unwrap(), 0);
+        assert!(!key.value_exists("list").unwrap());
+
+        assert!(key.multi_string_append("list", "a").unwrap());
+        assert!(key.multi_string_append("list", "b").unwrap());
+        assert!(!key.multi_string_append("list", "a").unwrap());
+        assert!(key
+            .multi_string_append_with("list", "c", Position::Front, false)
+            .unwrap());
+        assert!(key
+            .multi_string_append_with("list", "a", Position::Back, true)
+            .unwrap());
+        assert_eq!(key.get_multi_string("list").unwrap(), ["c", "a", "b", "a"]);
+        assert!(key.multi_string_contains("list", "b").unwrap());
+
+        assert_eq!(key.multi_string_remove("list", "a").unwrap(), 2);
+        assert_eq!(key.get_multi_string("list").unwrap(), ["c", "b"]);
+
+        key.set_value("number", &1u32).unwrap();
+        assert!(matches!(
+            key.multi_string_append("number", "a"),
+            Err(super::value::Error::TypeMismatch(..))
+        ));
 
-    let result = if is_recursive {
-        unsafe { RegDeleteTreeW(base, path.as_ptr()) }
-    } else {
-        unsafe { RegDeleteKeyW(base, path.as_ptr()) }
-    };
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
 
-    if result == 0 {
-        return Ok(());
+    #[test]
+    fn path_list_editing() {
+        use crate::{key::Position, Data};
+
+        const KEY: &str = r"Test\registry-rust-crate-path-list-editing";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let expand = |s: &str| Data::ExpandString(utfx::U16CString::from_str(s).unwrap());
+        key.set_value("Path", &expand(r"%USERPROFILE%\bin;;C:\Tools;"))
+            .unwrap();
+
+        assert!(key
+            .path_list_add("Path", r"C:\Other", Position::Front)
+            .unwrap());
+        assert!(!key
+            .path_list_add("Path", r"c:\tools", Position::Back)
+            .unwrap());
+        assert_eq!(
+            key.value("Path").unwrap(),
+            expand(r"C:\Other;%USERPROFILE%\bin;C:\Tools")
+        );
+
+        assert_eq!(key.path_list_remove("Path", r"C:\OTHER").unwrap(), 1);
+        assert_eq!(key.path_list_remove("Path", r"C:\Missing").unwrap(), 0);
+        assert_eq!(
+            key.value("Path").unwrap(),
+            expand(r"%USERPROFILE%\bin;C:\Tools")
+        );
+
+        key.set_value("plain", "a").unwrap();
+        key.path_list_add("plain", "b", Position::Back).unwrap();
+        assert_eq!(key.get_value::<String, _>("plain").unwrap(), "a;b");
+        assert!(matches!(key.value("plain").unwrap(), Data::String(_)));
+
+        key.path_list_add("new", "a", Position::Back).unwrap();
+        assert_eq!(key.value("new").unwrap(), expand("a"));
+
+        for entry in &["", "a;b"] {
+            assert!(matches!(
+                key.path_list_add("new", entry, Position::Back),
+                Err(crate::value::Error::InvalidInput(_))
+            ));
+        }
+        assert_eq!(key.value("new").unwrap(), expand("a"));
+
+        // Entries are compared by code unit, so `ß` does not match `SS`.
+        key.path_list_add("new", "\u{df}", Position::Back).unwrap();
+        assert!(key.path_list_add("new", "SS", Position::Back).unwrap());
+        assert_eq!(key.path_list_remove("new", "ss").unwrap(), 1);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
     }
 
-    let path = path.to_string_lossy();
-    Err(Error::from_code(result, path))
-}
+    #[test]
+    fn keys() {
+        const KEY: &str = r"Test\registry-rust-crate-keys";
 
-#[inline]
-pub(crate) fn create_hkey<P>(base: HKEY, path: P, sec: Security) -> Result<HKEY, Error>
-where
-    P: AsRef<U16CStr>,
-{
-    let path = path.as_ref();
-    let mut hkey = std::ptr::null_mut();
-    let result = unsafe {
-        RegCreateKeyExW(
-            base,
-            path.as_ptr(),
-            0,
-            std::ptr::null_mut(),
-            0,
-            sec.bits(),
-            std::ptr::null_mut(),
-            &mut hkey,
-            std::ptr::null_mut(),
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let before = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.create("a", crate::Security::AllAccess).unwrap();
+        key.create("b", crate::Security::AllAccess).unwrap();
+
+        let mut iter = key.keys();
+        assert_eq!(iter.remaining(), 2);
+        iter.next();
+        assert_eq!(iter.size_hint(), (1, Some(1)));
+
+        let keys = key.keys().collect::<Result<Vec<_>, _>>().unwrap();
+        let mut names = keys
+            .iter()
+            .map(|x| x.name().to_string_lossy())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["a", "b"]);
+        assert!(keys.iter().all(|x| x.last_write_time() > before));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn value_names_and_raw_values() {
+        const KEY: &str = r"Test\registry-rust-crate-value-names";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("a", &1u32).unwrap();
+        key.set_value_raw("b", 0x1234, &[1, 2, 3]).unwrap();
+
+        assert_eq!(key.values().remaining(), 2);
+        assert_eq!(key.value_names().size_hint(), (2, Some(2)));
+        assert_eq!(key.raw_values().skip(1).size_hint(), (1, Some(1)));
+
+        let mut names = key
+            .value_names()
+            .map(|x| x.unwrap().to_string_lossy())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["a", "b"]);
+
+        let mut raw = key
+            .raw_values()
+            .map(|x| x.map(|(name, ty, data)| (name.to_string_lossy(), ty, data)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        raw.sort();
+        assert_eq!(
+            raw,
+            [
+                (
+                    "a".to_string(),
+                    winapi::um::winnt::REG_DWORD,
+                    vec![1, 0, 0, 0]
+                ),
+                ("b".to_string(), 0x1234, vec![1, 2, 3]),
+            ]
+        );
+
+        // Values grown after the iterator sized its buffer are still read.
+        let mut values = key.values();
+        key.set_value("a", &vec![9u8; 100]).unwrap();
+        let grown = crate::Data::Binary(vec![9; 100]);
+        assert!(values.any(|x| matches!(x, Ok(x) if x.data() == &grown)));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn reverse_enumeration() {
+        const KEY: &str = r"Test\registry-rust-crate-reverse";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        for name in &["a", "b", "c"] {
+            key.create(*name, crate::Security::AllAccess).unwrap();
+            key.set_value(*name, &1u32).unwrap();
+        }
+
+        let forward = key
+            .keys()
+            .map(|x| x.unwrap().into_name())
+            .collect::<Vec<_>>();
+        let mut backward = key
+            .keys()
+            .rev()
+            .map(|x| x.unwrap().into_name())
+            .collect::<Vec<_>>();
+        backward.reverse();
+        assert_eq!(forward, backward);
+
+        let forward = key.value_names().map(Result::unwrap).collect::<Vec<_>>();
+        let mut backward = key
+            .values()
+            .rev()
+            .map(|x| x.unwrap().into_name())
+            .collect::<Vec<_>>();
+        backward.reverse();
+        assert_eq!(forward, backward);
+
+        // Both ends meet without yielding any entry twice.
+        let mut names = key.value_names();
+        let first = names.next().unwrap().unwrap();
+        let last = names.next_back().unwrap().unwrap();
+        assert_ne!(first, last);
+        assert_eq!(names.size_hint(), (1, Some(1)));
+        assert!(names.next_back().is_some());
+        assert!(names.next().is_none());
+
+        // Entries deleted while iterating backwards are skipped.
+        let keys = key.keys();
+        key.delete("c", false).unwrap();
+        assert_eq!(keys.rev().count(), 2);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn borrowed_names() {
+        const KEY: &str = r"Test\registry-rust-crate-borrowed-names";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        for name in &["a", "bb", "ccc"] {
+            key.create(*name, crate::Security::AllAccess).unwrap();
+            key.set_value(*name, &1u32).unwrap();
+        }
+
+        let mut keys = key.keys();
+        let mut names = vec![];
+        while let Some(name) = keys.next_name() {
+            names.push(name.unwrap().to_string_lossy());
+        }
+        names.sort();
+        assert_eq!(names, ["a", "bb", "ccc"]);
+
+        let mut values = key.value_names();
+        let mut names = vec![];
+        while let Some(name) = values.next_name() {
+            names.push(name.unwrap().to_string_lossy());
+        }
+        names.sort();
+        assert_eq!(names, ["a", "bb", "ccc"]);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn enumerate_without_query() {
+        const KEY: &str = r"Test\registry-rust-crate-enumerate-without-query";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.create("a", crate::Security::Read).unwrap();
+        key.set_value("b", &crate::Data::U32(1)).unwrap();
+
+        let key = Hive::CurrentUser
+            .open(KEY, crate::Security::EnumerateSubKeys)
+            .unwrap();
+        let names = key
+            .keys()
+            .map(|x| x.unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a"]);
+        assert_eq!(key.keys().rev().count(), 1);
+
+        let mut values = key.values();
+        assert!(values.next().unwrap().is_err());
+        assert!(values.next().is_none());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn enumeration_drift() {
+        const KEY: &str = r"Test\registry-rust-crate-drift";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        for name in &["a", "b", "c", "d"] {
+            key.create(*name, crate::Security::AllAccess).unwrap();
+            key.set_value(*name, &1u32).unwrap();
+        }
+
+        let name = |x: Option<Result<crate::iter::keys::KeyRef<'_>, _>>| {
+            x.unwrap().unwrap().name().to_string_lossy()
+        };
+        let mut keys = key.keys();
+        assert_eq!(name(keys.next()), "a");
+        key.delete("a", false).unwrap();
+        assert_eq!(name(keys.next()), "b");
+        key.create("aa", crate::Security::AllAccess).unwrap();
+        assert_eq!(name(keys.next()), "c");
+        assert_eq!(name(keys.next()), "d");
+        assert!(keys.next().is_none());
+
+        let mut values = key.value_names();
+        assert_eq!(values.next().unwrap().unwrap().to_string_lossy(), "a");
+        key.delete_value("a").unwrap();
+        assert_eq!(values.next().unwrap().unwrap().to_string_lossy(), "b");
+        assert_eq!(values.count(), 2);
+
+        // Without checking, deleting the value last read skips the one after it.
+        let mut values = key.value_names().unchecked();
+        assert_eq!(values.next().unwrap().unwrap().to_string_lossy(), "b");
+        key.delete_value("b").unwrap();
+        assert_eq!(values.next().unwrap().unwrap().to_string_lossy(), "d");
+
+        for subkey in key.keys().snapshot().unwrap() {
+            key.delete(subkey.name(), false).unwrap();
+        }
+        for value in key.values().snapshot().unwrap() {
+            key.delete_value(value.name()).unwrap();
+        }
+        assert_eq!(key.keys().remaining(), 0);
+        assert_eq!(key.values().remaining(), 0);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn sorted_keys() {
+        use crate::Order;
+
+        const KEY: &str = r"Test\registry-rust-crate-sorted-keys";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        // Set each subkey's last write time, as keys created in quick succession can share one.
+        #[link(name = "ntdll")]
+        extern "system" {
+            fn NtSetInformationKey(
+                key_handle: winapi::um::winnt::HANDLE,
+                key_set_information_class: u32,
+                key_set_information: *mut std::ffi::c_void,
+                length: u32,
+            ) -> i32;
+        }
+
+        for (i, name) in ["b", "_", "C", "a", "\u{df}"].iter().enumerate() {
+            let subkey = key.create(*name, crate::Security::AllAccess).unwrap();
+            let mut time = 132_000_000_000_000_000i64 + i as i64 * 10_000_000;
+            let status = unsafe {
+                NtSetInformationKey(subkey.handle as _, 0, &mut time as *mut i64 as *mut _, 8)
+            };
+            assert!(status >= 0);
+        }
+
+        let names = |order| {
+            key.keys()
+                .sorted(order)
+                .unwrap()
+                .map(|x| x.name().to_string_lossy())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(Order::Name), ["a", "b", "C", "_", "\u{df}"]);
+        assert_eq!(names(Order::LastWrite), ["b", "_", "C", "a", "\u{df}"]);
+        assert_eq!(names(Order::None).len(), 5);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn lazy_values() {
+        const KEY: &str = r"Test\registry-rust-crate-lazy-values";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("small", &1u32).unwrap();
+        key.set_value("large", &crate::Data::Binary(vec![7; 4096]))
+            .unwrap();
+
+        let values = key.lazy_values().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(values.len(), 2);
+
+        let small = values
+            .iter()
+            .find(|x| x.name().to_string_lossy() == "small")
+            .unwrap();
+        assert_eq!(small.value_type(), winapi::um::winnt::REG_DWORD);
+        assert_eq!(small.size(), 4);
+        assert_eq!(small.read().unwrap(), crate::Data::U32(1));
+
+        let large = values
+            .iter()
+            .find(|x| x.name().to_string_lossy() == "large")
+            .unwrap();
+        assert_eq!(large.value_type(), winapi::um::winnt::REG_BINARY);
+        assert_eq!(large.size(), 4096);
+        assert_eq!(large.read_raw().unwrap().1.len(), 4096);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn search() {
+        use crate::{Data, Query, RegPath, SearchMatch, Security};
+
+        const KEY: &str = r"Test\registry-rust-crate-search";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        let app = key.create(r"Vendor\MyApp", Security::AllAccess).unwrap();
+        app.set_value("Path", &"C:\\MyApp\\app.exe").unwrap();
+        app.set_value("MyAppVersion", &1u32).unwrap();
+        key.create(r"Vendor\Other\Deep\myapp", Security::AllAccess)
+            .unwrap();
+
+        let wide = |x: &str| utfx::U16CString::from_str(x).unwrap();
+        let search = |query| key.search(query).collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(
+            search(Query::new("myapp")),
+            [
+                SearchMatch::Key(RegPath::new(r"Vendor\MyApp")),
+                SearchMatch::ValueData(
+                    RegPath::new(r"Vendor\MyApp"),
+                    wide("Path"),
+                    Data::String(wide("C:\\MyApp\\app.exe"))
+                ),
+                SearchMatch::ValueName(RegPath::new(r"Vendor\MyApp"), wide("MyAppVersion")),
+                SearchMatch::Key(RegPath::new(r"Vendor\Other\Deep\myapp")),
+            ]
+        );
+
+        let found = search(Query {
+            match_case: true,
+            whole_string: true,
+            value_names: false,
+            value_data: false,
+            ..Query::new("myapp")
+        });
+        assert_eq!(
+            found,
+            [SearchMatch::Key(RegPath::new(r"Vendor\Other\Deep\myapp"))]
+        );
+
+        let found = search(Query {
+            max_depth: Some(3),
+            ..Query::new("myapp")
+        });
+        assert_eq!(found.len(), 3);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn open_keys() {
+        use crate::Security;
+
+        const KEY: &str = r"Test\registry-rust-crate-open-keys";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        for name in &["a", "b"] {
+            let subkey = key.create(*name, Security::AllAccess).unwrap();
+            subkey.set_value("name", *name).unwrap();
+        }
+
+        let subkeys = key.open_keys(Security::Read);
+        assert_eq!(subkeys.remaining(), 2);
+        let mut names = subkeys
+            .map(|x| x.unwrap().get_value::<String, _>("name").unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["a", "b"]);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn walk() {
+        use crate::{CreateOptions, OnError, Security, WalkOptions};
+
+        const KEY: &str = r"Test\registry-rust-crate-walk";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        key.create(r"a\b", Security::AllAccess)
+            .unwrap()
+            .set_value("x", &1u32)
+            .unwrap();
+        key.create(r"a\c", Security::AllAccess).unwrap();
+        key.create("d", Security::AllAccess).unwrap();
+
+        // Only grants the right to delete it, so it cannot be opened for reading.
+        let opts = CreateOptions {
+            security: Security::AllAccess,
+            security_descriptor: Some("D:P(A;;SD;;;WD)".into()),
+            ..Default::default()
+        };
+        key.create_with("locked", &opts).unwrap();
+
+        let walk = |opts: WalkOptions| key.walk(opts).collect::<Vec<_>>();
+
+        let entries = walk(WalkOptions {
+            include_values: true,
+            on_error: OnError::Skip,
+            ..Default::default()
+        });
+        let paths = entries
+            .iter()
+            .map(|x| x.as_ref().unwrap().path.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, [r"a", r"a\b", r"a\c", "d"]);
+        let b = entries[1].as_ref().unwrap();
+        assert_eq!(b.depth, 2);
+        assert_eq!(
+            b.values,
+            [(
+                utfx::U16CString::from_str("x").unwrap(),
+                crate::Data::U32(1)
+            )]
+        );
+
+        let entries = walk(WalkOptions::default());
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries.iter().filter(|x| x.is_err()).count(), 1);
+
+        let entries = walk(WalkOptions {
+            on_error: OnError::Stop,
+            ..Default::default()
+        });
+        assert!(entries.last().unwrap().is_err());
+
+        let paths = |opts: WalkOptions| {
+            walk(opts)
+                .into_iter()
+                .map(|x| x.unwrap().path.to_string())
+                .collect::<Vec<_>>()
+        };
+        let shallow = WalkOptions {
+            max_depth: Some(1),
+            on_error: OnError::Skip,
+            ..Default::default()
+        };
+        assert_eq!(paths(shallow.clone()), ["a", "d"]);
+        assert!(paths(WalkOptions {
+            max_depth: Some(0),
+            ..Default::default()
+        })
+        .is_empty());
+
+        // Pruned keys are never opened, so the locked key reports no error.
+        let pruned = WalkOptions {
+            prune: Some(std::sync::Arc::new(|path| {
+                path.name() == Some("locked") || path.as_str() == r"a\b"
+            })),
+            ..Default::default()
+        };
+        assert_eq!(paths(pruned.clone()), ["a", r"a\c", "d"]);
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::iter::ParallelIterator;
+
+            let par_paths = |opts| {
+                let mut paths = key
+                    .par_walk(opts)
+                    .map(|x| x.unwrap().path.to_string())
+                    .collect::<Vec<_>>();
+                paths.sort();
+                paths
+            };
+            assert_eq!(par_paths(shallow), ["a", "d"]);
+            assert_eq!(par_paths(pruned), ["a", r"a\c", "d"]);
+
+            let mut paths = key
+                .par_walk(WalkOptions {
+                    on_error: OnError::Skip,
+                    ..Default::default()
+                })
+                .map(|x| x.unwrap().path.to_string())
+                .collect::<Vec<_>>();
+            paths.sort();
+            assert_eq!(paths, [r"a", r"a\b", r"a\c", "d"]);
+            assert_eq!(
+                key.par_walk(WalkOptions::default())
+                    .filter(|x| x.is_err())
+                    .count(),
+                1
+            );
+        }
+
+        key.delete("locked", false).unwrap();
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn matching() {
+        use crate::Security;
+
+        const KEY: &str = r"Test\registry-rust-crate-matching";
+
+        let _ = Hive::CurrentUser.delete(KEY, true);
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        key.create(r"Uninstall\AppA", Security::AllAccess).unwrap();
+        key.create(r"Uninstall\AppB\Nested", Security::AllAccess)
+            .unwrap();
+        key.create(r"Other\AppC", Security::AllAccess).unwrap();
+        key.set_value("DisplayName", "App").unwrap();
+        key.set_value("displayversion", "1.0").unwrap();
+        key.set_value("Publisher", "Me").unwrap();
+
+        let paths = |pattern| {
+            let mut paths = key
+                .keys_matching(pattern)
+                .map(|x| x.unwrap().to_string())
+                .collect::<Vec<_>>();
+            paths.sort();
+            paths
+        };
+        assert_eq!(
+            paths(r"Uninstall\*"),
+            [r"Uninstall\AppA", r"Uninstall\AppB"]
+        );
+        assert_eq!(
+            paths(r"*\App?"),
+            [r"Other\AppC", r"Uninstall\AppA", r"Uninstall\AppB"]
+        );
+        assert_eq!(paths(r"uninstall\*b\*"), [r"Uninstall\AppB\Nested"]);
+        assert!(paths("Missing").is_empty());
+
+        let mut names = key
+            .values_matching("Display*")
+            .map(|x| x.unwrap().name().to_string_lossy())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["DisplayName", "displayversion"]);
+
+        #[cfg(feature = "regex")]
+        {
+            let regex = |x| regex::Regex::new(x).unwrap();
+            assert_eq!(key.keys_matching_regex(&[regex("^Uninstall$")]).count(), 1);
+            let mut paths = key
+                .keys_matching_regex(&[regex("^Uninstall$"), regex(".*")])
+                .map(|x| x.unwrap().to_string())
+                .collect::<Vec<_>>();
+            paths.sort();
+            assert_eq!(paths, [r"Uninstall\AppA", r"Uninstall\AppB"]);
+            assert_eq!(key.values_matching_regex(&regex("^Pub")).count(), 1);
+        }
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn conversion_traits() {
+        const KEY: &str = r"Test\registry-rust-crate-conversion-traits";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("Port", &8080u32).unwrap();
+        key.set_value("Big", &(1u64 << 40)).unwrap();
+        key.set_value("Enabled", &true).unwrap();
+        key.set_value("Name", "registry").unwrap();
+        key.set_value("Path", std::ffi::OsStr::new(r"C:\Windows"))
+            .unwrap();
+        key.set_value("Blob", &vec![1u8, 2, 3]).unwrap();
+        key.set_value("List", &["a", "b"][..]).unwrap();
+
+        assert_eq!(key.get_value::<u32, _>("Port").unwrap(), 8080);
+        assert_eq!(key.get_value::<u64, _>("Big").unwrap(), 1 << 40);
+        assert!(key.get_value::<bool, _>("Enabled").unwrap());
+        let name: String = key.get_value("Name").unwrap();
+        assert_eq!(name, "registry");
+        let path: std::ffi::OsString = key.get_value("Path").unwrap();
+        assert_eq!(path, r"C:\Windows");
+        assert_eq!(key.get_value::<Vec<u8>, _>("Blob").unwrap(), [1, 2, 3]);
+        assert_eq!(key.get_value::<Vec<String>, _>("List").unwrap(), ["a", "b"]);
+
+        match key.get_value::<String, _>("Port") {
+            Err(crate::value::Error::TypeMismatch(name, _)) => assert_eq!(name, "Port"),
+            x => panic!("{:?}", x),
+        }
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn get_u32() {
+        const KEY: &str = r"Test\registry-rust-crate-get-u32";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_value("le", &crate::Data::U32(0x12345678)).unwrap();
+        key.set_value("be", &crate::Data::U32BE(0x12345678))
+            .unwrap();
+
+        let name = utfx::U16CString::from_str("be").unwrap();
+        let (_, buf, len) = crate::value::query_value_raw(key.handle, &name).unwrap();
+        let bytes = crate::value::u16_to_u8_vec(buf);
+        assert_eq!(&bytes[..len], &[0x12, 0x34, 0x56, 0x78]);
+
+        assert_eq!(key.get_u32("le").unwrap(), 0x12345678);
+        assert_eq!(key.get_u32("be").unwrap(), 0x12345678);
+        key.set_value("u64", &crate::Data::U64(1)).unwrap();
+        assert!(key.get_u32("u64").is_err());
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn expand_string() {
+        use crate::Expand;
+        const KEY: &str = r"Test\registry-rust-crate-expand-string";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let path = utfx::U16CString::from_str(r"%SystemRoot%\System32").unwrap();
+        key.set_value("path", &crate::Data::ExpandString(path))
+            .unwrap();
+
+        let raw = key.get_expand_string("path", Expand::No).unwrap();
+        assert_eq!(raw, r"%SystemRoot%\System32");
+
+        let expanded = key.get_expand_string("path", Expand::Yes).unwrap();
+        let root = std::env::var("SystemRoot").unwrap();
+        assert_eq!(expanded, format!(r"{}\System32", root));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn multi_string() {
+        const KEY: &str = r"Test\registry-rust-crate-multi-string";
+
+        let key = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        key.set_multi_string("empty", &[] as &[&str]).unwrap();
+        assert!(key.get_multi_string("empty").unwrap().is_empty());
+
+        key.set_multi_string("list", &["a", "", "b"]).unwrap();
+        assert_eq!(key.get_multi_string("list").unwrap(), ["a", "", "b"]);
+
+        // Written without any terminators after the last string, as some tools do.
+        let bytes = [b'a', 0, 0, 0, b'b', 0];
+        crate::value::set_value_raw(
+            key.handle,
+            &utfx::U16CString::from_str("raw").unwrap(),
+            7,
+            &bytes,
         )
-    };
+        .unwrap();
+        assert_eq!(key.get_multi_string("raw").unwrap(), ["a", "b"]);
 
-    if result == 0 {
-        return Ok(hkey);
+        key.set_value("u32", &crate::Data::U32(1)).unwrap();
+        let result = key.get_multi_string("u32");
+        assert!(matches!(result, Err(crate::value::Error::TypeMismatch(..))));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
     }
 
-    let path = path.to_string_lossy();
-    Err(Error::from_code(result, path))
-}
+    #[test]
+    fn copy_tree() {
+        const KEY: &str = r"Test\registry-rust-crate-copy-tree";
 
-#[cfg(test)]
-mod tests {
-    use crate::Hive;
+        let parent = Hive::CurrentUser
+            .create(KEY, crate::Security::AllAccess)
+            .unwrap();
+        let src = parent
+            .create(r"src\nested", crate::Security::AllAccess)
+            .unwrap();
+        src.set_value("u32", &crate::Data::U32(42)).unwrap();
+        let dest = parent.create("dest", crate::Security::AllAccess).unwrap();
+
+        parent
+            .open("src", crate::Security::Read)
+            .unwrap()
+            .copy_tree_to(&dest)
+            .unwrap();
+        let copied = dest.open("nested", crate::Security::Read).unwrap();
+        assert!(matches!(copied.value("u32").unwrap(), crate::Data::U32(42)));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
 
     #[test]
-    fn test_paths() {
+    fn key_info() {
+        const KEY: &str = r"Test\registry-rust-crate-info";
+
         let key = Hive::CurrentUser
-            .open("SOFTWARE\\Microsoft", crate::Security::Read)
+            .create(KEY, crate::Security::AllAccess)
             .unwrap();
-        assert_eq!(key.to_string(), "HKEY_CURRENT_USER\\SOFTWARE\\Microsoft")
+        key.create("subkey", crate::Security::AllAccess).unwrap();
+        key.set_value("binary", &crate::Data::Binary(vec![0; 16]))
+            .unwrap();
+
+        let info = key.info().unwrap();
+        assert_eq!(info.subkey_count, 1);
+        assert_eq!(info.max_subkey_name_len, 6);
+        assert_eq!(info.value_count, 1);
+        assert_eq!(info.max_value_name_len, 6);
+        assert_eq!(info.max_value_data_len, 16);
+        assert!(info.last_write_time > std::time::UNIX_EPOCH);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
     }
 
     #[test]
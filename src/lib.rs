@@ -25,18 +25,57 @@
 //! [`RegKey`](struct.RegKey.html)s also support iteration of all subkeys with the `keys()` function, and all values with the `values()` function.
 //!
 
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+#[path = "derive.rs"]
+pub mod __private;
+#[cfg(feature = "serialized")]
+pub mod blob;
+#[cfg(feature = "serde")]
+pub mod de;
+mod diff;
 mod hive;
 pub mod iter;
+#[cfg(feature = "serde_json")]
+pub mod json;
 pub mod key;
+mod path;
+mod quota;
 mod sec;
+#[cfg(feature = "serde")]
+pub mod ser;
+mod stream;
+#[cfg(feature = "handle-tracking")]
+mod tracking;
+mod transaction;
 pub mod value;
 
-pub use hive::Hive;
+#[cfg(feature = "serde")]
+pub use de::{from_key, from_key_with_config};
+pub use diff::{diff, Change, Diff, MergePolicy};
+pub use hive::{Hive, LoadedHive, ParseHiveError};
+pub use iter::keys::Order;
+pub use iter::search::{Query, SearchMatch};
+pub use iter::walk::{OnError, Prune, WalkEntry, WalkOptions};
 #[doc(inline)]
-pub use key::RegKey;
-pub use sec::Security;
+pub use key::{
+    Disposition, KeyInfo, Position, RegKey, SaveFormat, TempKey, TreeStats, Virtualization,
+};
+pub use path::RegPath;
+pub use quota::{system_registry_quota, RegistryQuota};
+#[cfg(feature = "derive")]
+pub use registry_derive::RegistrySettings;
+pub use sec::{CreateOptions, Options, ReadOptions, RestoreFlags, Security, TypeFilter, View};
+#[cfg(feature = "serde")]
+pub use ser::to_key;
+pub use stream::{ValueReader, ValueWriter};
+#[cfg(feature = "handle-tracking")]
+pub use tracking::{dump_open_handles, OpenHandle};
+pub use transaction::Transaction;
 #[doc(inline)]
-pub use value::Data;
+pub use value::{
+    Coercion, Data, EnumRepr, Expand, FromRegValue, RegEnum, ToRegValue, Type as RegType,
+};
 
 #[derive(Debug, thiserror::Error)]
 /// A higher level convenience error type for functions that do
@@ -51,8 +90,14 @@ pub enum Error {
     Value(#[from] value::Error),
     #[error("A values error occurred.")]
     Values(#[from] iter::values::Error),
+    #[error("A merge conflict occurred: {0:?}")]
+    Conflict(Box<Change>),
 }
 
+// Lets generated code refer to `::registry` in this crate's own tests.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as registry;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,6 +142,129 @@ mod tests {
         );
     }
 
+    #[test]
+    fn load_app_key() {
+        let path = std::env::temp_dir().join("registry-rust-crate-appkey.dat");
+        let _ = std::fs::remove_file(&path);
+
+        {
+            let regkey = Hive::load_app_key(&path, Security::AllAccess).unwrap();
+            regkey.set_value("u32", &Data::U32(42)).unwrap();
+        }
+
+        {
+            let regkey = Hive::load_app_key(&path, Security::Read).unwrap();
+            assert!(matches!(regkey.value("u32").unwrap(), Data::U32(42)));
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn transaction_commit_and_rollback() {
+        const KEY: &str = r"Test\registry-rust-crate-transaction";
+
+        let transaction = Transaction::new().unwrap();
+        Hive::CurrentUser
+            .create_transacted(KEY, Security::AllAccess, &transaction)
+            .unwrap();
+        transaction.rollback().unwrap();
+        assert!(Hive::CurrentUser
+            .open(KEY, Security::Read)
+            .unwrap_err()
+            .is_not_found());
+
+        let transaction = Transaction::new().unwrap();
+        let regkey = Hive::CurrentUser
+            .create_transacted(KEY, Security::AllAccess, &transaction)
+            .unwrap();
+        regkey.set_value("u32", &Data::U32(42)).unwrap();
+        transaction.commit().unwrap();
+
+        let regkey = Hive::CurrentUser.open(KEY, Security::Read).unwrap();
+        assert!(matches!(regkey.value("u32").unwrap(), Data::U32(42)));
+        drop(regkey);
+
+        Hive::CurrentUser
+            .create(format!(r"{}\a\b", KEY), Security::AllAccess)
+            .unwrap();
+        let transaction = Transaction::new().unwrap();
+        Hive::CurrentUser
+            .delete_transacted(KEY, true, &transaction)
+            .unwrap();
+        transaction.rollback().unwrap();
+        assert!(Hive::CurrentUser
+            .open(format!(r"{}\a\b", KEY), Security::Read)
+            .is_ok());
+
+        let transaction = Transaction::new().unwrap();
+        Hive::CurrentUser
+            .delete_transacted(KEY, true, &transaction)
+            .unwrap();
+        transaction.commit().unwrap();
+        assert!(Hive::CurrentUser
+            .open(KEY, Security::Read)
+            .unwrap_err()
+            .is_not_found());
+    }
+
+    #[test]
+    fn view_is_inherited() {
+        let regkey = Hive::LocalMachine
+            .open_with_view("SOFTWARE", Security::Read, View::Force32)
+            .unwrap();
+        assert_eq!(regkey.view(), View::Force32);
+
+        let subkey = regkey.open("Microsoft", Security::Read).unwrap();
+        assert_eq!(subkey.view(), View::Force32);
+
+        let subkey = regkey
+            .keys()
+            .next()
+            .unwrap()
+            .unwrap()
+            .open(Security::Read)
+            .unwrap();
+        assert_eq!(subkey.view(), View::Force32);
+    }
+
+    #[test]
+    fn create_volatile() {
+        const KEY: &str = r"Test\registry-rust-crate-volatile";
+
+        let regkey = Hive::CurrentUser
+            .create_with_options(KEY, Security::AllAccess, Options::Volatile)
+            .unwrap();
+        assert!(regkey.create("persistent", Security::AllAccess).is_err());
+        regkey
+            .create_with_options("volatile", Security::AllAccess, Options::Volatile)
+            .unwrap();
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn create_disposition() {
+        const KEY: &str = r"Test\registry-rust-crate-disposition";
+
+        let (_, disposition) = Hive::CurrentUser
+            .create_with_disposition(KEY, Security::AllAccess)
+            .unwrap();
+        assert_eq!(disposition, Disposition::CreatedNewKey);
+
+        let (regkey, disposition) = Hive::CurrentUser
+            .create_with_disposition(KEY, Security::AllAccess)
+            .unwrap();
+        assert_eq!(disposition, Disposition::OpenedExistingKey);
+
+        let (_, disposition) = regkey
+            .create_with_disposition("subkey", Security::AllAccess)
+            .unwrap();
+        assert_eq!(disposition, Disposition::CreatedNewKey);
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
     #[test]
     fn set_value_and_delete() {
         let regkey = Hive::CurrentUser
@@ -114,7 +282,7 @@ mod tests {
                 ]),
             )
             .unwrap();
-        regkey.set_value("nothing", &Data::None).unwrap();
+        regkey.set_value("nothing", &Data::None(vec![])).unwrap();
         regkey
             .set_value("some binary", &Data::Binary(vec![1, 2, 3, 4, 255]))
             .unwrap();
@@ -143,6 +311,60 @@ mod tests {
             r"HKEY_CURRENT_USER\Test\registry-rust-crate\subkey"
         );
 
-        Hive::CurrentUser.delete("Test", true).unwrap();
+        Hive::CurrentUser
+            .delete(r"Test\registry-rust-crate", true)
+            .unwrap();
+    }
+
+    #[test]
+    fn data_accessors() {
+        use std::convert::TryFrom;
+
+        let string = Data::String("Meow".try_into().unwrap());
+        assert_eq!(string.as_str().unwrap().to_string_lossy(), "Meow");
+        assert_eq!(string.as_u32(), None);
+        assert_eq!(String::try_from(string.clone()).unwrap(), "Meow");
+
+        assert_eq!(Data::U32BE(1).as_u32(), Some(1));
+        assert_eq!(Data::U32(2).as_u64(), Some(2));
+        assert_eq!(u64::try_from(Data::U64(3)).unwrap(), 3);
+        assert_eq!(Data::Binary(vec![1]).as_bytes(), Some(&[1u8][..]));
+        assert_eq!(Vec::<u8>::try_from(Data::Link(vec![2])).unwrap(), [2]);
+
+        let multi = Data::MultiString(vec!["a".try_into().unwrap()]);
+        assert_eq!(multi.as_multi().unwrap().len(), 1);
+        assert_eq!(Vec::<String>::try_from(multi).unwrap(), ["a"]);
+
+        let err = u32::try_from(string).unwrap_err();
+        assert_eq!(err.to_string(), "Expected 32-bit number data, found String");
+    }
+
+    #[test]
+    fn round_trip_all_types() {
+        const KEY: &str = r"Test\registry-rust-crate-round-trip";
+
+        let regkey = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        let values = vec![
+            Data::None(vec![1, 2]),
+            Data::String("Meow meow".try_into().unwrap()),
+            Data::ExpandString(r"%SystemRoot%\System32".try_into().unwrap()),
+            Data::Binary(vec![1, 2, 3, 4, 255]),
+            Data::U32(0x1234FEFE),
+            Data::U32BE(0x1234FEFE),
+            Data::Link(vec![b'\\', 0, b'R', 0]),
+            Data::MultiString(vec!["a".try_into().unwrap(), "b".try_into().unwrap()]),
+            Data::ResourceList(vec![1, 0, 0, 0, 5]),
+            Data::FullResourceDescriptor(vec![2, 0, 0]),
+            Data::ResourceRequirementsList(vec![3]),
+            Data::U64(0x1234FEFE_1234FEFE),
+        ];
+
+        for (i, data) in values.iter().enumerate() {
+            let name = format!("value{}", i);
+            regkey.set_value(&name, data).unwrap();
+            assert_eq!(&regkey.value(&name).unwrap(), data);
+        }
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
     }
 }
@@ -0,0 +1,184 @@
+use std::{
+    convert::{Infallible, TryFrom},
+    fmt::Display,
+    str::FromStr,
+};
+
+use utfx::U16CString;
+
+/// A registry path relative to a hive or key, such as `SOFTWARE\Microsoft\Windows`.
+///
+/// Paths are normalized on construction: leading and trailing backslashes are
+/// stripped, and repeated backslashes are collapsed into one. Forward slashes are
+/// left as they are, as they are valid in key names.
+///
+/// A `RegPath` can be passed anywhere a key path is accepted, such as
+/// [`RegKey::open`](struct.RegKey.html#method.open) or
+/// [`Hive::create`](enum.Hive.html#method.create), and is only converted to UTF-16
+/// when it is used.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RegPath(String);
+
+impl RegPath {
+    /// Creates a normalized path.
+    pub fn new<S: AsRef<str>>(path: S) -> RegPath {
+        let components = path
+            .as_ref()
+            .split('\\')
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+        RegPath(components.join("\\"))
+    }
+
+    /// Returns a new path with the given path appended to this one.
+    pub fn join<S: AsRef<str>>(&self, path: S) -> RegPath {
+        let path = RegPath::new(path);
+
+        if self.0.is_empty() {
+            return path;
+        }
+
+        if path.0.is_empty() {
+            return self.clone();
+        }
+
+        RegPath(format!("{}\\{}", self.0, path.0))
+    }
+
+    /// Iterates over the key names in this path, from the outermost.
+    pub fn components(&self) -> impl Iterator<Item = &str> + '_ {
+        self.0.split('\\').filter(|x| !x.is_empty())
+    }
+
+    /// The path of the parent key, or `None` if this path is empty.
+    pub fn parent(&self) -> Option<RegPath> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        match self.0.rfind('\\') {
+            Some(i) => Some(RegPath(self.0[..i].to_string())),
+            None => Some(RegPath::default()),
+        }
+    }
+
+    /// The name of the last key in this path, or `None` if this path is empty.
+    pub fn name(&self) -> Option<&str> {
+        self.components().last()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Converts this path to a NUL terminated UTF-16 string for FFI usage.
+    #[inline]
+    pub fn to_wide(&self) -> Result<U16CString, utfx::NulError<u16>> {
+        U16CString::from_str(&self.0)
+    }
+}
+
+impl Display for RegPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for RegPath {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for RegPath {
+    type Err = Infallible;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(RegPath::new(s))
+    }
+}
+
+impl From<&str> for RegPath {
+    #[inline]
+    fn from(path: &str) -> Self {
+        RegPath::new(path)
+    }
+}
+
+impl From<String> for RegPath {
+    #[inline]
+    fn from(path: String) -> Self {
+        RegPath::new(path)
+    }
+}
+
+impl TryFrom<RegPath> for U16CString {
+    type Error = utfx::NulError<u16>;
+
+    #[inline]
+    fn try_from(path: RegPath) -> Result<Self, Self::Error> {
+        path.to_wide()
+    }
+}
+
+impl TryFrom<&RegPath> for U16CString {
+    type Error = utfx::NulError<u16>;
+
+    #[inline]
+    fn try_from(path: &RegPath) -> Result<Self, Self::Error> {
+        path.to_wide()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            RegPath::new(r"\SOFTWARE\\Microsoft\").as_str(),
+            r"SOFTWARE\Microsoft"
+        );
+        assert_eq!(RegPath::new(r"a/b").as_str(), "a/b");
+        assert!(RegPath::new(r"\\").is_empty());
+    }
+
+    #[test]
+    fn join_and_components() {
+        let path = RegPath::new("SOFTWARE").join(r"\Microsoft\Windows\");
+        assert_eq!(path.as_str(), r"SOFTWARE\Microsoft\Windows");
+        assert_eq!(
+            path.components().collect::<Vec<_>>(),
+            ["SOFTWARE", "Microsoft", "Windows"]
+        );
+        assert_eq!(path.name(), Some("Windows"));
+        assert_eq!(RegPath::default().join("a").as_str(), "a");
+        assert_eq!(path.join("").as_str(), path.as_str());
+    }
+
+    #[test]
+    fn parent() {
+        let path = RegPath::new(r"a\b");
+        assert_eq!(path.parent(), Some(RegPath::new("a")));
+        assert_eq!(RegPath::new("a").parent(), Some(RegPath::default()));
+        assert_eq!(RegPath::default().parent(), None);
+    }
+
+    #[test]
+    fn open_with_path() {
+        let path = RegPath::new("SOFTWARE").join("Microsoft");
+        let key = crate::Hive::CurrentUser
+            .open(&path, crate::Security::Read)
+            .unwrap();
+        assert_eq!(key.to_string(), r"HKEY_CURRENT_USER\SOFTWARE\Microsoft");
+    }
+}
@@ -0,0 +1,49 @@
+use std::io;
+
+use winapi::um::winbase::GetSystemRegistryQuota;
+
+/// The size the registry may grow to and its current size, returned by
+/// [`system_registry_quota`](fn.system_registry_quota.html).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RegistryQuota {
+    /// The maximum size of the registry in bytes.
+    pub allowed: u32,
+    /// The current size of the registry in bytes.
+    pub used: u32,
+}
+
+impl RegistryQuota {
+    /// The number of bytes the registry can still grow by.
+    #[inline]
+    pub fn remaining(&self) -> u32 {
+        self.allowed.saturating_sub(self.used)
+    }
+}
+
+/// Queries the registry's size quota and current size using `GetSystemRegistryQuota`.
+///
+/// Current versions of Windows do not enforce a quota, so `allowed` may be reported as a
+/// large value, but `used` still reflects how much the registry has grown.
+pub fn system_registry_quota() -> Result<RegistryQuota, io::Error> {
+    let mut allowed = 0u32;
+    let mut used = 0u32;
+
+    let result = unsafe { GetSystemRegistryQuota(&mut allowed, &mut used) };
+
+    if result == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(RegistryQuota { allowed, used })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn query_quota() {
+        let quota = super::system_registry_quota().unwrap();
+        assert!(quota.used > 0);
+        assert!(quota.used <= quota.allowed);
+        assert_eq!(quota.remaining(), quota.allowed - quota.used);
+    }
+}
@@ -1,5 +1,16 @@
 #![allow(non_upper_case_globals)]
 
+use std::{io, ptr::null_mut};
+
+use utfx::U16CString;
+use winapi::shared::sddl::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1};
+use winapi::um::minwinbase::SECURITY_ATTRIBUTES;
+use winapi::um::winbase::LocalFree;
+use winapi::um::winnt::PSECURITY_DESCRIPTOR;
+use winapi::um::winreg::RRF_NOEXPAND;
+
+use crate::{key, Expand};
+
 bitflags::bitflags! {
     /// A safe representation of ACL bitflags.
     pub struct Security: u32 {
@@ -11,6 +22,7 @@ bitflags::bitflags! {
         const CreateLink = 0x20;
         const Wow6464Key = 0x100;
         const Wow6432Key = 0x200;
+        const Delete = 0x10000;
         const Write = 0x20006;
         const Read = 0x20019;
         const Execute = 0x20019;
@@ -18,8 +30,231 @@ bitflags::bitflags! {
     }
 }
 
+bitflags::bitflags! {
+    /// Options for creating or opening a key.
+    pub struct Options: u32 {
+        /// The key is stored in memory only, and is lost when the system restarts or
+        /// its hive is unloaded. Subkeys of a volatile key must also be volatile.
+        const Volatile = 0x1;
+        /// The key is created as a symbolic link. See [`RegKey::create_link`](struct.RegKey.html#method.create_link).
+        const CreateLink = 0x2;
+        /// The key is opened with backup or restore semantics, ignoring its security
+        /// descriptor. The calling process must hold `SeBackupPrivilege` for read access,
+        /// or `SeRestorePrivilege` for write access, and the requested `Security` is ignored.
+        const BackupRestore = 0x4;
+        /// If the key is a symbolic link, the link itself is opened rather than its target.
+        const OpenLink = 0x8;
+    }
+}
+
+bitflags::bitflags! {
+    /// The value types accepted by `RegKey::get_value_at_with_filter` and
+    /// `Hive::get_value_with_filter`. Values of other types fail with
+    /// `value::Error::TypeMismatch`.
+    pub struct TypeFilter: u32 {
+        const None = 0x1;
+        const String = 0x2;
+        const ExpandString = 0x4;
+        const Binary = 0x8;
+        const U32 = 0x10;
+        const MultiString = 0x20;
+        const U64 = 0x40;
+        /// Any type, including those without a flag of their own such as `Data::U32BE`.
+        const Any = 0xffff;
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags controlling how a hive file is restored over a key.
+    pub struct RestoreFlags: u32 {
+        /// The restored hive is volatile, and is not saved to disk. Only valid when
+        /// restoring over the root of `Hive::LocalMachine` or `Hive::Users`.
+        const WholeHiveVolatile = 0x1;
+        /// The hive is reverted to its state at the last flush.
+        const RefreshHive = 0x2;
+        /// The hive is not flushed lazily, and is only written to disk when flushed explicitly.
+        const NoLazyFlush = 0x4;
+        /// The restore proceeds even if there are open handles to subkeys of the key.
+        const ForceRestore = 0x8;
+    }
+}
+
+impl Default for RestoreFlags {
+    fn default() -> Self {
+        RestoreFlags::empty()
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options::empty()
+    }
+}
+
+/// Options for reading a value with `RegGetValueW`, used by
+/// [`RegKey::value_with_options`](struct.RegKey.html#method.value_with_options) and
+/// [`Hive::get_value_with_options`](enum.Hive.html#method.get_value_with_options).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// The value types accepted. Values of other types fail with
+    /// `value::Error::TypeMismatch` instead of being returned as they are.
+    pub types: TypeFilter,
+    /// Whether environment variables in `REG_EXPAND_SZ` values are expanded, in which case
+    /// they are returned as `Data::String`. Windows rejects expansion combined with a filter
+    /// that includes `TypeFilter::ExpandString`, other than `TypeFilter::Any`.
+    pub expand: Expand,
+}
+
+impl ReadOptions {
+    #[inline]
+    pub(crate) fn rrf_flags(&self) -> u32 {
+        let flags = self.types.bits();
+        match self.expand {
+            Expand::Yes => flags,
+            Expand::No => flags | RRF_NOEXPAND,
+        }
+    }
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        ReadOptions {
+            types: TypeFilter::Any,
+            expand: Expand::No,
+        }
+    }
+}
+
+impl From<TypeFilter> for ReadOptions {
+    #[inline]
+    fn from(types: TypeFilter) -> Self {
+        ReadOptions {
+            types,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for TypeFilter {
+    fn default() -> Self {
+        TypeFilter::Any
+    }
+}
+
 impl Default for Security {
     fn default() -> Self {
         Security::AllAccess
     }
 }
+
+/// The WOW64 view of the registry to access.
+///
+/// By default, 32-bit processes on 64-bit Windows see a redirected view of some keys,
+/// such as `HKEY_LOCAL_MACHINE\Software`. Subkeys and keys opened by iterators
+/// inherit the view of the key they were opened from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum View {
+    /// The native view of the current process.
+    Default,
+    /// The 32-bit view, as seen by 32-bit processes.
+    Force32,
+    /// The 64-bit view, as seen by 64-bit processes.
+    Force64,
+}
+
+impl View {
+    pub(crate) fn from_security(sec: Security) -> View {
+        if sec.contains(Security::Wow6432Key) {
+            View::Force32
+        } else if sec.contains(Security::Wow6464Key) {
+            View::Force64
+        } else {
+            View::Default
+        }
+    }
+
+    /// The `RegGetValueW` flags selecting this view.
+    pub(crate) fn rrf_flags(self) -> u32 {
+        match self {
+            View::Default => 0,
+            View::Force32 => 0x20000,
+            View::Force64 => 0x10000,
+        }
+    }
+
+    /// Adds this view to the given access rights, unless they already select a view.
+    pub(crate) fn apply(self, sec: Security) -> Security {
+        if sec.intersects(Security::Wow6432Key | Security::Wow6464Key) {
+            return sec;
+        }
+
+        match self {
+            View::Default => sec,
+            View::Force32 => sec | Security::Wow6432Key,
+            View::Force64 => sec | Security::Wow6464Key,
+        }
+    }
+}
+
+/// Options for creating a key and any missing keys along its path, used by
+/// `RegKey::create_with` and `Hive::create_with`.
+///
+/// Security descriptors are given in the Security Descriptor Definition Language
+/// (SDDL), such as `D:P(A;;KA;;;SY)(A;;KA;;;BA)`, and are only applied to keys that
+/// are newly created. Keys without one inherit the ACL of their parent.
+#[derive(Debug, Clone, Default)]
+pub struct CreateOptions {
+    /// The access rights requested for the final key.
+    pub security: Security,
+    /// The access rights used while creating missing intermediate keys, which
+    /// must include `Security::CreateSubKey`.
+    pub intermediate_security: Security,
+    /// The options used to create the final key. Intermediate keys are always
+    /// created non-volatile.
+    pub options: Options,
+    /// The security descriptor of the final key, if newly created.
+    pub security_descriptor: Option<String>,
+    /// The security descriptor of intermediate keys that are newly created.
+    pub intermediate_security_descriptor: Option<String>,
+}
+
+/// A security descriptor converted from SDDL, freed on drop.
+pub(crate) struct SecurityDescriptor(PSECURITY_DESCRIPTOR);
+
+impl SecurityDescriptor {
+    pub(crate) fn from_sddl(sddl: &str) -> Result<SecurityDescriptor, key::Error> {
+        let wide = U16CString::from_str(sddl)?;
+        let mut descriptor = null_mut();
+        let result = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                wide.as_ptr(),
+                SDDL_REVISION_1 as u32,
+                &mut descriptor,
+                null_mut(),
+            )
+        };
+
+        if result == 0 {
+            return Err(key::Error::InvalidSecurityDescriptor(
+                sddl.to_string(),
+                io::Error::last_os_error(),
+            ));
+        }
+
+        Ok(SecurityDescriptor(descriptor))
+    }
+
+    pub(crate) fn attributes(&self) -> SECURITY_ATTRIBUTES {
+        SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: self.0,
+            bInheritHandle: 0,
+        }
+    }
+}
+
+impl Drop for SecurityDescriptor {
+    fn drop(&mut self) {
+        unsafe { LocalFree(self.0) };
+    }
+}
@@ -0,0 +1,755 @@
+//! Serializing data structures into registry keys with [`serde`](https://serde.rs).
+//!
+//! Struct fields and map entries are written as values of the key, and nested
+//! structs and maps are written as subkeys. Values are typed as follows:
+//!
+//! * `bool` and integers up to 32 bits are written as `REG_DWORD`, with signed
+//!   integers stored as their two's complement bits
+//! * 64-bit integers are written as `REG_QWORD`
+//! * strings, chars, floats and unit enum variants are written as `REG_SZ`
+//! * byte slices, sequences of `u8` and empty sequences are written as `REG_BINARY`
+//! * sequences of strings are written as `REG_MULTI_SZ`
+//! * `None` deletes any existing value of that name
+//!
+//! Other types, such as enums with data, fail with `Error::UnsupportedType`.
+
+use serde::ser::{self, Impossible, Serialize};
+use utfx::U16CString;
+
+use crate::{key, value, Data, RegKey, Security};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("A key error occurred.")]
+    Key(#[from] key::Error),
+
+    #[error("A value error occurred.")]
+    Value(#[from] value::Error),
+
+    #[error("Cannot serialize {0} to the registry")]
+    UnsupportedType(&'static str),
+
+    #[error("Map keys must be strings")]
+    KeyMustBeAString,
+
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Writes a struct or map into `key`, creating subkeys for nested structs and maps.
+///
+/// Existing values and subkeys not present in `value` are left as they are.
+pub fn to_key<T>(value: &T, key: &RegKey) -> Result<(), Error>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(Serializer { key, name: None })
+}
+
+/// Serializes a single value named `name` into `key`, or the contents of `key`
+/// itself if there is no name.
+struct Serializer<'a> {
+    key: &'a RegKey,
+    name: Option<&'a str>,
+}
+
+impl<'a> Serializer<'a> {
+    fn write(self, data: Data) -> Result<(), Error> {
+        match self.name {
+            Some(name) => Ok(self.key.set_value(name, &data)?),
+            None => Err(Error::UnsupportedType("a value outside of a struct or map")),
+        }
+    }
+
+    fn string(self, s: &str) -> Result<(), Error> {
+        let data = Data::String(U16CString::from_str(s).map_err(value::Error::from)?);
+        self.write(data)
+    }
+
+    fn subkey(self) -> Result<KeySerializer<'a>, Error> {
+        let key = match self.name {
+            Some(name) => Target::Owned(self.key.create(name, Security::Write)?),
+            None => Target::Borrowed(self.key),
+        };
+        Ok(KeySerializer { key, pending: None })
+    }
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = SeqSerializer<'a>;
+    type SerializeTuple = SeqSerializer<'a>;
+    type SerializeTupleStruct = SeqSerializer<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = KeySerializer<'a>;
+    type SerializeStruct = KeySerializer<'a>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.write(Data::U32(v as u32))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i32(v as i32)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.write(Data::U32(v as u32))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.write(Data::U64(v as u64))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.write(Data::U32(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.write(Data::U64(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.string(&v.to_string())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.string(&v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.string(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.string(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        self.write(Data::Binary(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        let name = match self.name {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+
+        match self.key.delete_value(name) {
+            Err(e) if e.is_not_found() => Ok(()),
+            result => Ok(result?),
+        }
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.write(Data::None(vec![]))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.string(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(Error::UnsupportedType("an enum variant with data"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer<'a>, Error> {
+        Ok(SeqSerializer {
+            target: self,
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'a>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::UnsupportedType("an enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<KeySerializer<'a>, Error> {
+        self.subkey()
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<KeySerializer<'a>, Error> {
+        self.subkey()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::UnsupportedType("an enum variant with data"))
+    }
+}
+
+enum Target<'a> {
+    Borrowed(&'a RegKey),
+    Owned(RegKey),
+}
+
+impl Target<'_> {
+    fn key(&self) -> &RegKey {
+        match self {
+            Target::Borrowed(key) => key,
+            Target::Owned(key) => key,
+        }
+    }
+}
+
+/// Writes the fields of a struct or the entries of a map as values of a key.
+struct KeySerializer<'a> {
+    key: Target<'a>,
+    pending: Option<String>,
+}
+
+impl ser::SerializeStruct for KeySerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(Serializer {
+            key: self.key.key(),
+            name: Some(key),
+        })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for KeySerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.pending = Some(key.serialize(NameSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let name = self
+            .pending
+            .take()
+            .ok_or_else(|| Error::Custom("serialize_value called before serialize_key".into()))?;
+        value.serialize(Serializer {
+            key: self.key.key(),
+            name: Some(&name),
+        })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+enum Element {
+    String(U16CString),
+    Byte(u8),
+}
+
+/// Collects the elements of a sequence, writing them as a single value once complete.
+struct SeqSerializer<'a> {
+    target: Serializer<'a>,
+    elements: Vec<Element>,
+}
+
+impl SeqSerializer<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.elements.push(value.serialize(ElementSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        // An empty sequence has no element type, and an empty `REG_BINARY` reads back
+        // as an empty sequence of any type.
+        let data = if self.elements.iter().all(|x| matches!(x, Element::Byte(_))) {
+            Data::Binary(
+                self.elements
+                    .into_iter()
+                    .filter_map(|x| match x {
+                        Element::Byte(b) => Some(b),
+                        Element::String(_) => None,
+                    })
+                    .collect(),
+            )
+        } else if self
+            .elements
+            .iter()
+            .all(|x| matches!(x, Element::String(_)))
+        {
+            Data::MultiString(
+                self.elements
+                    .into_iter()
+                    .filter_map(|x| match x {
+                        Element::String(s) => Some(s),
+                        Element::Byte(_) => None,
+                    })
+                    .collect(),
+            )
+        } else {
+            return Err(Error::UnsupportedType("a sequence of mixed types"));
+        };
+
+        self.target.write(data)
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.finish()
+    }
+}
+
+macro_rules! unsupported {
+    ($what:expr; $($method:ident($($ty:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $ty),*) -> Result<Self::Ok, Error> {
+                Err(Error::UnsupportedType($what))
+            }
+        )*
+    };
+}
+
+macro_rules! unsupported_compound {
+    ($what:expr) => {
+        fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<Self::Ok, Error> {
+            Err(Error::UnsupportedType($what))
+        }
+
+        fn serialize_newtype_variant<T: Serialize + ?Sized>(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: &T,
+        ) -> Result<Self::Ok, Error> {
+            Err(Error::UnsupportedType($what))
+        }
+
+        fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+            Err(Error::UnsupportedType($what))
+        }
+
+        fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+            Err(Error::UnsupportedType($what))
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeTupleStruct, Error> {
+            Err(Error::UnsupportedType($what))
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeTupleVariant, Error> {
+            Err(Error::UnsupportedType($what))
+        }
+
+        fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+            Err(Error::UnsupportedType($what))
+        }
+
+        fn serialize_struct(
+            self,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeStruct, Error> {
+            Err(Error::UnsupportedType($what))
+        }
+
+        fn serialize_struct_variant(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeStructVariant, Error> {
+            Err(Error::UnsupportedType($what))
+        }
+    };
+}
+
+/// Serializes an element of a sequence, which must be a string or a byte.
+struct ElementSerializer;
+
+impl ser::Serializer for ElementSerializer {
+    type Ok = Element;
+    type Error = Error;
+    type SerializeSeq = Impossible<Element, Error>;
+    type SerializeTuple = Impossible<Element, Error>;
+    type SerializeTupleStruct = Impossible<Element, Error>;
+    type SerializeTupleVariant = Impossible<Element, Error>;
+    type SerializeMap = Impossible<Element, Error>;
+    type SerializeStruct = Impossible<Element, Error>;
+    type SerializeStructVariant = Impossible<Element, Error>;
+
+    fn serialize_u8(self, v: u8) -> Result<Element, Error> {
+        Ok(Element::Byte(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Element, Error> {
+        let s = U16CString::from_str(v).map_err(value::Error::from)?;
+        Ok(Element::String(s))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Element, Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Element, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Element, Error> {
+        value.serialize(self)
+    }
+
+    unsupported! { "a sequence of anything but strings or bytes";
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+    }
+
+    unsupported_compound!("a sequence of anything but strings or bytes");
+}
+
+/// Serializes a map key into a value or subkey name.
+struct NameSerializer;
+
+impl ser::Serializer for NameSerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_str(self, v: &str) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<String, Error> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _: bool) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_f32(self, _: f32) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_f64(self, _: f64) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<String, Error> {
+        Err(Error::KeyMustBeAString)
+    }
+
+    unsupported_compound!("a map key that is not a string");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hive;
+    use serde::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Window {
+        width: u32,
+        height: u32,
+    }
+
+    #[derive(Serialize)]
+    enum Theme {
+        Dark,
+    }
+
+    #[derive(Serialize)]
+    struct Config {
+        name: String,
+        enabled: bool,
+        offset: i32,
+        size: u64,
+        theme: Theme,
+        recent: Vec<String>,
+        #[serde(with = "bytes")]
+        blob: Vec<u8>,
+        missing: Option<u32>,
+        window: Window,
+        extra: BTreeMap<String, u32>,
+    }
+
+    mod bytes {
+        pub fn serialize<S: serde::Serializer>(v: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_bytes(v)
+        }
+    }
+
+    #[test]
+    fn serialize_struct() {
+        const KEY: &str = r"Test\registry-rust-crate-serialize";
+
+        let config = Config {
+            name: "registry".into(),
+            enabled: true,
+            offset: -1,
+            size: 1 << 40,
+            theme: Theme::Dark,
+            recent: vec!["a".into(), "b".into()],
+            blob: vec![1, 2, 3],
+            missing: None,
+            window: Window {
+                width: 640,
+                height: 480,
+            },
+            extra: vec![("Answer".to_string(), 42)].into_iter().collect(),
+        };
+
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        key.set_value("missing", &Data::U32(1)).unwrap();
+        to_key(&config, &key).unwrap();
+
+        assert_eq!(key.get_value::<String, _>("name").unwrap(), "registry");
+        assert_eq!(key.value("enabled").unwrap(), Data::U32(1));
+        assert_eq!(key.value("offset").unwrap(), Data::U32(u32::MAX));
+        assert_eq!(key.value("size").unwrap(), Data::U64(1 << 40));
+        assert_eq!(key.get_value::<String, _>("theme").unwrap(), "Dark");
+        assert_eq!(
+            key.get_value::<Vec<String>, _>("recent").unwrap(),
+            ["a", "b"]
+        );
+        assert_eq!(key.value("blob").unwrap(), Data::Binary(vec![1, 2, 3]));
+        assert!(key.value("missing").unwrap_err().is_not_found());
+
+        let window = key.open("window", Security::Read).unwrap();
+        assert_eq!(window.value("width").unwrap(), Data::U32(640));
+        assert_eq!(window.value("height").unwrap(), Data::U32(480));
+
+        let extra = key.open("extra", Security::Read).unwrap();
+        assert_eq!(extra.value("Answer").unwrap(), Data::U32(42));
+
+        assert!(matches!(
+            to_key(&42u32, &key),
+            Err(Error::UnsupportedType(_))
+        ));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+
+    #[test]
+    fn serialize_empty_seq() {
+        const KEY: &str = r"Test\registry-rust-crate-serialize-empty-seq";
+
+        let mut map = BTreeMap::new();
+        map.insert("empty", Vec::<String>::new());
+
+        let key = Hive::CurrentUser.create(KEY, Security::AllAccess).unwrap();
+        to_key(&map, &key).unwrap();
+        assert_eq!(key.value("empty").unwrap(), Data::Binary(vec![]));
+
+        Hive::CurrentUser.delete(KEY, true).unwrap();
+    }
+}
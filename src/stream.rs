@@ -0,0 +1,114 @@
+use std::io::{self, BufRead, Cursor, Read, Seek, SeekFrom, Write};
+
+use utfx::U16CString;
+use winapi::um::winnt::REG_BINARY;
+
+use crate::{value, RegKey};
+
+/// Reads the raw data of a value through `std::io::Read`, created with
+/// [`RegKey::open_value_reader`](struct.RegKey.html#method.open_value_reader).
+///
+/// The registry API has no way to read part of a value, so the data is fetched in one
+/// call. It is read straight into a byte buffer, which can be handed back with
+/// `into_buffer` and reused for the next value to avoid allocating per value.
+#[derive(Debug)]
+pub struct ValueReader {
+    ty: u32,
+    cursor: Cursor<Vec<u8>>,
+}
+
+impl ValueReader {
+    pub(crate) fn new(ty: u32, buf: Vec<u8>) -> ValueReader {
+        ValueReader {
+            ty,
+            cursor: Cursor::new(buf),
+        }
+    }
+
+    /// The raw type code of the value, such as `REG_BINARY`.
+    #[inline]
+    pub fn value_type(&self) -> u32 {
+        self.ty
+    }
+
+    /// The length of the value's data in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cursor.get_ref().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the buffer holding the value's data, for reuse.
+    #[inline]
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.cursor.into_inner()
+    }
+}
+
+impl Read for ValueReader {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.cursor.read(buf)
+    }
+}
+
+impl BufRead for ValueReader {
+    #[inline]
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.cursor.fill_buf()
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.cursor.consume(amt)
+    }
+}
+
+impl Seek for ValueReader {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.cursor.seek(pos)
+    }
+}
+
+/// Writes the data of a `REG_BINARY` value through `std::io::Write`, created with
+/// [`RegKey::create_value_writer`](struct.RegKey.html#method.create_value_writer).
+///
+/// As the registry can only set a value as a whole, data is buffered and written when
+/// `finish` is called. Dropping the writer without calling `finish` discards the data.
+#[derive(Debug)]
+pub struct ValueWriter<'a> {
+    regkey: &'a RegKey,
+    name: U16CString,
+    buf: Vec<u8>,
+}
+
+impl<'a> ValueWriter<'a> {
+    pub(crate) fn new(regkey: &'a RegKey, name: U16CString, buf: Vec<u8>) -> ValueWriter<'a> {
+        ValueWriter { regkey, name, buf }
+    }
+
+    /// Writes the buffered data to the value, returning the buffer for reuse.
+    pub fn finish(mut self) -> Result<Vec<u8>, value::Error> {
+        value::set_value_raw(self.regkey.handle, &self.name, REG_BINARY, &self.buf)?;
+        self.buf.clear();
+        Ok(self.buf)
+    }
+}
+
+impl Write for ValueWriter<'_> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use std::{backtrace::Backtrace, collections::BTreeMap, fmt::Display, sync::Mutex};
+
+use crate::RegKey;
+
+/// A `RegKey` handle that has not yet been closed, as returned by
+/// [`dump_open_handles`](fn.dump_open_handles.html).
+#[derive(Debug)]
+pub struct OpenHandle {
+    /// The key the handle was opened for, as it was displayed when opened.
+    pub key: String,
+    /// The backtrace of where the handle was opened.
+    pub backtrace: String,
+}
+
+impl Display for OpenHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} opened at:", self.key)?;
+        f.write_str(&self.backtrace)
+    }
+}
+
+struct Entry {
+    key: String,
+    backtrace: Backtrace,
+}
+
+static HANDLES: Mutex<BTreeMap<usize, Entry>> = Mutex::new(BTreeMap::new());
+
+fn handles() -> std::sync::MutexGuard<'static, BTreeMap<usize, Entry>> {
+    // A panic while holding the lock cannot leave the map inconsistent.
+    HANDLES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn track(key: &RegKey) {
+    let entry = Entry {
+        key: key.to_string(),
+        backtrace: Backtrace::force_capture(),
+    };
+    handles().insert(key.handle as usize, entry);
+}
+
+pub(crate) fn untrack(key: &RegKey) {
+    handles().remove(&(key.handle as usize));
+}
+
+/// Lists every `RegKey` handle that is currently open, with the backtrace of where
+/// it was opened, for diagnosing handle leaks.
+///
+/// Handles released with `RegKey::into_raw` are no longer tracked.
+pub fn dump_open_handles() -> Vec<OpenHandle> {
+    handles()
+        .values()
+        .map(|entry| OpenHandle {
+            key: entry.key.clone(),
+            backtrace: entry.backtrace.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_open_handles() {
+        let key = crate::Hive::CurrentUser
+            .open("SOFTWARE", crate::Security::Read)
+            .unwrap();
+        let handle = key.handle as usize;
+        assert!(handles().contains_key(&handle));
+        assert!(dump_open_handles()
+            .iter()
+            .any(|x| x.key == r"HKEY_CURRENT_USER\SOFTWARE"));
+
+        drop(key);
+        assert!(!handles().contains_key(&handle));
+    }
+}
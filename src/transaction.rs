@@ -0,0 +1,61 @@
+use std::{io, ptr::null_mut};
+
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::ktmw32::{CommitTransaction, CreateTransaction, RollbackTransaction};
+use winapi::um::winnt::HANDLE;
+
+/// A Kernel Transaction Manager transaction, for applying several registry changes atomically.
+///
+/// Keys opened or created with a transaction take part in it, as do all changes made through
+/// them. Changes are only visible outside the transaction once it has been committed, and
+/// dropping a transaction without committing it rolls it back.
+#[derive(Debug)]
+pub struct Transaction {
+    handle: HANDLE,
+}
+
+impl Transaction {
+    pub fn new() -> Result<Transaction, io::Error> {
+        let handle = unsafe { CreateTransaction(null_mut(), null_mut(), 0, 0, 0, 0, null_mut()) };
+
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Transaction { handle })
+    }
+
+    /// Commits all changes made as part of this transaction.
+    pub fn commit(self) -> Result<(), io::Error> {
+        let result = unsafe { CommitTransaction(self.handle) };
+
+        if result == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Discards all changes made as part of this transaction.
+    pub fn rollback(self) -> Result<(), io::Error> {
+        let result = unsafe { RollbackTransaction(self.handle) };
+
+        if result == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn as_handle(&self) -> HANDLE {
+        self.handle
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        // Closing an uncommitted transaction rolls it back.
+        unsafe { CloseHandle(self.handle) };
+    }
+}
@@ -1,13 +1,22 @@
 use std::{
     convert::{Infallible, TryFrom, TryInto},
+    ffi::{OsStr, OsString},
     fmt::{Debug, Display},
     io,
+    path::{Path, PathBuf},
     ptr::null_mut,
 };
 
-use utfx::U16CString;
+use utfx::{U16CStr, U16CString};
 use winapi::shared::minwindef::HKEY;
-use winapi::um::winreg::{RegDeleteValueW, RegQueryValueExW, RegSetValueExW};
+use winapi::shared::winerror::{
+    ERROR_CANTREAD, ERROR_MORE_DATA, ERROR_NO_MORE_ITEMS, ERROR_UNSUPPORTED_TYPE,
+};
+use winapi::um::processenv::ExpandEnvironmentStringsW;
+use winapi::um::winreg::{
+    RegDeleteKeyValueW, RegDeleteValueW, RegEnumValueW, RegGetValueW, RegQueryMultipleValuesW,
+    RegQueryValueExW, RegSetKeyValueW, RegSetValueExW, VALENTW,
+};
 
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
@@ -27,6 +36,7 @@ pub enum Error {
     #[error("Missing null terminator in string")]
     MissingNul(#[from] utfx::MissingNulError<u16>),
 
+    #[deprecated(note = "not used")]
     #[error("Missing null terminator in multi string")]
     MissingMultiNul,
 
@@ -36,6 +46,28 @@ pub enum Error {
     #[error("An unknown IO error occurred for given value name: '{0}'")]
     Unknown(String, #[source] io::Error),
 
+    #[error("Value '{0}' does not have a requested type")]
+    TypeMismatch(String, #[source] io::Error),
+
+    #[error("Value '{0}' already exists")]
+    AlreadyExists(String),
+
+    #[error("Invalid FILETIME for value '{0}'")]
+    InvalidFiletime(String),
+
+    #[error("Expected {expected} data, found {found:?}")]
+    UnexpectedType { expected: &'static str, found: Type },
+
+    #[error("Value '{0}' holds {1}, expected one of: {}", .2.join(", "))]
+    UnknownVariant(String, String, Vec<String>),
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[cfg(feature = "uuid")]
+    #[error("Value '{0}' is not a valid GUID")]
+    InvalidGuid(String, #[source] uuid::Error),
+
     #[deprecated(note = "not used")]
     #[error("Error determining required buffer size for value '{0}'")]
     BufferSize(String, #[source] io::Error),
@@ -46,17 +78,23 @@ pub enum Error {
 }
 
 impl Error {
-    #[cfg(test)]
     pub(crate) fn is_not_found(&self) -> bool {
-        match self {
-            Error::NotFound(_, _) => true,
-            _ => false,
-        }
+        matches!(self, Error::NotFound(..))
     }
 
-    fn from_code(code: i32, value_name: String) -> Self {
+    #[cfg(feature = "derive")]
+    pub(crate) fn not_found(value_name: &str) -> Self {
+        use winapi::shared::winerror::ERROR_FILE_NOT_FOUND;
+        Error::from_code(ERROR_FILE_NOT_FOUND as i32, value_name.to_string())
+    }
+
+    pub(crate) fn from_code(code: i32, value_name: String) -> Self {
         let err = std::io::Error::from_raw_os_error(code);
 
+        if code == ERROR_UNSUPPORTED_TYPE as i32 {
+            return Error::TypeMismatch(value_name, err);
+        }
+
         return match err.kind() {
             io::ErrorKind::NotFound => Error::NotFound(value_name, err),
             io::ErrorKind::PermissionDenied => Error::PermissionDenied(value_name, err),
@@ -71,9 +109,10 @@ impl From<Infallible> for Error {
     }
 }
 
+/// The type of a value's data, such as `REG_SZ` for `String`.
 #[repr(u32)]
-#[derive(Debug, Copy, Clone)]
-pub(crate) enum Type {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
     None = 0,
     String = 1,
     ExpandString = 2,
@@ -92,27 +131,51 @@ impl Type {
     const MAX: u32 = 11;
 }
 
+/// Whether [`RegKey::get_expand_string`](../struct.RegKey.html#method.get_expand_string)
+/// expands environment variables such as `%SystemRoot%`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Expand {
+    Yes,
+    No,
+}
+
+/// How [`RegKey::set_guid`](../struct.RegKey.html#method.set_guid) stores a GUID.
+#[cfg(feature = "uuid")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GuidFormat {
+    /// A `REG_SZ` value in the registry's usual form, such as
+    /// `{6B29FC40-CA47-1067-B31D-00DD010662DA}`.
+    Braced,
+    /// A 16-byte `REG_BINARY` value in the in-memory layout of a Windows `GUID`, where the
+    /// first three fields are little-endian.
+    Binary,
+}
+
 /// A type-safe wrapper around Windows Registry value data.
-#[derive(Clone)]
+///
+/// Types without a more specific representation, such as `REG_NONE`, symbolic link
+/// targets and the resource list types written by device drivers, keep their raw bytes
+/// so that they can be written back unchanged.
+#[derive(Clone, PartialEq, Eq)]
 pub enum Data {
-    None,
+    None(Vec<u8>),
     String(U16CString),
     ExpandString(U16CString),
     Binary(Vec<u8>),
     U32(u32),
     U32BE(u32),
-    Link,
+    Link(Vec<u8>),
     MultiString(Vec<U16CString>),
-    ResourceList,
-    FullResourceDescriptor,
-    ResourceRequirementsList,
+    ResourceList(Vec<u8>),
+    FullResourceDescriptor(Vec<u8>),
+    ResourceRequirementsList(Vec<u8>),
     U64(u64),
 }
 
 impl Debug for Data {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Data::None => f.write_str("None"),
+            Data::None(s) => write!(f, "None({:?})", s),
             Data::String(s) => {
                 write!(f, "String({:?})", s.to_string_lossy())
             }
@@ -122,13 +185,13 @@ impl Debug for Data {
             Data::Binary(s) => write!(f, "Binary({:?})", s),
             Data::U32(x) => write!(f, "U32({})", x),
             Data::U32BE(x) => write!(f, "U32BE({})", x),
-            Data::Link => f.write_str("Link"),
+            Data::Link(s) => write!(f, "Link({:?})", s),
             x @ Data::MultiString(_) => {
                 write!(f, "MultiString({})", x.to_string())
             }
-            Data::ResourceList => f.write_str("ResourceList"),
-            Data::FullResourceDescriptor => f.write_str("FullResourceDescriptor"),
-            Data::ResourceRequirementsList => f.write_str("ResourceRequirementsList"),
+            Data::ResourceList(s) => write!(f, "ResourceList({:?})", s),
+            Data::FullResourceDescriptor(s) => write!(f, "FullResourceDescriptor({:?})", s),
+            Data::ResourceRequirementsList(s) => write!(f, "ResourceRequirementsList({:?})", s),
             Data::U64(x) => write!(f, "U64({})", x),
         }
     }
@@ -137,7 +200,7 @@ impl Debug for Data {
 impl Display for Data {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Data::None => f.write_str("<None>"),
+            Data::None(_) => f.write_str("<None>"),
             Data::String(s) => f.write_str(&s.to_string_lossy()),
             Data::ExpandString(s) => f.write_str(&s.to_string_lossy()),
             Data::Binary(s) => write!(
@@ -150,55 +213,637 @@ impl Display for Data {
             ),
             Data::U32(x) => write!(f, "0x{:016x}", x),
             Data::U32BE(x) => write!(f, "0x{:016x}", x),
-            Data::Link => f.write_str("<Link>"),
+            Data::Link(_) => f.write_str("<Link>"),
             Data::MultiString(x) => f
                 .debug_list()
                 .entries(x.iter().map(|x| x.to_string_lossy()))
                 .finish(),
-            Data::ResourceList => f.write_str("<Resource List>"),
-            Data::FullResourceDescriptor => f.write_str("<Full Resource Descriptor>"),
-            Data::ResourceRequirementsList => f.write_str("<Resource Requirements List>"),
+            Data::ResourceList(_) => f.write_str("<Resource List>"),
+            Data::FullResourceDescriptor(_) => f.write_str("<Full Resource Descriptor>"),
+            Data::ResourceRequirementsList(_) => f.write_str("<Resource Requirements List>"),
             Data::U64(x) => write!(f, "0x{:032x}", x),
         }
     }
 }
 
 impl Data {
-    fn as_type(&self) -> Type {
+    /// The type of this data.
+    #[inline]
+    pub fn value_type(&self) -> Type {
+        self.as_type()
+    }
+
+    /// The string of a `String` or `ExpandString`.
+    pub fn as_str(&self) -> Option<&U16CStr> {
+        match self {
+            Data::String(x) | Data::ExpandString(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// The number of a `U32` or `U32BE`.
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            Data::U32(x) | Data::U32BE(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// The number of a `U64`, or of a `U32` or `U32BE` widened.
+    pub fn as_u64(&self) -> Option<u64> {
         match self {
-            Data::None => Type::None,
+            Data::U64(x) => Some(*x),
+            Data::U32(x) | Data::U32BE(x) => Some(*x as u64),
+            _ => None,
+        }
+    }
+
+    /// The bytes of `Binary` data, or of the types kept as raw bytes such as `None` and `Link`.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Data::None(x)
+            | Data::Binary(x)
+            | Data::Link(x)
+            | Data::ResourceList(x)
+            | Data::FullResourceDescriptor(x)
+            | Data::ResourceRequirementsList(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// The strings of a `MultiString`.
+    pub fn as_multi(&self) -> Option<&[U16CString]> {
+        match self {
+            Data::MultiString(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn unexpected(&self, expected: &'static str) -> Error {
+        Error::UnexpectedType {
+            expected,
+            found: self.as_type(),
+        }
+    }
+
+    pub(crate) fn as_type(&self) -> Type {
+        match self {
+            Data::None(_) => Type::None,
             Data::String(_) => Type::String,
             Data::ExpandString(_) => Type::ExpandString,
             Data::Binary(_) => Type::Binary,
             Data::U32(_) => Type::U32,
             Data::U32BE(_) => Type::U32BE,
-            Data::Link => Type::Link,
+            Data::Link(_) => Type::Link,
             Data::MultiString(_) => Type::MultiString,
-            Data::ResourceList => Type::ResourceList,
-            Data::FullResourceDescriptor => Type::FullResourceDescriptor,
-            Data::ResourceRequirementsList => Type::ResourceRequirementsList,
+            Data::ResourceList(_) => Type::ResourceList,
+            Data::FullResourceDescriptor(_) => Type::FullResourceDescriptor,
+            Data::ResourceRequirementsList(_) => Type::ResourceRequirementsList,
             Data::U64(_) => Type::U64,
         }
     }
 
     fn to_bytes(&self) -> Vec<u8> {
         match self {
-            Data::None => vec![],
+            Data::None(x) => x.to_vec(),
             Data::String(s) => string_to_utf16_byte_vec(s),
             Data::ExpandString(s) => string_to_utf16_byte_vec(s),
             Data::Binary(x) => x.to_vec(),
             Data::U32(x) => x.to_le_bytes().to_vec(),
             Data::U32BE(x) => x.to_be_bytes().to_vec(),
-            Data::Link => vec![],
+            Data::Link(x) => x.to_vec(),
             Data::MultiString(x) => multi_string_bytes(x),
-            Data::ResourceList => vec![],
-            Data::FullResourceDescriptor => vec![],
-            Data::ResourceRequirementsList => vec![],
+            Data::ResourceList(x) => x.to_vec(),
+            Data::FullResourceDescriptor(x) => x.to_vec(),
+            Data::ResourceRequirementsList(x) => x.to_vec(),
             Data::U64(x) => x.to_le_bytes().to_vec(),
         }
     }
 }
 
+/// Types that can be written as registry value data, used by
+/// [`RegKey::set_value`](../struct.RegKey.html#method.set_value).
+///
+/// Implement this for your own types to store them directly.
+pub trait ToRegValue {
+    fn to_reg_value(&self) -> Result<Data, Error>;
+}
+
+/// How closely the type of a value must match the type it is read as, used by
+/// [`RegKey::get_value_with_coercion`](../struct.RegKey.html#method.get_value_with_coercion).
+///
+/// `get_value` sits between the two: strings may be read from `REG_SZ` or `REG_EXPAND_SZ`
+/// and 64-bit numbers from `REG_DWORD` or `REG_QWORD`, but strings are never parsed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Coercion {
+    /// Only accepts the type's own registry type, such as `REG_DWORD` for a `u32`,
+    /// `REG_QWORD` for a `u64` and `REG_SZ` for a `String`.
+    Strict,
+    /// Also converts between `REG_SZ` and `REG_EXPAND_SZ`, between `REG_DWORD` and
+    /// `REG_QWORD` when the number fits, and between numbers and strings holding a decimal
+    /// or `0x` prefixed hexadecimal number.
+    Lenient,
+}
+
+/// Types that can be read from registry value data, used by
+/// [`RegKey::get_value`](../struct.RegKey.html#method.get_value).
+///
+/// Implementations should fail with `Error::TypeMismatch` for data of the wrong type.
+/// Its value name is filled in by `get_value`, so may be left empty.
+pub trait FromRegValue: Sized {
+    fn from_reg_value(data: Data) -> Result<Self, Error>;
+
+    /// Converts data with the given coercion policy. The default implementation ignores
+    /// the policy and calls `from_reg_value`.
+    #[inline]
+    fn from_reg_value_with(data: Data, _coercion: Coercion) -> Result<Self, Error> {
+        Self::from_reg_value(data)
+    }
+}
+
+impl Error {
+    /// A type mismatch for a value whose name is not known here.
+    #[inline]
+    pub fn unexpected_type() -> Self {
+        Error::from_code(ERROR_UNSUPPORTED_TYPE as i32, String::new())
+    }
+
+    pub(crate) fn with_value_name(self, value_name: &U16CStr) -> Self {
+        match self {
+            Error::TypeMismatch(name, err) if name.is_empty() => {
+                Error::TypeMismatch(value_name.to_string_lossy(), err)
+            }
+            e => e,
+        }
+    }
+}
+
+impl<T: ToRegValue + ?Sized> ToRegValue for &T {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        (**self).to_reg_value()
+    }
+}
+
+impl ToRegValue for Data {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        Ok(self.clone())
+    }
+}
+
+impl ToRegValue for u32 {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        Ok(Data::U32(*self))
+    }
+}
+
+impl ToRegValue for u64 {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        Ok(Data::U64(*self))
+    }
+}
+
+/// Stored as the two's complement bits of a `REG_DWORD`.
+impl ToRegValue for i32 {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        Ok(Data::U32(*self as u32))
+    }
+}
+
+/// Stored as the two's complement bits of a `REG_QWORD`.
+impl ToRegValue for i64 {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        Ok(Data::U64(*self as u64))
+    }
+}
+
+/// Booleans are stored as a `REG_DWORD` of 0 or 1.
+impl ToRegValue for bool {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        Ok(Data::U32(*self as u32))
+    }
+}
+
+impl ToRegValue for str {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        Ok(Data::String(U16CString::from_str(self)?))
+    }
+}
+
+impl ToRegValue for String {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        self.as_str().to_reg_value()
+    }
+}
+
+/// Converted to UTF-16 without going through UTF-8, so unpaired surrogates are kept.
+impl ToRegValue for OsStr {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        Ok(Data::String(U16CString::from_os_str(self)?))
+    }
+}
+
+impl ToRegValue for OsString {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        self.as_os_str().to_reg_value()
+    }
+}
+
+impl ToRegValue for Path {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        self.as_os_str().to_reg_value()
+    }
+}
+
+impl ToRegValue for PathBuf {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        self.as_os_str().to_reg_value()
+    }
+}
+
+impl ToRegValue for [u8] {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        Ok(Data::Binary(self.to_vec()))
+    }
+}
+
+impl ToRegValue for Vec<u8> {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        self.as_slice().to_reg_value()
+    }
+}
+
+impl ToRegValue for [String] {
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        let strings = self
+            .iter()
+            .map(U16CString::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Data::MultiString(strings))
+    }
+}
+
+impl ToRegValue for Vec<String> {
+    #[inline]
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        self.as_slice().to_reg_value()
+    }
+}
+
+impl ToRegValue for [&str] {
+    fn to_reg_value(&self) -> Result<Data, Error> {
+        let strings = self
+            .iter()
+            .map(U16CString::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Data::MultiString(strings))
+    }
+}
+
+impl FromRegValue for Data {
+    #[inline]
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        Ok(data)
+    }
+}
+
+/// Reads a number from numeric data or, for lenient coercion, a string holding one.
+fn lenient_number(data: &Data) -> Option<i128> {
+    match data {
+        Data::U32(x) | Data::U32BE(x) => Some(*x as i128),
+        Data::U64(x) => Some(*x as i128),
+        Data::String(x) | Data::ExpandString(x) => {
+            let s = x.to_string_lossy();
+            let s = s.trim();
+            match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+                Some(hex) => i128::from_str_radix(hex, 16).ok(),
+                None => s.parse().ok(),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn lenient<T: TryFrom<i128>>(data: &Data) -> Result<T, Error> {
+    lenient_number(data)
+        .and_then(|x| T::try_from(x).ok())
+        .ok_or_else(Error::unexpected_type)
+}
+
+impl FromRegValue for u32 {
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        match data {
+            Data::U32(x) | Data::U32BE(x) => Ok(x),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+
+    fn from_reg_value_with(data: Data, coercion: Coercion) -> Result<Self, Error> {
+        match coercion {
+            Coercion::Strict => Self::from_reg_value(data),
+            Coercion::Lenient => lenient(&data),
+        }
+    }
+}
+
+impl FromRegValue for u64 {
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        match data {
+            Data::U64(x) => Ok(x),
+            Data::U32(x) | Data::U32BE(x) => Ok(x as u64),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+
+    fn from_reg_value_with(data: Data, coercion: Coercion) -> Result<Self, Error> {
+        match (coercion, data) {
+            (_, Data::U64(x)) => Ok(x),
+            (Coercion::Lenient, data) => lenient(&data),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+}
+
+impl FromRegValue for i32 {
+    #[inline]
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        u32::from_reg_value(data).map(|x| x as i32)
+    }
+
+    fn from_reg_value_with(data: Data, coercion: Coercion) -> Result<Self, Error> {
+        match (coercion, data) {
+            (_, Data::U32(x)) | (_, Data::U32BE(x)) => Ok(x as i32),
+            (Coercion::Lenient, Data::U64(x)) => {
+                i32::try_from(x as i64).map_err(|_| Error::unexpected_type())
+            }
+            (Coercion::Lenient, data) => lenient(&data),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+}
+
+/// A `REG_DWORD` is sign-extended, as it is read as an `i32`.
+impl FromRegValue for i64 {
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        match data {
+            Data::U64(x) => Ok(x as i64),
+            Data::U32(x) | Data::U32BE(x) => Ok(x as i32 as i64),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+
+    fn from_reg_value_with(data: Data, coercion: Coercion) -> Result<Self, Error> {
+        match (coercion, data) {
+            (_, Data::U64(x)) => Ok(x as i64),
+            (Coercion::Lenient, Data::U32(x)) | (Coercion::Lenient, Data::U32BE(x)) => {
+                Ok(x as i32 as i64)
+            }
+            (Coercion::Lenient, data) => lenient(&data),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+}
+
+/// Any non-zero number is true. Strict coercion only accepts a `REG_DWORD`, while
+/// lenient coercion also accepts strings holding a number, `true` or `false`.
+impl FromRegValue for bool {
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        u64::from_reg_value(data).map(|x| x != 0)
+    }
+
+    fn from_reg_value_with(data: Data, coercion: Coercion) -> Result<Self, Error> {
+        match (coercion, data) {
+            (_, Data::U32(x)) | (_, Data::U32BE(x)) => Ok(x != 0),
+            (Coercion::Lenient, Data::String(x)) | (Coercion::Lenient, Data::ExpandString(x)) => {
+                match x.to_string_lossy().trim().to_ascii_lowercase().as_str() {
+                    "true" => Ok(true),
+                    "false" => Ok(false),
+                    _ => lenient::<i128>(&Data::String(x)).map(|x| x != 0),
+                }
+            }
+            (Coercion::Lenient, data) => lenient::<i128>(&data).map(|x| x != 0),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+}
+
+impl FromRegValue for String {
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        match data {
+            Data::String(x) | Data::ExpandString(x) => Ok(x.to_string()?),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+
+    fn from_reg_value_with(data: Data, coercion: Coercion) -> Result<Self, Error> {
+        match (coercion, data) {
+            (_, Data::String(x)) | (Coercion::Lenient, Data::ExpandString(x)) => Ok(x.to_string()?),
+            (Coercion::Lenient, Data::U32(x)) | (Coercion::Lenient, Data::U32BE(x)) => {
+                Ok(x.to_string())
+            }
+            (Coercion::Lenient, Data::U64(x)) => Ok(x.to_string()),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+}
+
+/// Decoded without going through UTF-8, so this never fails on unpaired surrogates.
+impl FromRegValue for OsString {
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        match data {
+            Data::String(x) | Data::ExpandString(x) => Ok(x.to_os_string()),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+
+    fn from_reg_value_with(data: Data, coercion: Coercion) -> Result<Self, Error> {
+        match (coercion, data) {
+            (Coercion::Strict, Data::ExpandString(_)) => Err(Error::unexpected_type()),
+            (_, data) => Self::from_reg_value(data),
+        }
+    }
+}
+
+impl FromRegValue for PathBuf {
+    #[inline]
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        OsString::from_reg_value(data).map(PathBuf::from)
+    }
+
+    #[inline]
+    fn from_reg_value_with(data: Data, coercion: Coercion) -> Result<Self, Error> {
+        OsString::from_reg_value_with(data, coercion).map(PathBuf::from)
+    }
+}
+
+impl FromRegValue for Vec<u8> {
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        match data {
+            Data::Binary(x) => Ok(x),
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+}
+
+impl FromRegValue for Vec<String> {
+    fn from_reg_value(data: Data) -> Result<Self, Error> {
+        match data {
+            Data::MultiString(x) => {
+                Ok(x.iter().map(|x| x.to_string()).collect::<Result<_, _>>()?)
+            }
+            _ => Err(Error::unexpected_type()),
+        }
+    }
+}
+
+impl TryFrom<Data> for String {
+    type Error = Error;
+
+    fn try_from(data: Data) -> Result<Self, Self::Error> {
+        match data {
+            Data::String(x) | Data::ExpandString(x) => Ok(x.to_string()?),
+            _ => Err(data.unexpected("string")),
+        }
+    }
+}
+
+impl TryFrom<Data> for u32 {
+    type Error = Error;
+
+    fn try_from(data: Data) -> Result<Self, Self::Error> {
+        data.as_u32()
+            .ok_or_else(|| data.unexpected("32-bit number"))
+    }
+}
+
+impl TryFrom<Data> for u64 {
+    type Error = Error;
+
+    fn try_from(data: Data) -> Result<Self, Self::Error> {
+        data.as_u64().ok_or_else(|| data.unexpected("number"))
+    }
+}
+
+impl TryFrom<Data> for Vec<u8> {
+    type Error = Error;
+
+    fn try_from(data: Data) -> Result<Self, Self::Error> {
+        match data {
+            Data::Binary(x)
+            | Data::Link(x)
+            | Data::ResourceList(x)
+            | Data::FullResourceDescriptor(x)
+            | Data::ResourceRequirementsList(x) => Ok(x),
+            _ => Err(data.unexpected("binary")),
+        }
+    }
+}
+
+impl TryFrom<Data> for Vec<String> {
+    type Error = Error;
+
+    fn try_from(data: Data) -> Result<Self, Self::Error> {
+        match data {
+            Data::MultiString(x) => {
+                Ok(x.iter().map(|x| x.to_string()).collect::<Result<_, _>>()?)
+            }
+            _ => Err(data.unexpected("multi string")),
+        }
+    }
+}
+
+/// How a variant of a [`RegEnum`](trait.RegEnum.html) is stored.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EnumRepr {
+    /// A `REG_SZ` value, matched case-insensitively when read.
+    String(&'static str),
+    /// A `REG_DWORD` value.
+    U32(u32),
+}
+
+impl Display for EnumRepr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnumRepr::String(s) => write!(f, "{:?}", s),
+            EnumRepr::U32(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+/// A Rust enum stored as a string or number value, read and written with
+/// [`RegKey::get_enum`](../struct.RegKey.html#method.get_enum) and
+/// [`RegKey::set_enum`](../struct.RegKey.html#method.set_enum).
+///
+/// ```ignore
+/// #[derive(Clone, Copy, PartialEq)]
+/// enum StartType { Auto, Manual, Disabled }
+///
+/// impl RegEnum for StartType {
+///     const MAPPING: &'static [(Self, EnumRepr)] = &[
+///         (StartType::Auto, EnumRepr::U32(2)),
+///         (StartType::Manual, EnumRepr::U32(3)),
+///         (StartType::Disabled, EnumRepr::U32(4)),
+///     ];
+/// }
+/// ```
+///
+/// A variant may appear more than once to accept aliases when reading, in which case it is
+/// written using its first entry.
+pub trait RegEnum: Sized + Copy + PartialEq + 'static {
+    const MAPPING: &'static [(Self, EnumRepr)];
+}
+
+pub(crate) fn query_enum<T: RegEnum>(base: HKEY, value_name: &U16CStr) -> Result<T, Error> {
+    let data = query_value(base, value_name)?;
+    let found = T::MAPPING.iter().find(|(_, repr)| match (repr, &data) {
+        (EnumRepr::String(s), Data::String(x)) => x.to_string_lossy().eq_ignore_ascii_case(s),
+        (EnumRepr::U32(n), Data::U32(x)) | (EnumRepr::U32(n), Data::U32BE(x)) => n == x,
+        _ => false,
+    });
+
+    if let Some((variant, _)) = found {
+        return Ok(*variant);
+    }
+
+    let data = match data {
+        Data::String(x) => format!("{:?}", x.to_string_lossy()),
+        Data::U32(x) | Data::U32BE(x) => x.to_string(),
+        _ => return Err(Error::type_mismatch(value_name)),
+    };
+    let allowed = T::MAPPING
+        .iter()
+        .map(|(_, repr)| repr.to_string())
+        .collect();
+    Err(Error::UnknownVariant(
+        value_name.to_string_lossy(),
+        data,
+        allowed,
+    ))
+}
+
+pub(crate) fn enum_data<T: RegEnum>(variant: &T) -> Option<Data> {
+    let (_, repr) = T::MAPPING.iter().find(|(x, _)| x == variant)?;
+    Some(match repr {
+        // Mappings are static strings, which are not expected to contain a nul.
+        EnumRepr::String(s) => Data::String(U16CString::from_str(s).ok()?),
+        EnumRepr::U32(x) => Data::U32(*x),
+    })
+}
+
 #[inline(always)]
 fn multi_string_bytes(s: &[U16CString]) -> Vec<u8> {
     let mut vec = s
@@ -219,23 +864,217 @@ fn string_to_utf16_byte_vec(s: &U16CString) -> Vec<u8> {
         .collect()
 }
 
-fn parse_wide_string_nul(vec: Vec<u16>) -> Result<U16CString, Error> {
-    Ok(U16CString::from_vec_with_nul(vec)?)
+/// Parses string data, failing with `Error::InvalidNul` if it has a nul before its end
+/// rather than losing the data after it.
+fn parse_wide_string_nul(mut vec: Vec<u16>) -> Result<U16CString, Error> {
+    // The terminator may be missing, including for zero-length data, or be followed by
+    // further nuls padding the data.
+    while vec.last() == Some(&0) {
+        vec.pop();
+    }
+    Ok(U16CString::new(vec)?)
 }
 
 fn parse_wide_multi_string(vec: Vec<u16>) -> Result<Vec<U16CString>, Error> {
-    let len = vec.len();
-    if vec[len - 1] != 0 || vec[len - 2] != 0 {
-        return Err(Error::MissingMultiNul);
+    // The list should end with the last string's terminator and then its own, but
+    // either or both may be missing.
+    let mut end = vec.len();
+    for _ in 0..2 {
+        if end > 0 && vec[end - 1] == 0 {
+            end -= 1;
+        }
+    }
+
+    if end == 0 {
+        return Ok(vec![]);
     }
 
-    (&vec[0..vec.len() - 2])
+    // Empty strings within the list are kept, as some tools write them.
+    (&vec[..end])
         .split(|x| *x == 0)
         .map(U16CString::new)
         .collect::<Result<Vec<_>, _>>()
         .map_err(Error::InvalidNul)
 }
 
+impl Error {
+    fn type_mismatch(value_name: &U16CStr) -> Self {
+        Error::from_code(ERROR_UNSUPPORTED_TYPE as i32, value_name.to_string_lossy())
+    }
+}
+
+pub(crate) fn query_multi_string<S>(base: HKEY, value_name: S) -> Result<Vec<String>, Error>
+where
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+{
+    let value_name = value_name.try_into().map_err(Into::into)?;
+
+    match query_value(base, &value_name)? {
+        Data::MultiString(x) => Ok(x.iter().map(|x| x.to_string()).collect::<Result<_, _>>()?),
+        _ => Err(Error::type_mismatch(&value_name)),
+    }
+}
+
+pub(crate) fn query_u32<S>(base: HKEY, value_name: S) -> Result<u32, Error>
+where
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+{
+    let value_name = value_name.try_into().map_err(Into::into)?;
+
+    match query_value(base, &value_name)? {
+        Data::U32(x) | Data::U32BE(x) => Ok(x),
+        _ => Err(Error::type_mismatch(&value_name)),
+    }
+}
+
+pub(crate) fn query_u64<S>(base: HKEY, value_name: S) -> Result<u64, Error>
+where
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+{
+    let value_name = value_name.try_into().map_err(Into::into)?;
+
+    match query_value(base, &value_name)? {
+        Data::U64(x) => Ok(x),
+        Data::U32(x) | Data::U32BE(x) => Ok(x as u64),
+        Data::Binary(x) if x.len() == 8 => Ok(u64::from_le_bytes(fixed_bytes(&x))),
+        _ => Err(Error::type_mismatch(&value_name)),
+    }
+}
+
+pub(crate) fn query_bool<S>(base: HKEY, value_name: S) -> Result<bool, Error>
+where
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+{
+    let value_name = value_name.try_into().map_err(Into::into)?;
+
+    match query_value(base, &value_name)? {
+        Data::U32(x) | Data::U32BE(x) => Ok(x != 0),
+        Data::U64(x) => Ok(x != 0),
+        Data::String(x) => match x.to_string_lossy().trim().to_ascii_lowercase().as_str() {
+            "1" | "true" => Ok(true),
+            "0" | "false" => Ok(false),
+            _ => Err(Error::type_mismatch(&value_name)),
+        },
+        _ => Err(Error::type_mismatch(&value_name)),
+    }
+}
+
+#[cfg(feature = "uuid")]
+pub(crate) fn query_guid<S>(base: HKEY, value_name: S) -> Result<uuid::Uuid, Error>
+where
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+{
+    let value_name = value_name.try_into().map_err(Into::into)?;
+
+    match query_value(base, &value_name)? {
+        Data::String(x) => uuid::Uuid::parse_str(x.to_string_lossy().trim())
+            .map_err(|e| Error::InvalidGuid(value_name.to_string_lossy(), e)),
+        Data::Binary(x) if x.len() == 16 => Ok(uuid::Uuid::from_bytes_le(fixed_bytes(&x))),
+        _ => Err(Error::type_mismatch(&value_name)),
+    }
+}
+
+#[cfg(feature = "uuid")]
+pub(crate) fn guid_data(guid: &uuid::Uuid, format: GuidFormat) -> Data {
+    match format {
+        GuidFormat::Braced => {
+            let s = guid.braced().to_string().to_uppercase();
+            // A formatted GUID never contains a nul.
+            Data::String(U16CString::from_str(s).unwrap())
+        }
+        GuidFormat::Binary => Data::Binary(guid.to_bytes_le().to_vec()),
+    }
+}
+
+/// Reads a raw `FILETIME` from an 8-byte `REG_BINARY` or a `REG_QWORD` value.
+pub(crate) fn query_filetime(base: HKEY, value_name: &U16CStr) -> Result<u64, Error> {
+    match query_value(base, value_name)? {
+        Data::U64(x) => Ok(x),
+        Data::Binary(x) if x.len() == 8 => Ok(u64::from_le_bytes(fixed_bytes(&x))),
+        _ => Err(Error::type_mismatch(value_name)),
+    }
+}
+
+/// Reads a `REG_SZ` or `REG_EXPAND_SZ` value, replacing invalid UTF-16 and a dangling
+/// odd byte with U+FFFD. The string ends at the first nul, or at the end of the data.
+pub(crate) fn query_string_lossy(base: HKEY, value_name: &U16CStr) -> Result<String, Error> {
+    let (ty, mut buf, len) = query_value_raw(base, value_name)?;
+
+    match Type::try_from(ty) {
+        Ok(Type::String) | Ok(Type::ExpandString) => {}
+        _ => return Err(Error::type_mismatch(value_name)),
+    }
+
+    buf.truncate(len / 2);
+    let end = buf.iter().position(|x| *x == 0);
+    let mut s = String::from_utf16_lossy(&buf[..end.unwrap_or(buf.len())]);
+    if end.is_none() && len % 2 == 1 {
+        s.push(char::REPLACEMENT_CHARACTER);
+    }
+    Ok(s)
+}
+
+pub(crate) fn query_expand_string<S>(
+    base: HKEY,
+    value_name: S,
+    expand: Expand,
+) -> Result<String, Error>
+where
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+{
+    let value_name = value_name.try_into().map_err(Into::into)?;
+
+    match query_value(base, &value_name)? {
+        Data::ExpandString(x) if expand == Expand::Yes => {
+            let expanded = expand_environment_strings(&x)
+                .map_err(|e| Error::Unknown(value_name.to_string_lossy(), e))?;
+            Ok(expanded.to_string()?)
+        }
+        Data::String(x) | Data::ExpandString(x) => Ok(x.to_string()?),
+        _ => Err(Error::type_mismatch(&value_name)),
+    }
+}
+
+fn expand_environment_strings(s: &U16CStr) -> Result<U16CString, io::Error> {
+    let mut buf: Vec<u16> = vec![0; s.len() + 1];
+
+    loop {
+        let len =
+            unsafe { ExpandEnvironmentStringsW(s.as_ptr(), buf.as_mut_ptr(), buf.len() as u32) };
+
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // The returned length includes the NUL terminator.
+        if len as usize <= buf.len() {
+            buf.truncate(len as usize - 1);
+            return Ok(unsafe { U16CString::from_vec_unchecked(buf) });
+        }
+
+        buf.resize(len as usize, 0);
+    }
+}
+
+pub(crate) fn set_multi_string<S, T>(base: HKEY, value_name: S, strings: &[T]) -> Result<(), Error>
+where
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+    T: AsRef<str>,
+{
+    let strings = strings
+        .iter()
+        .map(U16CString::from_str)
+        .collect::<Result<Vec<_>, _>>()?;
+    set_value(base, value_name, &Data::MultiString(strings))
+}
+
 #[inline]
 pub(crate) fn set_value<S>(base: HKEY, value_name: S, data: &Data) -> Result<(), Error>
 where
@@ -243,16 +1082,58 @@ where
     S::Error: Into<Error>,
 {
     let value_name = value_name.try_into().map_err(Into::into)?;
-    let raw_ty = data.as_type() as u32;
-    let vec = data.to_bytes();
+    set_value_raw(base, &value_name, data.as_type() as u32, &data.to_bytes())
+}
+
+#[inline]
+pub(crate) fn set_value_raw(
+    base: HKEY,
+    value_name: &U16CStr,
+    raw_ty: u32,
+    bytes: &[u8],
+) -> Result<(), Error> {
     let result = unsafe {
         RegSetValueExW(
             base,
             value_name.as_ptr(),
             0,
             raw_ty,
-            vec.as_ptr(),
-            vec.len() as u32,
+            bytes.as_ptr(),
+            bytes.len() as u32,
+        )
+    };
+
+    if result != 0 {
+        return Err(Error::from_code(result, value_name.to_string_lossy()));
+    }
+
+    Ok(())
+}
+
+#[inline]
+pub(crate) fn set_value_at<P, S>(
+    base: HKEY,
+    subkey: P,
+    value_name: S,
+    data: &Data,
+) -> Result<(), Error>
+where
+    P: TryInto<U16CString>,
+    P::Error: Into<Error>,
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+{
+    let subkey = subkey.try_into().map_err(Into::into)?;
+    let value_name = value_name.try_into().map_err(Into::into)?;
+    let bytes = data.to_bytes();
+    let result = unsafe {
+        RegSetKeyValueW(
+            base,
+            subkey.as_ptr(),
+            value_name.as_ptr(),
+            data.as_type() as u32,
+            bytes.as_ptr() as *const _,
+            bytes.len() as u32,
         )
     };
 
@@ -279,6 +1160,128 @@ where
     Ok(())
 }
 
+#[inline]
+pub(crate) fn delete_value_at<P, S>(base: HKEY, subkey: P, value_name: S) -> Result<(), Error>
+where
+    P: TryInto<U16CString>,
+    P::Error: Into<Error>,
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+{
+    let subkey = subkey.try_into().map_err(Into::into)?;
+    let value_name = value_name.try_into().map_err(Into::into)?;
+    let result = unsafe { RegDeleteKeyValueW(base, subkey.as_ptr(), value_name.as_ptr()) };
+
+    if result != 0 {
+        return Err(Error::from_code(result, value_name.to_string_lossy()));
+    }
+
+    Ok(())
+}
+
+/// Value names are limited to 16,383 characters, plus the NUL terminator.
+const MAX_VALUE_NAME_LEN: usize = 16_384;
+
+pub(crate) fn clear_values(base: HKEY) -> Result<(), Error> {
+    let mut name = vec![0u16; MAX_VALUE_NAME_LEN];
+
+    loop {
+        // Deleting a value shifts the indices of the rest, so always take the first.
+        let mut name_len = name.len() as u32;
+        let result = unsafe {
+            RegEnumValueW(
+                base,
+                0,
+                name.as_mut_ptr(),
+                &mut name_len,
+                null_mut(),
+                null_mut(),
+                null_mut(),
+                null_mut(),
+            )
+        };
+
+        if result == ERROR_NO_MORE_ITEMS as i32 {
+            return Ok(());
+        }
+
+        if result != 0 {
+            return Err(Error::from_code(result, String::new()));
+        }
+
+        name[name_len as usize] = 0;
+        let result = unsafe { RegDeleteValueW(base, name.as_ptr()) };
+
+        if result != 0 {
+            let name = String::from_utf16_lossy(&name[..name_len as usize]);
+            return Err(Error::from_code(result, name));
+        }
+    }
+}
+
+/// The total size in bytes of the data of every value of a key.
+pub(crate) fn total_data_len(base: HKEY) -> Result<u64, Error> {
+    let mut name = vec![0u16; MAX_VALUE_NAME_LEN];
+    let mut total = 0u64;
+
+    for index in 0.. {
+        let mut name_len = name.len() as u32;
+        let mut data_len = 0u32;
+        let result = unsafe {
+            RegEnumValueW(
+                base,
+                index,
+                name.as_mut_ptr(),
+                &mut name_len,
+                null_mut(),
+                null_mut(),
+                null_mut(),
+                &mut data_len,
+            )
+        };
+
+        if result == ERROR_NO_MORE_ITEMS as i32 {
+            break;
+        }
+
+        if result != 0 {
+            return Err(Error::from_code(result, String::new()));
+        }
+
+        total += data_len as u64;
+    }
+
+    Ok(total)
+}
+
+#[inline]
+pub(crate) fn value_exists<S>(base: HKEY, value_name: S) -> Result<bool, Error>
+where
+    S: TryInto<U16CString>,
+    S::Error: Into<Error>,
+{
+    let value_name = value_name.try_into().map_err(Into::into)?;
+    let result = unsafe {
+        RegQueryValueExW(
+            base,
+            value_name.as_ptr(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+        )
+    };
+
+    if result == 0 {
+        return Ok(true);
+    }
+
+    match Error::from_code(result, value_name.to_string_lossy()) {
+        e if e.is_not_found() => Ok(false),
+        e => Err(e),
+    }
+}
+
 #[inline]
 pub(crate) fn query_value<S>(base: HKEY, value_name: S) -> Result<Data, Error>
 where
@@ -286,6 +1289,151 @@ where
     S::Error: Into<Error>,
 {
     let value_name = value_name.try_into().map_err(Into::into)?;
+    let (ty, buf, len) = query_value_raw(base, &value_name)?;
+    parse_value_type_data(ty, buf, len)
+}
+
+/// Reads a value of the given subkey using `RegGetValueW` with the given `RRF_*` flags.
+pub(crate) fn get_value(
+    base: HKEY,
+    subkey: &U16CStr,
+    value_name: &U16CStr,
+    flags: u32,
+) -> Result<Data, Error> {
+    let mut sz: u32 = 0;
+
+    // Get the required buffer size first
+    let result = unsafe {
+        RegGetValueW(
+            base,
+            subkey.as_ptr(),
+            value_name.as_ptr(),
+            flags,
+            null_mut(),
+            null_mut(),
+            &mut sz,
+        )
+    };
+
+    if result != 0 {
+        return Err(Error::from_code(result, value_name.to_string_lossy()));
+    }
+
+    let mut ty = 0u32;
+
+    // Expanded strings may not fit the size reported for the data as stored.
+    loop {
+        let mut buf: Vec<u16> = vec![0u16; (sz / 2 + sz % 2) as usize];
+
+        let result = unsafe {
+            RegGetValueW(
+                base,
+                subkey.as_ptr(),
+                value_name.as_ptr(),
+                flags,
+                &mut ty,
+                buf.as_mut_ptr() as *mut _,
+                &mut sz,
+            )
+        };
+
+        if result == ERROR_MORE_DATA as i32 {
+            continue;
+        }
+
+        if result != 0 {
+            return Err(Error::from_code(result, value_name.to_string_lossy()));
+        }
+
+        buf.truncate((sz / 2 + sz % 2) as usize);
+        return parse_value_type_data(ty, buf, sz as usize);
+    }
+}
+
+/// Reads several values at once using `RegQueryMultipleValuesW`, which reads them
+/// atomically with respect to other writers.
+pub(crate) fn query_values(base: HKEY, value_names: &[U16CString]) -> Result<Vec<Data>, Error> {
+    if value_names.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut entries: Vec<VALENTW> = value_names
+        .iter()
+        .map(|name| VALENTW {
+            ve_valuename: name.as_ptr() as *mut _,
+            ve_valuelen: 0,
+            ve_valueptr: 0,
+            ve_type: 0,
+        })
+        .collect();
+    // Always pass a buffer, as a null one only queries the total size, which would leave
+    // the entries unread if every value is empty.
+    let mut buf: Vec<u16> = vec![0u16; 1];
+
+    loop {
+        let mut sz = (buf.len() * 2) as u32;
+        let result = unsafe {
+            RegQueryMultipleValuesW(
+                base,
+                entries.as_mut_ptr(),
+                entries.len() as u32,
+                buf.as_mut_ptr(),
+                &mut sz,
+            )
+        };
+
+        // The values may grow between calls, so retry until the buffer is large enough.
+        if result == ERROR_MORE_DATA as i32 {
+            buf = vec![0u16; (sz / 2 + sz % 2) as usize];
+            continue;
+        }
+
+        if result == ERROR_CANTREAD as i32 {
+            let names = value_names
+                .iter()
+                .map(|x| x.to_string_lossy())
+                .collect::<Vec<_>>();
+            let err = io::Error::from_raw_os_error(result);
+            return Err(Error::NotFound(names.join(", "), err));
+        }
+
+        if result != 0 {
+            return Err(Error::from_code(result, String::new()));
+        }
+
+        break;
+    }
+
+    let base_ptr = buf.as_ptr() as usize;
+    let buf = u16_to_u8_vec(buf);
+    entries
+        .iter()
+        .map(|entry| {
+            if entry.ve_valuelen == 0 {
+                return parse_value_type_data(entry.ve_type, vec![], 0);
+            }
+
+            // Each value's data is at an offset within the buffer, which may not be
+            // aligned to a `u16`, so copy it out byte by byte.
+            let start = entry.ve_valueptr - base_ptr;
+            let bytes = &buf[start..start + entry.ve_valuelen as usize];
+            let data = bytes
+                .chunks(2)
+                .map(|x| u16::from_le_bytes([x[0], *x.get(1).unwrap_or(&0)]))
+                .collect();
+            parse_value_type_data(entry.ve_type, data, bytes.len())
+        })
+        .collect()
+}
+
+/// Queries the raw type and data of a value, and the length of the data in bytes. The
+/// data is returned as a `u16` buffer so that it is suitably aligned for parsing as a
+/// wide string.
+#[inline]
+pub(crate) fn query_value_raw(
+    base: HKEY,
+    value_name: &U16CStr,
+) -> Result<(u32, Vec<u16>, usize), Error> {
     let mut sz: u32 = 0;
 
     // Get the required buffer size first
@@ -324,52 +1472,124 @@ where
         return Err(Error::from_code(result, value_name.to_string_lossy()));
     }
 
-    parse_value_type_data(ty, buf)
+    buf.truncate((sz / 2 + sz % 2) as usize);
+    Ok((ty, buf, sz as usize))
+}
+
+/// Queries a value's raw type code and data length in bytes, without reading its data.
+pub(crate) fn query_value_info(base: HKEY, value_name: &U16CStr) -> Result<(u32, usize), Error> {
+    let mut ty = 0u32;
+    let mut sz = 0u32;
+
+    let result = unsafe {
+        RegQueryValueExW(
+            base,
+            value_name.as_ptr(),
+            null_mut(),
+            &mut ty,
+            null_mut(),
+            &mut sz,
+        )
+    };
+
+    if result != 0 {
+        return Err(Error::from_code(result, value_name.to_string_lossy()));
+    }
+
+    Ok((ty, sz as usize))
 }
 
-pub fn u16_to_u8_vec(mut vec: Vec<u16>) -> Vec<u8> {
-    unsafe {
-        let capacity = vec.capacity();
-        let len = vec.len();
-        let ptr = vec.as_mut_ptr();
-        std::mem::forget(vec);
-        Vec::from_raw_parts(ptr as *mut u8, len, capacity)
+/// Reads a value's raw bytes into `buf`, reusing its allocation, and returns its type code.
+pub(crate) fn query_value_into(
+    base: HKEY,
+    value_name: &U16CStr,
+    buf: &mut Vec<u8>,
+) -> Result<u32, Error> {
+    let mut ty = 0u32;
+
+    loop {
+        let capacity = buf.capacity();
+        buf.resize(capacity, 0);
+        let mut sz = capacity as u32;
+        let ptr = if capacity == 0 {
+            null_mut()
+        } else {
+            buf.as_mut_ptr()
+        };
+
+        let result = unsafe {
+            RegQueryValueExW(base, value_name.as_ptr(), null_mut(), &mut ty, ptr, &mut sz)
+        };
+
+        // With a null buffer, success only reports the required size.
+        if result == 0 && (capacity != 0 || sz == 0) {
+            buf.truncate(sz as usize);
+            return Ok(ty);
+        }
+
+        if result != 0 && result != ERROR_MORE_DATA as i32 {
+            buf.clear();
+            return Err(Error::from_code(result, value_name.to_string_lossy()));
+        }
+
+        buf.clear();
+        buf.reserve(sz as usize);
     }
 }
 
+pub fn u16_to_u8_vec(vec: Vec<u16>) -> Vec<u8> {
+    u16_slice_to_u8_vec(&vec)
+}
+
+/// Converts to bytes with a single allocation, unlike collecting each `u16`'s bytes.
+pub(crate) fn u16_slice_to_u8_vec(slice: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(slice.len() * 2);
+    for x in slice {
+        bytes.extend_from_slice(&x.to_le_bytes());
+    }
+    bytes
+}
+
 #[inline(always)]
-pub(crate) fn parse_value_type_data(ty: u32, buf: Vec<u16>) -> Result<Data, Error> {
+pub(crate) fn parse_value_type_data(ty: u32, mut buf: Vec<u16>, len: usize) -> Result<Data, Error> {
     let ty = Type::try_from(ty).map_err(|_| Error::UnhandledType(ty))?;
+    buf.truncate(len / 2 + len % 2);
 
     match ty {
-        Type::None => return Ok(Data::None),
         Type::String => return parse_wide_string_nul(buf).map(Data::String),
         Type::ExpandString => return parse_wide_string_nul(buf).map(Data::ExpandString),
-        Type::Link => return Ok(Data::Link),
         Type::MultiString => return parse_wide_multi_string(buf).map(Data::MultiString),
-        Type::ResourceList => return Ok(Data::ResourceList),
-        Type::FullResourceDescriptor => return Ok(Data::FullResourceDescriptor),
-        Type::ResourceRequirementsList => return Ok(Data::ResourceRequirementsList),
         _ => {}
     }
 
-    let buf = u16_to_u8_vec(buf);
+    // The buffer may be padded to a whole number of `u16`s.
+    let mut buf = u16_to_u8_vec(buf);
+    buf.truncate(len);
 
     match ty {
+        Type::None => Ok(Data::None(buf)),
         Type::Binary => Ok(Data::Binary(buf)),
-        Type::U32 => Ok(Data::U32(u32::from_le_bytes([
-            buf[0], buf[1], buf[2], buf[3],
-        ]))),
-        Type::U32BE => Ok(Data::U32BE(u32::from_be_bytes([
-            buf[0], buf[1], buf[2], buf[3],
-        ]))),
-        Type::U64 => Ok(Data::U64(u64::from_le_bytes([
-            buf[0], buf[1], buf[2], buf[3], buf[4], buf[5], buf[6], buf[7],
-        ]))),
+        Type::U32 => Ok(Data::U32(u32::from_le_bytes(fixed_bytes(&buf)))),
+        Type::U32BE => Ok(Data::U32BE(u32::from_be_bytes(fixed_bytes(&buf)))),
+        Type::U64 => Ok(Data::U64(u64::from_le_bytes(fixed_bytes(&buf)))),
+        Type::Link => Ok(Data::Link(buf)),
+        Type::ResourceList => Ok(Data::ResourceList(buf)),
+        Type::FullResourceDescriptor => Ok(Data::FullResourceDescriptor(buf)),
+        Type::ResourceRequirementsList => Ok(Data::ResourceRequirementsList(buf)),
         _ => unreachable!(),
     }
 }
 
+/// Copies the bytes of a fixed size number, padding short data with zeroes rather than
+/// panicking, as the registry does not enforce the size of numeric values.
+#[inline(always)]
+fn fixed_bytes<const N: usize>(buf: &[u8]) -> [u8; N] {
+    let mut bytes = [0u8; N];
+    let len = buf.len().min(N);
+    bytes[..len].copy_from_slice(&buf[..len]);
+    bytes
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error("Invalid or unknown type value: {0:#x}")]
 pub struct TryIntoTypeError(u32);